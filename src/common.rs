@@ -1,35 +1,800 @@
 use anyhow;
-use clap::{Args, Parser, ValueEnum};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, ValueEnum};
 use serde::{Deserialize, Serialize};
 use std::{
-    fmt::{Display, Error},
-    path::PathBuf,
+    fmt::Display,
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
+/// Table of CSS named colors used by [`RGB::nearest_name`].
+mod named_colors;
+
+/// Errors returned by the image analysis, the cache and the parsing of colors.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::{Centrality, ColorSchemeError, RGB};
+/// assert!(matches!("#zzzzzz".parse::<RGB>(), Err(ColorSchemeError::InvalidHex(_))));
+/// assert!(matches!("middle".parse::<Centrality>(), Err(ColorSchemeError::InvalidCentrality(_))));
+/// ```
+#[derive(Debug, thiserror::Error)]
+pub enum ColorSchemeError {
+    /// A file could not be read.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// The image could not be decoded.
+    #[error("could not decode image: {0}")]
+    ImageDecode(#[from] image::ImageError),
+    /// The pixels of the image are in a color space that cannot be converted to RGB.
+    #[error("unsupported color space {0}, convert the image to RGB before analyzing it")]
+    UnsupportedColorSpace(String),
+    /// The file is not in a known image format.
+    #[error("Inputted file is not an image: {0}")]
+    NotAnImage(String),
+    /// No image path was given to analyze.
+    #[error("no image path was given")]
+    MissingImage,
+    /// The image has no pixels to analyze.
+    #[error("image contains no pixels")]
+    EmptyImage,
+    /// Every pixel of the image is more transparent than the alpha threshold.
+    #[error("image contains no opaque pixels")]
+    NoOpaquePixels,
+    /// A string is not a hexadecimal RGB value or `rgb()` color, or one of its channels is out of range.
+    #[error("{0:?} is not a valid RGB value. Example: #FFFFFF")]
+    InvalidHex(String),
+    /// A string is not the name of a [`Centrality`].
+    #[error("unknown centrality {0:?}")]
+    InvalidCentrality(String),
+    /// A string is not the name of an [`Interpolation`].
+    #[error("unknown interpolation {0:?}")]
+    InvalidInterpolation(String),
+    /// The path is not valid unicode and cannot be stored in the cache.
+    #[error("path is not valid unicode: {}", .0.display())]
+    InvalidPath(PathBuf),
+    /// The cache database could not be queried.
+    #[error("database error: {0}")]
+    Database(#[from] sqlite::Error),
+    /// The record is not in the cache.
+    #[error("record not found in the cache")]
+    NotFound,
+    /// A value stored in the cache or read from the file system is out of range.
+    #[error("value out of range: {0}")]
+    OutOfRange(#[from] std::num::TryFromIntError),
+    /// The image at a URL could not be downloaded.
+    #[error("could not download {url}: {reason}")]
+    Download {
+        /// URL of the image.
+        url: String,
+        /// Why the download failed.
+        reason: String,
+    },
+    /// The XDG base directories could not be found.
+    #[error(transparent)]
+    Xdg(#[from] xdg::BaseDirectoriesError),
+    /// A string is not a [`Crop`] rectangle.
+    #[error("{0:?} is not a valid crop, expected x,y,width,height. Example: 0,0,1920,40")]
+    InvalidCrop(String),
+    /// The [`Crop`] rectangle is not inside of the image.
+    #[error("crop {crop} is outside of the {width}x{height} image")]
+    CropOutOfBounds {
+        /// The rectangle that was given.
+        crop: Crop,
+        /// Width of the image.
+        width: u32,
+        /// Height of the image.
+        height: u32,
+    },
+    /// The [`Cli::palette_from_file`] is not a list of colors.
+    #[error("{} is not a valid palette: {reason}", .path.display())]
+    InvalidPalette {
+        /// Path of the palette file.
+        path: PathBuf,
+        /// Why the palette could not be read.
+        reason: String,
+    },
+    /// The analysis of the image did not finish within [`Cli::timeout`].
+    #[error("analysis did not finish within {0:?}")]
+    Timeout(std::time::Duration),
+    /// The single thread of a [`Cli::deterministic`] analysis could not be started.
+    #[error("could not start the analysis thread: {0}")]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
+}
+
+impl ColorSchemeError {
+    /// Get the name of the variant of the error.
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::{ColorSchemeError, RGB};
+    /// assert_eq!(ColorSchemeError::NotFound.kind(), "NotFound");
+    /// assert_eq!("#zzzzzz".parse::<RGB>().unwrap_err().kind(), "InvalidHex");
+    /// ```
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ColorSchemeError::Io(_) => "Io",
+            ColorSchemeError::ImageDecode(_) => "ImageDecode",
+            ColorSchemeError::UnsupportedColorSpace(_) => "UnsupportedColorSpace",
+            ColorSchemeError::NotAnImage(_) => "NotAnImage",
+            ColorSchemeError::MissingImage => "MissingImage",
+            ColorSchemeError::EmptyImage => "EmptyImage",
+            ColorSchemeError::NoOpaquePixels => "NoOpaquePixels",
+            ColorSchemeError::InvalidHex(_) => "InvalidHex",
+            ColorSchemeError::InvalidCentrality(_) => "InvalidCentrality",
+            ColorSchemeError::InvalidInterpolation(_) => "InvalidInterpolation",
+            ColorSchemeError::InvalidPath(_) => "InvalidPath",
+            ColorSchemeError::Database(_) => "Database",
+            ColorSchemeError::NotFound => "NotFound",
+            ColorSchemeError::OutOfRange(_) => "OutOfRange",
+            ColorSchemeError::Download { .. } => "Download",
+            ColorSchemeError::Xdg(_) => "Xdg",
+            ColorSchemeError::InvalidCrop(_) => "InvalidCrop",
+            ColorSchemeError::CropOutOfBounds { .. } => "CropOutOfBounds",
+            ColorSchemeError::InvalidPalette { .. } => "InvalidPalette",
+            ColorSchemeError::Timeout(_) => "Timeout",
+            ColorSchemeError::ThreadPool(_) => "ThreadPool",
+        }
+    }
+}
+
+/// Error of a failed run, printed to stderr as JSON if [`Cli::error_format`] is [`ErrorFormat::Json`].
+///
+/// # Examples
+/// ```
+/// use clap::Parser;
+/// use color_scheme_generator::{common::{Cli, ErrorReport}, generate};
+/// let args = Cli::parse_from(["color_scheme_generator", "/nonexistent/wallpaper.png"]);
+/// let error = anyhow::Error::from(generate(&args).unwrap_err());
+/// let report = serde_json::to_value(ErrorReport::from(&error)).unwrap();
+/// assert_eq!(report["kind"], "Io");
+/// assert!(report["error"].as_str().unwrap().contains("No such file or directory"));
+///
+/// let report = ErrorReport::from(&anyhow::anyhow!("no image path was given"));
+/// assert_eq!(report.kind, "Other");
+/// ```
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ErrorReport {
+    /// Message of the error.
+    pub error: String,
+    /// Name of the [`ColorSchemeError`] variant of the error, `Other` if it is not a [`ColorSchemeError`].
+    pub kind: String,
+}
+
+impl From<&anyhow::Error> for ErrorReport {
+    fn from(error: &anyhow::Error) -> Self {
+        ErrorReport {
+            error: format!("{error:#}"),
+            kind: error
+                .downcast_ref::<ColorSchemeError>()
+                .map_or("Other", ColorSchemeError::kind)
+                .to_string(),
+        }
+    }
+}
+
+/// Options of [`Cli`] that can be given instead of [`Cli::image`].
+const IMAGE_REPLACEMENTS: [&str; 7] = [
+    "clear_cache",
+    "list_cache",
+    "batch",
+    "generate_completions",
+    "version_json",
+    "seed_color",
+    "palette_from_file",
+];
+
 /// Command line argument Struct used by clap to parse CLI arguments.
-#[derive(Parser, Serialize, Deserialize)]
+#[derive(Clone, Parser, Serialize, Deserialize)]
 #[command(version, about, long_about = None)]
 pub struct Cli {
-    /// Path to the image file.
-    #[arg(required = true, index = 1)]
-    pub image: PathBuf,
+    /// Path to the image file, or a directory of images to output the color theme of each one as a JSON array, or an http:// or https:// URL of an image.
+    #[arg(index = 1, required_unless_present_any = IMAGE_REPLACEMENTS)]
+    pub image: Option<PathBuf>,
     /// Measure of centrality to be used to analyze an image.
     #[arg(short, long, default_value_t = Centrality::Median)]
     pub centrality: Centrality,
     /// Output format for color themes.
     #[arg(short, long, default_value_t = OutputFormat::JSON)]
     pub serialization_format: OutputFormat,
+    /// Output the color theme by filling the placeholders of a template instead of using the serialization format, e.g. "bar={bar} ws={workspace} text={text}".
+    /// Every color is also available by its index as {colorN}, {hexN} without the leading #, and {rN}, {gN} and {bN} for its sub pixels.
+    #[arg(long)]
+    pub template: Option<String>,
+    /// Name of the scheme in the base16 output, the file name of the image by default.
+    #[arg(long)]
+    pub scheme_name: Option<String>,
+    /// Omit the leading '#' of every color in the text output.
+    #[arg(long, default_value_t = false)]
+    pub text_no_hash: bool,
+    /// Use this color as every text color of the output instead of the black or white that contrasts most with the color behind it.
+    #[arg(long, value_name = "COLOR")]
+    pub text_color: Option<RGB>,
+    /// Opacity of every color of the polybar output from 0 for transparent to 255 for opaque, written as `#AARRGGBB`.
+    #[arg(long, value_name = "ALPHA")]
+    pub alpha: Option<u8>,
+    /// Opacity of the bar color, the first color, from 0 for transparent to 255 for opaque. Written as `#rrggbbaa` by the text and css outputs and as an `alpha` field by the json and yaml outputs.
+    #[arg(long, value_name = "ALPHA")]
+    pub bar_alpha: Option<u8>,
+    /// Opacity of the workspace color, the second color, written like `--bar-alpha`.
+    #[arg(long, value_name = "ALPHA")]
+    pub workspace_alpha: Option<u8>,
+    /// Opacity of the text color of the css output, written like `--bar-alpha`.
+    #[arg(long, value_name = "ALPHA")]
+    pub text_alpha: Option<u8>,
+    /// Longest edge in pixels the image is downsampled to before being analyzed. 0 analyzes the full resolution image.
+    #[arg(long, default_value_t = 512)]
+    pub sample_size: u32,
+    /// Analyze only every Nth pixel of the image, after it is downsampled to --sample-size. 1 analyzes every pixel.
+    /// The centrality is an approximation from the sampled pixels, which is close for the average and prevalent centralities on large images.
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub sample_rate: u32,
+    /// Minimum alpha value for a pixel to be analyzed. Pixels that are more transparent are ignored.
+    #[arg(long, default_value_t = 1)]
+    pub alpha_threshold: u8,
+    /// Minimum HSL lightness in percent for a pixel to be analyzed. Darker pixels are ignored.
+    #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0 ..= 100))]
+    pub min_lightness: u8,
+    /// Maximum HSL lightness in percent for a pixel to be analyzed. Lighter pixels are ignored.
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u8).range(0 ..= 100))]
+    pub max_lightness: u8,
+    /// Analyze the image as it is stored instead of rotating and flipping it as given by its EXIF orientation.
+    #[arg(long, default_value_t = false)]
+    pub ignore_orientation: bool,
+    /// Analyze the decoded image in chunks of rows instead of collecting a copy of its pixels first.
+    /// The whole image is still decoded, so this only saves the memory of the copy of the downsampled pixels.
+    /// Only the average, median, prevalent and mode centralities can be streamed, the median is estimated from a random sample of the pixels.
+    #[arg(long, default_value_t = false)]
+    pub streaming: bool,
+    /// Analyze the image on a single thread so the same image always gives byte identical output.
+    /// Averages are summed in the same order on every run instead of in the order the threads finish.
+    #[arg(long, default_value_t = false)]
+    pub deterministic: bool,
+    /// Average the pixels in linear light instead of averaging their gamma encoded sRGB values.
+    #[arg(long, default_value_t = false)]
+    pub gamma: bool,
+    /// Seconds the analysis of an image may take before giving up with an error, e.g. 0.5. The analysis is not limited by default.
+    /// The analysis stops once the time is up, but the decoding of the image runs to completion before the time is checked.
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    pub timeout: Option<std::time::Duration>,
+    /// Only analyze the pixels inside of this region of the image.
+    #[arg(long)]
+    pub region: Option<Region>,
+    /// Only analyze the pixels inside of this rectangle of the image, given in pixels as x,y,width,height from the top left corner, e.g. 0,0,1920,40.
+    #[arg(long, value_name = "X,Y,WIDTH,HEIGHT", conflicts_with = "region")]
+    pub crop: Option<Crop>,
+    /// Frame of an animated GIF, PNG or WebP image that is analyzed, or the average of every frame.
+    #[arg(long, default_value_t = Frame::First)]
+    pub frame: Frame,
 
     #[command(flatten)]
     pub color_themes: ColorThemeOption,
-    /// Level of logging
-    #[arg(short, long, default_value_t = 0)]
+    /// Level of logging: 0 for errors, 1 for warnings, 2 for information about the cache, 3 for timings of the analysis and 4 for everything.
+    #[arg(short, long, default_value_t = 1)]
     pub log_level: usize,
+    /// Only log errors, regardless of `--log-level`.
+    #[arg(short, long, default_value_t = false)]
+    pub quiet: bool,
+    /// Format that the error is printed to stderr in if the application fails.
+    #[arg(long, default_value_t = ErrorFormat::Text)]
+    pub error_format: ErrorFormat,
+    /// Write the output to the given path instead of stdout.
+    #[arg(short, long, value_hint = clap::ValueHint::FilePath)]
+    pub output: Option<PathBuf>,
+    /// Write an image of the color theme to the given path in addition to the normal output.
+    #[arg(long, value_hint = clap::ValueHint::FilePath)]
+    pub swatch: Option<PathBuf>,
+    /// Print every color as a block of 24-bit ANSI color before the normal output. Ignored when stdout is not a terminal.
+    #[arg(long, default_value_t = false)]
+    pub preview: bool,
+    /// Print the swatch of the color theme as an inline sixel image before the normal output, if the terminal is known to support sixel.
+    /// Falls back to the blocks of `--preview` on other terminals. Ignored when stdout is not a terminal.
+    #[arg(long, default_value_t = false)]
+    pub preview_image: bool,
+    /// Path of the cache database, or `:memory:` for a cache that is discarded on exit. Defaults to the `COLOR_SCHEME_GENERATOR_CACHE` environment variable, else `cache.db` inside of XDG_CACHE_HOME.
+    #[arg(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+    pub cache_path: Option<PathBuf>,
+    /// Remove every cached color theme and the images that were piped into stdin or downloaded, and exit.
+    #[arg(long, default_value_t = false)]
+    pub clear_cache: bool,
+    /// Output every cached image with its centrality and number of cached color themes and exit.
+    #[arg(long, default_value_t = false)]
+    pub list_cache: bool,
+    /// Times an operation on the cache is retried if the cache is locked by another instance of the application.
+    #[arg(long, value_name = "COUNT", default_value_t = crate::database::DEFAULT_RETRIES)]
+    pub db_retries: u8,
+    /// Output whether the color theme is cached, the effective centrality and color theme options and the cache path as JSON and exit without generating the color theme.
+    #[arg(long, default_value_t = false)]
+    pub explain: bool,
+    /// Output the histograms of the sub pixels and the most prevalent colors of the analyzed pixels as JSON and exit without generating the color theme.
+    #[arg(long, default_value_t = false)]
+    pub histogram: bool,
+    /// Keep running and output the color theme again every time the contents of the image change, one record per line.
+    #[arg(long, default_value_t = false)]
+    pub watch: bool,
+    /// Read newline separated image paths from stdin and output the color theme of each one as a JSON array.
+    #[arg(long, default_value_t = false)]
+    pub batch: bool,
+    /// Print the completion script for the given shell and exit.
+    #[arg(long, hide = true, value_name = "SHELL")]
+    pub generate_completions: Option<crate::completions::Shell>,
+    /// Output the version, the supported serialization formats and centralities as JSON and exit.
+    #[arg(long, default_value_t = false)]
+    pub version_json: bool,
+    /// When the image path is a directory, also analyze the images inside of its subdirectories.
+    #[arg(short, long, default_value_t = false)]
+    pub recursive: bool,
+    /// Generate the color theme from this color instead of analyzing an image, e.g. "#ff0000" or "rgb(255, 0, 0)".
+    #[arg(long, value_name = "COLOR")]
+    pub seed_color: Option<RGB>,
+    /// Read the color theme from a JSON or YAML palette file, as output by the json and yaml formats, instead of analyzing an image.
+    /// The colors are adjusted by the options such as --invert and --saturation and output in the serialization format.
+    #[arg(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath, conflicts_with = "seed_color")]
+    pub palette_from_file: Option<PathBuf>,
+    /// Invert every color of the color theme before it is output.
+    #[arg(long, default_value_t = false)]
+    pub invert: bool,
+    /// Rotate the hue of every color of the color theme by 180 degrees before it is output.
+    #[arg(long, default_value_t = false)]
+    pub complement_all: bool,
+    /// Shift every color of the color theme toward warm, more red and less blue, for positive values or toward cool, more blue and less red, for negative values before it is output.
+    #[arg(long, default_value_t = 0, allow_negative_numbers = true, value_parser = clap::value_parser!(i8).range(-100..=100))]
+    pub temperature: i8,
+    /// Scale the HSL saturation of every color of the color theme by this percentage before it is output, e.g. 100 doubles it and -100 removes it.
+    #[arg(long, default_value_t = 0, allow_negative_numbers = true, value_parser = clap::value_parser!(i8).range(-100..=100))]
+    pub saturation: i8,
+    /// Output the color theme as it is seen with this color vision deficiency.
+    #[arg(long)]
+    pub cvd: Option<Cvd>,
+    /// Instead of simulating `--cvd`, change the lightness of the colors so they stay distinguishable with it.
+    #[arg(long, default_value_t = false, requires = "cvd")]
+    pub cvd_safe: bool,
+    /// Order of the colors of the color theme in the output.
+    #[arg(long, default_value_t = Sort::None)]
+    pub sort: Sort,
+    /// Lighten or darken the workspace color until its WCAG contrast ratio against the bar color is at least this value, from 1 to 21.
+    #[arg(long, value_name = "RATIO")]
+    pub min_contrast: Option<f64>,
+    /// Annotate every color selected by the centrality with the fraction of the analyzed pixels it stands for, in the JSON, YAML and text outputs. Only the prevalent, mode, k-means and median cut centralities count pixels, other colors are n/a.
+    #[arg(long, default_value_t = false)]
+    pub percentage: bool,
+    /// Output only the color of the image with the highest saturation times the amount of pixels it covers, instead of a color theme.
+    #[arg(long, default_value_t = false)]
+    pub accent_only: bool,
+    /// Remove every color of the color theme that is closer than `--dedup-threshold` to an earlier color before it is output.
+    #[arg(long, default_value_t = false)]
+    pub strip_duplicates: bool,
+    /// CIE76 distance in the CIELAB color space below which `--strip-duplicates` considers two colors duplicates.
+    #[arg(
+        long,
+        default_value_t = 2.3,
+        value_name = "DISTANCE",
+        requires = "strip_duplicates"
+    )]
+    pub dedup_threshold: f64,
+}
+
+impl Cli {
+    /// Parse the command line arguments, using the options of the config file as the defaults.
+    ///
+    /// # Notes
+    /// The config file is a subset of TOML, see [`read_config`]. Every option is the name of a field of [`Cli`] whose value becomes
+    /// the default of that option, so options given on the command line take precedence and are checked as if the config file was empty.
+    /// An option of the config file that conflicts with an option given on the command line, such as [`Cli::region`] and [`Cli::crop`], is ignored.
+    /// Options such as [`Cli::seed_color`] replace the image in the config file too.
+    /// A flag set to true in the config file cannot be turned off on the command line.
+    ///
+    /// # Errors
+    /// Will error if the config file has invalid syntax, options or values, or if the arguments are invalid in which case the error is a [`clap::Error`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::{Centrality, Cli, RGB};
+    /// let config = "# Defaults\ncentrality = \"kmeans\"\nsample_size = 128 # half of the default\npreview = true\n";
+    /// let args = Cli::parse_with_config(["color_scheme_generator", "img.png"], config).unwrap();
+    /// assert!(args.centrality == Centrality::Kmeans);
+    /// assert_eq!(args.sample_size, 128);
+    /// assert!(args.preview);
+    ///
+    /// let args = Cli::parse_with_config(["color_scheme_generator", "img.png", "-c", "average"], config).unwrap();
+    /// assert!(args.centrality == Centrality::Average);
+    ///
+    /// let config = "seed_color = \"#ff0000\" # red\n";
+    /// let args = Cli::parse_with_config(["color_scheme_generator", "--triadic"], config).unwrap();
+    /// assert_eq!(args.seed_color, Some(RGB { red: 255, green: 0, blue: 0 }));
+    ///
+    /// // Options of the config file that conflict with the command line are ignored.
+    /// let args = Cli::parse_with_config(["color_scheme_generator", "img.png", "--crop", "0,0,1,1"], "region = \"center\"").unwrap();
+    /// assert!(args.region.is_none() && args.crop.is_some());
+    /// let args = Cli::parse_with_config(["color_scheme_generator", "--palette-from-file", "p.json"], config).unwrap();
+    /// assert_eq!(args.seed_color, None);
+    /// // Options of the config file do not need the options they require on the command line.
+    /// assert!(Cli::parse_with_config(["color_scheme_generator", "img.png"], "dedup_threshold = 5").is_ok());
+    /// assert!(Cli::parse_with_config(["color_scheme_generator", "img.png", "--dedup-threshold", "5"], "").is_err());
+    ///
+    /// for config in ["unknown = 1", "sample_size 1", "sample_size = \"many\"", "seed_color = #00ff00", "centrality = kmeans", "[colors]\nbar = 1"] {
+    ///     assert!(Cli::parse_with_config(["color_scheme_generator", "img.png"], config).is_err(), "{config}");
+    /// }
+    /// ```
+    pub fn parse_with_config<I, T>(args: I, config: &str) -> anyhow::Result<Cli>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let args = args.into_iter().map(Into::into).collect::<Vec<String>>();
+        let config = parse_config(config)?;
+        let original = Cli::command();
+        for (line, option, value) in &config {
+            let arg = original
+                .get_arguments()
+                .find(|a| a.get_id() == option.as_str() && a.get_long().is_some())
+                .ok_or(anyhow::anyhow!(
+                    "config line {line}: unknown option {option}"
+                ))?;
+            let long = arg.get_long().unwrap_or_default().to_string();
+            clap::Command::new(APP_NAME)
+                .arg(
+                    clap::Arg::new(option.clone())
+                        .long(long.clone())
+                        .value_parser(arg.get_value_parser().clone()),
+                )
+                .try_get_matches_from([APP_NAME.to_string(), format!("--{long}={value}")])
+                .map_err(|e| {
+                    let error = e.to_string();
+                    let error = error.lines().next().unwrap_or_default();
+                    anyhow::anyhow!(
+                        "config line {line}: {}",
+                        error.trim_start_matches("error: ")
+                    )
+                })?;
+        }
+        let with_defaults = |config: &[&(usize, String, String)]| {
+            let replaces_image = config.iter().any(|(_, option, value)| {
+                IMAGE_REPLACEMENTS.contains(&option.as_str()) && value != "false"
+            });
+            let command = config
+                .iter()
+                .fold(Cli::command(), |command, (_, option, value)| {
+                    command.mut_arg(option, |a| a.default_value(value))
+                });
+            match replaces_image {
+                // Arg::required does not reset required_unless_present_any, so the image is rebuilt without it.
+                true => command.mut_arg("image", |a| {
+                    clap::Arg::new("image")
+                        .index(1)
+                        .help(a.get_help().cloned().unwrap_or_default())
+                        .value_hint(a.get_value_hint())
+                        .value_parser(a.get_value_parser().clone())
+                }),
+                false => command,
+            }
+        };
+        let matches =
+            with_defaults(&config.iter().collect::<Vec<_>>()).try_get_matches_from(&args)?;
+        let given = |id: &clap::Id| {
+            matches.value_source(id.as_str()) == Some(clap::parser::ValueSource::CommandLine)
+        };
+        let config = config
+            .iter()
+            .filter(|(_, option, _)| {
+                !original.get_arguments().any(|a| {
+                    given(a.get_id())
+                        && (a.get_id() == option.as_str()
+                            || original
+                                .get_arg_conflicts_with(a)
+                                .iter()
+                                .any(|c| c.get_id() == option.as_str())
+                            || original
+                                .get_arguments()
+                                .filter(|c| c.get_id() == option.as_str())
+                                .any(|c| {
+                                    original
+                                        .get_arg_conflicts_with(c)
+                                        .iter()
+                                        .any(|c| c.get_id() == a.get_id())
+                                }))
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(Cli::from_arg_matches(
+            &with_defaults(&config).try_get_matches_from(&args)?,
+        )?)
+    }
+
+    /// Get the arguments with the defaults that depend on other options applied.
+    ///
+    /// # Notes
+    /// If no color scheme option is given the quadratic color scheme is generated, see [`ColorThemeOption::is_default`].
+    /// Tetratic and blends need two colors, so with a centrality that selects a single color the centrality is switched
+    /// to [`Centrality::Prevalent`] with a warning. Applying it again changes nothing.
+    ///
+    /// # Examples
+    /// ```
+    /// # use clap::Parser;
+    /// # use color_scheme_generator::common::{Centrality, Cli};
+    /// let args = Cli::parse_from(["color_scheme_generator", "img.png"]).resolved();
+    /// assert!(args.color_themes.quadratic);
+    /// let args = Cli::parse_from(["color_scheme_generator", "img.png", "--tetratic", "-c", "average"]).resolved();
+    /// assert!(args.centrality == Centrality::Prevalent && !args.color_themes.quadratic);
+    /// let args = Cli::parse_from(["color_scheme_generator", "img.png", "--blends", "3", "-c", "kmeans"]).resolved();
+    /// assert!(args.centrality == Centrality::Kmeans);
+    /// ```
+    pub fn resolved(&self) -> Cli {
+        let mut cli = self.clone();
+        if (cli.color_themes.tetratic || cli.color_themes.blends > 0)
+            && !matches!(
+                cli.centrality,
+                Centrality::Prevalent
+                    | Centrality::Kmeans
+                    | Centrality::Mode
+                    | Centrality::MedianCut
+            )
+        {
+            log::warn!("Incompatible centrality argument. Switching to Prevalent.");
+            cli.centrality = Centrality::Prevalent
+        }
+        if cli.color_themes.is_default() {
+            cli.color_themes.quadratic = true;
+        }
+        cli
+    }
+
+    /// Get the verbosity of the log, which is 0 for errors only if [`Cli::quiet`] is given and [`Cli::log_level`] otherwise.
+    ///
+    /// # Examples
+    /// ```
+    /// # use clap::Parser;
+    /// # use color_scheme_generator::common::Cli;
+    /// assert_eq!(Cli::parse_from(["color_scheme_generator", "image.png", "--log-level", "4"]).verbosity(), 4);
+    /// assert_eq!(Cli::parse_from(["color_scheme_generator", "image.png", "--log-level", "4", "--quiet"]).verbosity(), 0);
+    /// ```
+    pub fn verbosity(&self) -> usize {
+        match self.quiet {
+            true => 0,
+            false => self.log_level,
+        }
+    }
+
+    /// Get the logger of the application, which logs the records of the crate to stderr at [`Cli::verbosity`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use clap::Parser;
+    /// # use color_scheme_generator::common::Cli;
+    /// # use color_scheme_generator::theme_calculation::generate_color_theme;
+    /// # use std::sync::Mutex;
+    /// // Record what the logger of the application would write.
+    /// struct Capture(Mutex<(Option<stderrlog::StdErrLog>, Vec<String>)>);
+    /// impl log::Log for Capture {
+    ///     fn enabled(&self, metadata: &log::Metadata) -> bool {
+    ///         self.0.lock().unwrap().0.as_ref().is_some_and(|logger| logger.enabled(metadata))
+    ///     }
+    ///     fn log(&self, record: &log::Record) {
+    ///         if self.enabled(record.metadata()) {
+    ///             self.0.lock().unwrap().1.push(record.args().to_string());
+    ///         }
+    ///     }
+    ///     fn flush(&self) {}
+    /// }
+    /// static CAPTURE: Capture = Capture(Mutex::new((None, vec![])));
+    /// log::set_logger(&CAPTURE).unwrap();
+    /// log::set_max_level(log::LevelFilter::Trace);
+    /// let path = std::env::temp_dir().join("color_scheme_generator_quiet.png");
+    /// image::RgbImage::from_pixel(4, 4, image::Rgb([128, 128, 128])).save(&path).unwrap();
+    ///
+    /// // The warnings of the centrality fallback and of no pixel being within the lightness bounds are only logged without --quiet.
+    /// for quiet in [false, true] {
+    ///     let mut args = vec!["color_scheme_generator", path.to_str().unwrap(), "-c", "median", "--tetratic", "--min-lightness", "90"];
+    ///     args.extend(quiet.then_some("--quiet"));
+    ///     let args = Cli::parse_from(args);
+    ///     *CAPTURE.0.lock().unwrap() = (Some(args.logger()), vec![]);
+    ///     let args = args.resolved();
+    ///     generate_color_theme(&args).unwrap();
+    ///     let logs = std::mem::take(&mut CAPTURE.0.lock().unwrap().1);
+    ///     assert_eq!(logs.iter().any(|l| l == "Incompatible centrality argument. Switching to Prevalent."), !quiet);
+    ///     assert_eq!(logs.iter().any(|l| l.starts_with("No pixels are within the lightness bounds")), !quiet);
+    /// }
+    /// ```
+    pub fn logger(&self) -> stderrlog::StdErrLog {
+        let mut logger = stderrlog::new();
+        logger
+            .module(env!("CARGO_CRATE_NAME"))
+            .verbosity(self.verbosity());
+        logger
+    }
+
+    /// Get the options that change which pixels of [`Cli::image`] are analyzed, as they are stored in the cache.
+    ///
+    /// # Notes
+    /// Color themes are cached by the path of the image, [`Cli::centrality`], these options and every field of [`ColorThemeOption`],
+    /// so changing any of them generates the color theme again instead of reusing a cached one.
+    /// The options are [`Cli::sample_size`], [`Cli::sample_rate`], [`Cli::alpha_threshold`], [`Cli::min_lightness`], [`Cli::max_lightness`],
+    /// [`Cli::ignore_orientation`], [`Cli::region`], [`Cli::crop`], [`Cli::frame`], [`Cli::streaming`], [`Cli::deterministic`] and [`Cli::gamma`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use clap::Parser;
+    /// # use color_scheme_generator::common::Cli;
+    /// let args = Cli::parse_from(["color_scheme_generator", "img.png"]);
+    /// assert_eq!(args.analysis_parameters(), "sample_size=512 sample_rate=1 alpha_threshold=1 min_lightness=0 max_lightness=100 ignore_orientation=false region=all crop=all frame=first streaming=false deterministic=false gamma=false");
+    /// let args = Cli::parse_from(["color_scheme_generator", "img.png", "--region", "top"]);
+    /// assert!(args.analysis_parameters().contains("region=top"));
+    /// let args = Cli::parse_from(["color_scheme_generator", "img.png", "--crop", "0,0,1920,40"]);
+    /// assert!(args.analysis_parameters().contains("crop=0,0,1920,40"));
+    /// ```
+    pub fn analysis_parameters(&self) -> String {
+        format!(
+            "sample_size={} sample_rate={} alpha_threshold={} min_lightness={} max_lightness={} ignore_orientation={} region={} crop={} frame={} streaming={} deterministic={} gamma={}",
+            self.sample_size,
+            self.sample_rate,
+            self.alpha_threshold,
+            self.min_lightness,
+            self.max_lightness,
+            self.ignore_orientation,
+            self.region.map_or("all".to_string(), |r| r.to_string()),
+            self.crop.map_or("all".to_string(), |c| c.to_string()),
+            self.frame,
+            self.streaming,
+            self.deterministic,
+            self.gamma
+        )
+    }
+
+    /// Get the path of the cache database: [`Cli::cache_path`] if given, else the [`CACHE_PATH_VAR`](crate::database::CACHE_PATH_VAR)
+    /// environment variable if set, else [`database::cache_path`](crate::database::cache_path).
+    ///
+    /// # Errors
+    /// Will error if neither is given and the cache directory cannot be created.
+    ///
+    /// # Examples
+    /// ```
+    /// # use clap::Parser;
+    /// use color_scheme_generator::{common::Cli, generate};
+    /// let cache_path = std::env::temp_dir().join("color_scheme_generator_cache_path.db");
+    /// # let _ = std::fs::remove_file(&cache_path);
+    /// let image = std::env::temp_dir().join("color_scheme_generator_cache_path.png");
+    /// image::RgbImage::from_pixel(4, 4, image::Rgb([200, 30, 30])).save(&image).unwrap();
+    /// let args = Cli::parse_from(["color_scheme_generator", image.to_str().unwrap(), "--cache-path", cache_path.to_str().unwrap()]);
+    /// assert_eq!(args.database_path().unwrap(), cache_path);
+    /// generate(&args).unwrap();
+    /// assert!(cache_path.exists());
+    ///
+    /// let args = Cli::parse_from(["color_scheme_generator", image.to_str().unwrap(), "--cache-path", ":memory:"]);
+    /// generate(&args).unwrap();
+    ///
+    /// std::env::set_var("COLOR_SCHEME_GENERATOR_CACHE", ":memory:");
+    /// let args = Cli::parse_from(["color_scheme_generator", image.to_str().unwrap()]);
+    /// assert_eq!(args.database_path().unwrap().to_str(), Some(":memory:"));
+    /// ```
+    pub fn database_path(&self) -> Result<PathBuf, ColorSchemeError> {
+        match (
+            &self.cache_path,
+            std::env::var_os(crate::database::CACHE_PATH_VAR),
+        ) {
+            (Some(path), _) => Ok(path.clone()),
+            (None, Some(path)) if !path.is_empty() => Ok(PathBuf::from(path)),
+            _ => crate::database::cache_path(),
+        }
+    }
 }
 
-#[derive(Args, Serialize, Deserialize)]
-#[group(multiple = false)]
+/// Read the `config.toml` inside of XDG_CONFIG_HOME, a missing config file is empty.
+///
+/// # Notes
+/// Only the subset of TOML needed for options is supported: one `option = value` pair per line, where the value is a string in
+/// double or single quotes, a number or a boolean, and comments starting with `#`. Tables, arrays and dotted keys are rejected.
+///
+/// # Errors
+/// Will error if the config file exists but cannot be read.
+pub fn read_config() -> anyhow::Result<String> {
+    let xdg_dirs = xdg::BaseDirectories::with_prefix(APP_NAME)?;
+    match xdg_dirs.find_config_file("config.toml") {
+        Some(path) => Ok(std::fs::read_to_string(path)?),
+        None => Ok(String::new()),
+    }
+}
+
+/// Parse a positive amount of seconds, such as `2` or `0.5`, into a [`std::time::Duration`].
+fn parse_seconds(seconds: &str) -> Result<std::time::Duration, String> {
+    seconds
+        .parse::<f64>()
+        .ok()
+        .filter(|s| *s > 0.0)
+        .and_then(|s| std::time::Duration::try_from_secs_f64(s).ok())
+        .ok_or(format!("{seconds:?} is not a positive amount of seconds"))
+}
+
+/// Get the line number, option and value as a string of every `option = value` line of a config file in the TOML subset of [`read_config`].
+fn parse_config(config: &str) -> anyhow::Result<Vec<(usize, String, String)>> {
+    config
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim()))
+        .filter(|(_, l)| !l.is_empty() && !l.starts_with('#'))
+        .map(|(i, l)| {
+            if l.starts_with('[') {
+                anyhow::bail!("config line {i}: tables are not supported, expected option = value");
+            }
+            let (option, value) = l
+                .split_once('=')
+                .ok_or(anyhow::anyhow!("config line {i}: expected option = value"))?;
+            let option = option.trim();
+            if option.is_empty()
+                || !option
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                anyhow::bail!("config line {i}: invalid option {option:?}, dotted and quoted keys are not supported");
+            }
+            let (value, rest) = parse_config_value(value.trim_start())
+                .map_err(|e| anyhow::anyhow!("config line {i}: {e}"))?;
+            let rest = rest.trim();
+            if !rest.is_empty() && !rest.starts_with('#') {
+                anyhow::bail!("config line {i}: unexpected {rest:?} after the value");
+            }
+            Ok((i, option.replace('-', "_"), value))
+        })
+        .collect()
+}
+
+/// Parse the TOML string, number or boolean at the start of a value, returning it as a string along with the rest of the line.
+fn parse_config_value(value: &str) -> Result<(String, &str), String> {
+    if let Some(literal) = value.strip_prefix('\'') {
+        let (literal, rest) = literal
+            .split_once('\'')
+            .ok_or("unterminated string".to_string())?;
+        return Ok((literal.to_string(), rest));
+    }
+    let Some(basic) = value.strip_prefix('"') else {
+        let (token, rest) = value.split_at(value.find('#').unwrap_or(value.len()));
+        let token = token.trim();
+        let number = token.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-')
+            && token.replace('_', "").parse::<f64>().is_ok();
+        return match matches!(token, "true" | "false") || number {
+            true => Ok((token.trim_start_matches('+').replace('_', ""), rest)),
+            false => Err(format!(
+                "expected a quoted string, a number or a boolean, found {token:?}"
+            )),
+        };
+    };
+    let mut string = String::new();
+    let mut chars = basic.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((string, &basic[i + 1..])),
+            '\\' => {
+                let escaped = match chars.next().map(|(_, c)| c) {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some(len @ ('u' | 'U')) => {
+                        let digits = (0..if len == 'u' { 4 } else { 8 })
+                            .filter_map(|_| chars.next().map(|(_, c)| c))
+                            .collect::<String>();
+                        u32::from_str_radix(&digits, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or(format!("invalid unicode escape \\{len}{digits}"))?
+                    }
+                    other => return Err(format!("invalid escape {other:?}")),
+                };
+                string.push(escaped);
+            }
+            c => string.push(c),
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+/// Color schemes to generate from the colors selected by the centrality.
+///
+/// # Notes
+/// Options can be combined, see [`crate::theme_calculation::gamut::color_theme`] for the order of the resulting colors.
+/// Tetratic and blends need two colors so they are only compatible with centralities that select more than one color,
+/// [`Cli::resolved`] switches the centrality to [`Centrality::Prevalent`] otherwise.
+#[derive(Clone, PartialEq, Eq, Hash, Args, Serialize, Deserialize)]
 pub struct ColorThemeOption {
     /// Make color selected by the centrality darker.
     #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0 ..= 100))]
@@ -76,6 +841,51 @@ pub struct ColorThemeOption {
     /// Number of colors, based on two colors selected by the centrality, interpolated together.
     #[arg(long, default_value_t = 0)]
     pub blends: u8,
+    /// Color space the blends are interpolated in.
+    #[arg(long, default_value_t = Interpolation::Lab)]
+    pub interpolation: Interpolation,
+    /// Number of colors, based on the color selected by the centrality, equally spaced around the color wheel.
+    #[arg(long, default_value_t = 0)]
+    pub hue_steps: u8,
+    /// Number of colors selected by the centrality. The colors after the first two are appended to the color theme as accent colors.
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u8).range(1..))]
+    pub palette_size: u8,
+    /// Make the color selected by the centrality the first color of the color theme, if the color scheme does not already start with it.
+    #[arg(long, default_value_t = false)]
+    pub include_seed: bool,
+    /// Choose the bar and workspace colors as the two most distinct of the colors selected by the centrality instead of the two first ones.
+    #[arg(long, default_value_t = false)]
+    pub dominant_pair: bool,
+}
+
+impl ColorThemeOption {
+    /// Check if no color scheme option is given, in which case [`Cli::resolved`] generates the quadratic color scheme.
+    ///
+    /// # Examples
+    /// ```
+    /// # use clap::Parser;
+    /// # use color_scheme_generator::common::Cli;
+    /// assert!(Cli::parse_from(["color_scheme_generator", "img.png", "--palette-size", "3"]).color_themes.is_default());
+    /// assert!(!Cli::parse_from(["color_scheme_generator", "img.png", "--shades", "3"]).color_themes.is_default());
+    /// ```
+    pub fn is_default(&self) -> bool {
+        self.darker == 0
+            && self.lighter == 0
+            && !self.complementary
+            && !self.contrast
+            && self.hue_offset == 0
+            && !self.triadic
+            && !self.quadratic
+            && !self.tetratic
+            && !self.analogous
+            && !self.split_complementary
+            && self.monochromatic == 0
+            && self.shades == 0
+            && self.tints == 0
+            && self.tones == 0
+            && self.blends == 0
+            && self.hue_steps == 0
+    }
 }
 
 impl Display for ColorThemeOption {
@@ -140,8 +950,28 @@ impl Display for ColorThemeOption {
             0 => "",
             _ => &format!("-Blends {}", self.blends),
         };
+        let interpolation = match self.interpolation {
+            Interpolation::Lab => "",
+            _ => &format!("-Interpolation {}", self.interpolation),
+        };
+        let hue_steps = match self.hue_steps {
+            0 => "",
+            _ => &format!("-HueSteps {}", self.hue_steps),
+        };
+        let palette_size = match self.palette_size {
+            2 => "",
+            _ => &format!("-PaletteSize {}", self.palette_size),
+        };
+        let include_seed = match self.include_seed {
+            true => "-IncludeSeed",
+            false => "",
+        };
+        let dominant_pair = match self.dominant_pair {
+            true => "-DominantPair",
+            false => "",
+        };
 
-        write!(f, "{darker}{lighter}{complementary}{contrast}{hue_offset}{triadic}{quadratic}{tetratic}{analogous}{split_complementary}{monochromatic}{shades}{tints}{tones}{blends}")
+        write!(f, "{darker}{lighter}{complementary}{contrast}{hue_offset}{triadic}{quadratic}{tetratic}{analogous}{split_complementary}{monochromatic}{shades}{tints}{tones}{blends}{interpolation}{hue_steps}{palette_size}{include_seed}{dominant_pair}")
     }
 }
 
@@ -149,16 +979,49 @@ impl Display for ColorThemeOption {
 #[derive(Clone, ValueEnum, Serialize, Deserialize)]
 pub enum OutputFormat {
     JSON,
+    #[value(name = "json-meta")]
+    JSONMETA,
     YAML,
     TEXT,
+    CSS,
+    PYWAL,
+    NAMED,
+    XRESOURCES,
+    ALACRITTY,
+    #[value(name = "alacritty-yaml")]
+    ALACRITTYYAML,
+    BASE16,
+    DUNST,
+    ROFI,
+    #[value(name = "gtk-css")]
+    GTKCSS,
+    HYPRLAND,
+    POLYBAR,
+    EMACS,
+    ENV,
 }
 
 impl std::fmt::Display for OutputFormat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             OutputFormat::JSON => write!(f, "json"),
+            OutputFormat::JSONMETA => write!(f, "json-meta"),
             OutputFormat::YAML => write!(f, "yaml"),
             OutputFormat::TEXT => write!(f, "text"),
+            OutputFormat::CSS => write!(f, "css"),
+            OutputFormat::PYWAL => write!(f, "pywal"),
+            OutputFormat::NAMED => write!(f, "named"),
+            OutputFormat::XRESOURCES => write!(f, "xresources"),
+            OutputFormat::ALACRITTY => write!(f, "alacritty"),
+            OutputFormat::ALACRITTYYAML => write!(f, "alacritty-yaml"),
+            OutputFormat::BASE16 => write!(f, "base16"),
+            OutputFormat::DUNST => write!(f, "dunst"),
+            OutputFormat::ROFI => write!(f, "rofi"),
+            OutputFormat::GTKCSS => write!(f, "gtk-css"),
+            OutputFormat::HYPRLAND => write!(f, "hyprland"),
+            OutputFormat::POLYBAR => write!(f, "polybar"),
+            OutputFormat::EMACS => write!(f, "emacs"),
+            OutputFormat::ENV => write!(f, "env"),
         }
     }
 }
@@ -172,6 +1035,33 @@ pub enum Centrality {
     Median,
     /// Get the most repeating pixels in an image.
     Prevalent,
+    /// Cluster the pixels in an image with k-means and get the centroids of the biggest clusters.
+    Kmeans,
+    /// Quantize the pixels in an image and get the centers of the most repeating buckets.
+    Mode,
+    /// Split the pixels in an image into buckets with the median cut algorithm and get the averages of the biggest buckets.
+    MedianCut,
+    /// Takes the average of the pixels in an image weighted by their saturation.
+    VividAverage,
+    /// Takes the average of the CIELAB lightness and components of the pixels in an image.
+    LabAverage,
+}
+
+impl Centrality {
+    /// Whether the centrality counts the pixels that every selected color stands for, see [`Coverage`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::Centrality;
+    /// assert!(Centrality::Kmeans.counts_pixels());
+    /// assert!(!Centrality::Median.counts_pixels());
+    /// ```
+    pub fn counts_pixels(&self) -> bool {
+        matches!(
+            self,
+            Centrality::Prevalent | Centrality::Kmeans | Centrality::Mode | Centrality::MedianCut
+        )
+    }
 }
 
 impl Display for Centrality {
@@ -180,24 +1070,224 @@ impl Display for Centrality {
             Centrality::Average => write!(f, "average"),
             Centrality::Median => write!(f, "median"),
             Centrality::Prevalent => write!(f, "prevalent"),
+            Centrality::Kmeans => write!(f, "kmeans"),
+            Centrality::Mode => write!(f, "mode"),
+            Centrality::MedianCut => write!(f, "median-cut"),
+            Centrality::VividAverage => write!(f, "vivid-average"),
+            Centrality::LabAverage => write!(f, "lab-average"),
         }
     }
 }
 
 impl FromStr for Centrality {
-    type Err = anyhow::Error;
+    type Err = ColorSchemeError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "average" => Ok(Centrality::Average),
             "median" => Ok(Centrality::Median),
             "prevalent" => Ok(Centrality::Prevalent),
-            _ => Err(Error.into()),
+            "kmeans" => Ok(Centrality::Kmeans),
+            "mode" => Ok(Centrality::Mode),
+            "median-cut" => Ok(Centrality::MedianCut),
+            "vivid-average" => Ok(Centrality::VividAverage),
+            "lab-average" => Ok(Centrality::LabAverage),
+            _ => Err(ColorSchemeError::InvalidCentrality(s.to_string())),
+        }
+    }
+}
+
+/// Color space that [`ColorThemeOption::blends`] are interpolated in.
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Default, ValueEnum, Serialize, Deserialize)]
+pub enum Interpolation {
+    /// Interpolate the red, green and blue sub pixels.
+    Srgb,
+    /// Interpolate in CIELAB, where the midpoints are perceptually halfway between the colors.
+    #[default]
+    Lab,
+    /// Interpolate the hue along the shorter arc of the color wheel, and the saturation and lightness.
+    Hsl,
+}
+
+impl Display for Interpolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Interpolation::Srgb => write!(f, "srgb"),
+            Interpolation::Lab => write!(f, "lab"),
+            Interpolation::Hsl => write!(f, "hsl"),
+        }
+    }
+}
+
+impl FromStr for Interpolation {
+    type Err = ColorSchemeError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "srgb" => Ok(Interpolation::Srgb),
+            "lab" => Ok(Interpolation::Lab),
+            "hsl" => Ok(Interpolation::Hsl),
+            _ => Err(ColorSchemeError::InvalidInterpolation(s.to_string())),
+        }
+    }
+}
+
+/// Region of an image that is analyzed, each region covers a quarter of the image.
+#[derive(PartialEq, Copy, Clone, ValueEnum, Serialize, Deserialize)]
+pub enum Region {
+    /// The middle half of the width and height of the image.
+    Center,
+    /// The four corners of the image, each a quarter of its width and height.
+    Corners,
+    /// The top quarter of the image.
+    Top,
+    /// The bottom quarter of the image.
+    Bottom,
+}
+
+/// Rectangle of an image that is analyzed, in pixels from the top left corner of the image.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::{ColorSchemeError, Crop};
+/// let crop = "0,1040,1920,40".parse::<Crop>().unwrap();
+/// assert_eq!(crop, Crop { x: 0, y: 1040, width: 1920, height: 40 });
+/// assert_eq!(crop.to_string(), "0,1040,1920,40");
+/// assert_eq!(" 1, 2, 3, 4 ".parse::<Crop>().unwrap(), Crop { x: 1, y: 2, width: 3, height: 4 });
+/// for invalid in ["0,0,10", "0,0,10,10,10", "a,0,10,10", "0,0,0,10", "-1,0,10,10"] {
+///     assert!(matches!(invalid.parse::<Crop>(), Err(ColorSchemeError::InvalidCrop(_))));
+/// }
+/// ```
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+pub struct Crop {
+    /// Column of the left edge of the rectangle.
+    pub x: u32,
+    /// Row of the top edge of the rectangle.
+    pub y: u32,
+    /// Width of the rectangle, at least 1.
+    pub width: u32,
+    /// Height of the rectangle, at least 1.
+    pub height: u32,
+}
+
+impl Display for Crop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+impl FromStr for Crop {
+    type Err = ColorSchemeError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ColorSchemeError::InvalidCrop(s.to_string()))?;
+        match values[..] {
+            [x, y, width, height] if width > 0 && height > 0 => Ok(Crop {
+                x,
+                y,
+                width,
+                height,
+            }),
+            _ => Err(ColorSchemeError::InvalidCrop(s.to_string())),
         }
     }
 }
 
+/// Frame of an animated image that is analyzed.
+#[derive(PartialEq, Copy, Clone, Debug, Default, ValueEnum, Serialize, Deserialize)]
+pub enum Frame {
+    /// The first frame, which is also the only frame of a still image.
+    #[default]
+    First,
+    /// The frame in the middle of the animation.
+    Middle,
+    /// The average of every frame of the animation, pixel by pixel.
+    Average,
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let frame = match self {
+            Frame::First => "first",
+            Frame::Middle => "middle",
+            Frame::Average => "average",
+        };
+        write!(f, "{frame}")
+    }
+}
+
+/// Order that the colors of a color theme are output in.
+#[derive(PartialEq, Copy, Clone, Debug, Default, ValueEnum, Serialize, Deserialize)]
+pub enum Sort {
+    /// The order that the colors are generated in.
+    #[default]
+    None,
+    /// Ascending hue, starting from red.
+    Hue,
+    /// Ascending relative luminance, from the darkest to the lightest color.
+    Luminance,
+    /// Descending amount of analyzed pixels that every color stands for, as counted by the centrality, see [`Coverage`].
+    Frequency,
+}
+
+impl Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let region = match self {
+            Region::Center => "center",
+            Region::Corners => "corners",
+            Region::Top => "top",
+            Region::Bottom => "bottom",
+        };
+        write!(f, "{region}")
+    }
+}
+
+/// Format of the error printed to stderr when the application fails.
+#[derive(PartialEq, Copy, Clone, Debug, Default, ValueEnum, Serialize, Deserialize)]
+pub enum ErrorFormat {
+    /// The error message as text.
+    #[default]
+    Text,
+    /// An [`ErrorReport`] as JSON.
+    Json,
+}
+
+impl Display for ErrorFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let format = match self {
+            ErrorFormat::Text => "text",
+            ErrorFormat::Json => "json",
+        };
+        write!(f, "{format}")
+    }
+}
+
+impl Display for Sort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sort = match self {
+            Sort::None => "none",
+            Sort::Hue => "hue",
+            Sort::Luminance => "luminance",
+            Sort::Frequency => "frequency",
+        };
+        write!(f, "{sort}")
+    }
+}
+
+/// Color vision deficiency that a color theme is simulated or adjusted for.
+#[derive(PartialEq, Copy, Clone, Debug, ValueEnum, Serialize, Deserialize)]
+pub enum Cvd {
+    /// Missing long wavelength (red) cones.
+    Protanopia,
+    /// Missing medium wavelength (green) cones.
+    Deuteranopia,
+    /// Missing short wavelength (blue) cones.
+    Tritanopia,
+}
+
 /// Struct representation for the [`image::Rgb<u8>`] type.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct RGB {
     /// Red RGB sub-pixel.
     pub red: u8,
@@ -213,50 +1303,723 @@ impl Display for RGB {
     }
 }
 
+/// A [`RGB`] color with an optional opacity, the alpha is only serialized if it is given.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::{RGB, RGBA};
+/// let red = RGB { red: 255, green: 0, blue: 0 };
+/// let translucent = RGBA { color: red.clone(), alpha: Some(204) };
+/// assert_eq!(translucent.to_string(), "#ff0000cc");
+/// assert_eq!(serde_json::to_string(&translucent).unwrap(), r#"{"red":255,"green":0,"blue":0,"alpha":204}"#);
+/// let opaque = RGBA { color: red.clone(), alpha: None };
+/// assert_eq!(opaque.to_string(), "#ff0000");
+/// assert_eq!(serde_json::to_string(&opaque).unwrap(), serde_json::to_string(&red).unwrap());
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct RGBA {
+    /// Red, green and blue sub-pixels.
+    #[serde(flatten)]
+    pub color: RGB,
+    /// Opacity from 0 for transparent to 255 for opaque.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alpha: Option<u8>,
+}
+
+impl Display for RGBA {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.alpha {
+            Some(alpha) => write!(f, "{}{alpha:02x}", self.color),
+            None => write!(f, "{}", self.color),
+        }
+    }
+}
+
+impl RGB {
+    /// Convert the color into its hue in degrees, saturation and lightness.
+    ///
+    /// # Notes
+    /// The hue is in the range [0, 360) and both saturation and lightness are in the range [0, 1].
+    /// The hue and saturation of grays are 0.
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::RGB;
+    /// let colors = [
+    ///     RGB { red: 0, green: 0, blue: 0 },
+    ///     RGB { red: 255, green: 0, blue: 0 },
+    ///     RGB { red: 0, green: 255, blue: 0 },
+    ///     RGB { red: 0, green: 0, blue: 255 },
+    ///     RGB { red: 255, green: 255, blue: 0 },
+    ///     RGB { red: 0, green: 255, blue: 255 },
+    ///     RGB { red: 255, green: 0, blue: 255 },
+    ///     RGB { red: 255, green: 255, blue: 255 },
+    ///     RGB { red: 128, green: 128, blue: 128 },
+    ///     RGB { red: 222, green: 186, blue: 189 },
+    ///     RGB { red: 33, green: 69, blue: 66 },
+    /// ];
+    /// for color in colors {
+    ///     let (h, s, l) = color.to_hsl();
+    ///     assert_eq!(RGB::from_hsl(h, s, l), color);
+    /// }
+    /// assert_eq!(RGB { red: 0, green: 255, blue: 255 }.to_hsl(), (180.0, 1.0, 0.5));
+    /// ```
+    pub fn to_hsl(&self) -> (f64, f64, f64) {
+        let (h, max, min) = self.hue();
+        let l = (max + min) / 2.0;
+        let delta = max - min;
+        let s = match delta == 0.0 {
+            true => 0.0,
+            false => delta / (1.0 - (2.0 * l - 1.0).abs()),
+        };
+        (h, s, l)
+    }
+
+    /// Create a color from a hue in degrees, saturation and lightness.
+    ///
+    /// # Notes
+    /// The hue wraps around the color wheel and both saturation and lightness are clamped to the range [0, 1].
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::RGB;
+    /// assert_eq!(RGB::from_hsl(480.0, 1.0, 0.5), RGB { red: 0, green: 255, blue: 0 });
+    /// ```
+    pub fn from_hsl(h: f64, s: f64, l: f64) -> RGB {
+        let s = s.clamp(0.0, 1.0);
+        let l = l.clamp(0.0, 1.0);
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        RGB::from_chroma(h, c, l - c / 2.0)
+    }
+
+    /// Convert the color into its hue in degrees, saturation and value.
+    ///
+    /// # Notes
+    /// The hue is in the range [0, 360) and both saturation and value are in the range [0, 1].
+    /// The hue and saturation of grays are 0.
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::RGB;
+    /// let colors = [
+    ///     RGB { red: 0, green: 0, blue: 0 },
+    ///     RGB { red: 255, green: 0, blue: 0 },
+    ///     RGB { red: 0, green: 255, blue: 0 },
+    ///     RGB { red: 0, green: 0, blue: 255 },
+    ///     RGB { red: 255, green: 255, blue: 0 },
+    ///     RGB { red: 0, green: 255, blue: 255 },
+    ///     RGB { red: 255, green: 0, blue: 255 },
+    ///     RGB { red: 255, green: 255, blue: 255 },
+    ///     RGB { red: 128, green: 128, blue: 128 },
+    ///     RGB { red: 222, green: 186, blue: 189 },
+    ///     RGB { red: 33, green: 69, blue: 66 },
+    /// ];
+    /// for color in colors {
+    ///     let (h, s, v) = color.to_hsv();
+    ///     assert_eq!(RGB::from_hsv(h, s, v), color);
+    /// }
+    /// assert_eq!(RGB { red: 0, green: 0, blue: 255 }.to_hsv(), (240.0, 1.0, 1.0));
+    /// ```
+    pub fn to_hsv(&self) -> (f64, f64, f64) {
+        let (h, max, min) = self.hue();
+        let s = match max == 0.0 {
+            true => 0.0,
+            false => (max - min) / max,
+        };
+        (h, s, max)
+    }
+
+    /// Create a color from a hue in degrees, saturation and value.
+    ///
+    /// # Notes
+    /// The hue wraps around the color wheel and both saturation and value are clamped to the range [0, 1].
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::RGB;
+    /// assert_eq!(RGB::from_hsv(300.0, 1.0, 1.0), RGB { red: 255, green: 0, blue: 255 });
+    /// ```
+    pub fn from_hsv(h: f64, s: f64, v: f64) -> RGB {
+        let s = s.clamp(0.0, 1.0);
+        let v = v.clamp(0.0, 1.0);
+        let c = v * s;
+        RGB::from_chroma(h, c, v - c)
+    }
+
+    /// Get the Euclidean distance between two colors in the sRGB color space, in the range [0, 441.7].
+    ///
+    /// # Notes
+    /// This distance is not perceptual, two pairs of colors that are equally far apart may look more or less different,
+    /// see [`RGB::distance_lab`] for a perceptual distance.
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::RGB;
+    /// let red = RGB { red: 255, green: 0, blue: 0 };
+    /// assert_eq!(red.distance(&red), 0.0);
+    /// assert_eq!(red.distance(&RGB { red: 255, green: 3, blue: 4 }), 5.0);
+    /// ```
+    pub fn distance(&self, other: &RGB) -> f64 {
+        [
+            (self.red, other.red),
+            (self.green, other.green),
+            (self.blue, other.blue),
+        ]
+        .iter()
+        .map(|(a, b)| (f64::from(*a) - f64::from(*b)).powi(2))
+        .sum::<f64>()
+        .sqrt()
+    }
+
+    /// Get the CIE76 distance between two colors, which is their Euclidean distance in the CIELAB color space.
+    ///
+    /// # Notes
+    /// This distance is perceptual, a distance of about 2.3 is just noticeable and colors that are equally far apart look about as different.
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::RGB;
+    /// let gray = RGB { red: 128, green: 128, blue: 128 };
+    /// assert_eq!(gray.distance_lab(&gray), 0.0);
+    /// // Both colors are as far from the gray in sRGB, but adding green looks like a bigger change than adding red.
+    /// let (greener, redder) = (RGB { red: 128, green: 168, blue: 128 }, RGB { red: 168, green: 128, blue: 128 });
+    /// assert_eq!(gray.distance(&greener), gray.distance(&redder));
+    /// assert!(gray.distance_lab(&redder) < gray.distance_lab(&greener));
+    /// ```
+    pub fn distance_lab(&self, other: &RGB) -> f64 {
+        let (l1, a1, b1) = self.to_lab();
+        let (l2, a2, b2) = other.to_lab();
+        ((l1 - l2).powi(2) + (a1 - a2).powi(2) + (b1 - b2).powi(2)).sqrt()
+    }
+
+    /// Convert the color into its CIELAB lightness, green-red and blue-yellow components under the D65 white point.
+    ///
+    /// # Notes
+    /// The lightness is in the range [0, 100].
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::RGB;
+    /// let colors = [
+    ///     RGB { red: 0, green: 0, blue: 0 },
+    ///     RGB { red: 255, green: 0, blue: 0 },
+    ///     RGB { red: 0, green: 255, blue: 0 },
+    ///     RGB { red: 0, green: 0, blue: 255 },
+    ///     RGB { red: 255, green: 255, blue: 255 },
+    ///     RGB { red: 128, green: 128, blue: 128 },
+    ///     RGB { red: 222, green: 186, blue: 189 },
+    ///     RGB { red: 33, green: 69, blue: 66 },
+    /// ];
+    /// for color in colors {
+    ///     let (l, a, b) = color.to_lab();
+    ///     assert_eq!(RGB::from_lab(l, a, b), color);
+    /// }
+    /// let (l, a, b) = RGB { red: 255, green: 0, blue: 0 }.to_lab();
+    /// assert_eq!((l.round(), a.round(), b.round()), (53.0, 80.0, 67.0));
+    /// ```
+    pub fn to_lab(&self) -> (f64, f64, f64) {
+        let (r, g, b) = (linear(self.red), linear(self.green), linear(self.blue));
+        let x = (0.4124564 * r + 0.3575761 * g + 0.1804375 * b) / 0.95047;
+        let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+        let z = (0.0193339 * r + 0.1191920 * g + 0.9503041 * b) / 1.08883;
+        let f = |t: f64| match t > (6.0_f64 / 29.0).powi(3) {
+            true => t.cbrt(),
+            false => t / (3.0 * (6.0_f64 / 29.0).powi(2)) + 4.0 / 29.0,
+        };
+        let (fx, fy, fz) = (f(x), f(y), f(z));
+        (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+    }
+
+    /// Create a color from its CIELAB lightness, green-red and blue-yellow components under the D65 white point.
+    ///
+    /// # Notes
+    /// Colors outside of the sRGB gamut are clamped to it.
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::RGB;
+    /// assert_eq!(RGB::from_lab(100.0, 0.0, 0.0), RGB { red: 255, green: 255, blue: 255 });
+    /// assert_eq!(RGB::from_lab(50.0, 0.0, 200.0).blue, 0);
+    /// ```
+    pub fn from_lab(l: f64, a: f64, b: f64) -> RGB {
+        let fy = (l + 16.0) / 116.0;
+        let (fx, fz) = (fy + a / 500.0, fy - b / 200.0);
+        let f_inverse = |t: f64| match t > 6.0 / 29.0 {
+            true => t.powi(3),
+            false => 3.0 * (6.0_f64 / 29.0).powi(2) * (t - 4.0 / 29.0),
+        };
+        let (x, y, z) = (
+            f_inverse(fx) * 0.95047,
+            f_inverse(fy),
+            f_inverse(fz) * 1.08883,
+        );
+        RGB {
+            red: gamma(3.2404542 * x - 1.5371385 * y - 0.4985314 * z),
+            green: gamma(-0.9692660 * x + 1.8760108 * y + 0.0415560 * z),
+            blue: gamma(0.0556434 * x - 0.2040259 * y + 1.0572252 * z),
+        }
+    }
+
+    /// Convert the color into linear light, every sub pixel in the range [0, 1].
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::RGB;
+    /// assert_eq!(RGB { red: 255, green: 0, blue: 0 }.to_linear(), [1.0, 0.0, 0.0]);
+    /// assert!((RGB { red: 128, green: 128, blue: 128 }.to_linear()[0] - 0.2158).abs() < 0.0001);
+    /// ```
+    pub fn to_linear(&self) -> [f64; 3] {
+        [linear(self.red), linear(self.green), linear(self.blue)]
+    }
+
+    /// Create a color from linear light, clamping every sub pixel to the range [0, 1].
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::RGB;
+    /// assert_eq!(RGB::from_linear([0.5, 0.0, 2.0]), RGB { red: 188, green: 0, blue: 255 });
+    /// let color = RGB { red: 222, green: 186, blue: 189 };
+    /// assert_eq!(RGB::from_linear(color.to_linear()), color);
+    /// ```
+    pub fn from_linear([red, green, blue]: [f64; 3]) -> RGB {
+        RGB {
+            red: gamma(red),
+            green: gamma(green),
+            blue: gamma(blue),
+        }
+    }
+
+    /// Scale the HSL saturation of the color by a percentage from -100 to 100, keeping its hue and lightness.
+    ///
+    /// # Notes
+    /// The saturation is clamped to the range [0, 1], so -100 turns the color into a gray and 100 doubles its saturation.
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::RGB;
+    /// let muted = RGB { red: 140, green: 120, blue: 120 };
+    /// let vivid = muted.with_saturation(100);
+    /// let ((h1, s1, _), (h2, s2, _)) = (muted.to_hsl(), vivid.to_hsl());
+    /// assert!(s2 > s1);
+    /// assert_eq!(h1, h2);
+    /// assert_eq!(vivid, RGB { red: 150, green: 110, blue: 110 });
+    /// assert_eq!(muted.with_saturation(-100), RGB { red: 130, green: 130, blue: 130 });
+    /// assert_eq!(muted.with_saturation(0), muted);
+    /// ```
+    pub fn with_saturation(&self, percent: i8) -> RGB {
+        let (h, s, l) = self.to_hsl();
+        RGB::from_hsl(h, s * (1.0 + f64::from(percent) / 100.0), l)
+    }
+
+    /// Shift the color toward warm for a positive temperature or toward cool for a negative one, from -100 to 100.
+    ///
+    /// # Notes
+    /// The red and blue sub pixels are scaled in opposite directions in linear light like a white balance, by up to 25% at 100,
+    /// so the hue of the color is never rotated around the color wheel. The sub pixels are clamped to the range [0, 255].
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::RGB;
+    /// let gray = RGB { red: 128, green: 128, blue: 128 };
+    /// let warm = gray.with_temperature(50);
+    /// assert!(warm.red > gray.red && warm.blue < gray.blue);
+    /// assert_eq!(warm.green, gray.green);
+    /// let cool = gray.with_temperature(-50);
+    /// assert!(cool.red < gray.red && cool.blue > gray.blue);
+    /// assert_eq!(gray.with_temperature(0), gray);
+    /// assert_eq!(RGB { red: 255, green: 0, blue: 0 }.with_temperature(100), RGB { red: 255, green: 0, blue: 0 });
+    /// ```
+    pub fn with_temperature(&self, temperature: i8) -> RGB {
+        let shift = f64::from(temperature.clamp(-100, 100)) / 100.0 * 0.25;
+        let [red, green, blue] = self.to_linear();
+        RGB::from_linear([red * (1.0 + shift), green, blue * (1.0 - shift)])
+    }
+
+    /// Get the WCAG relative luminance of the color, from 0 for black to 1 for white.
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::RGB;
+    /// assert_eq!(RGB { red: 0, green: 0, blue: 0 }.luminance(), 0.0);
+    /// assert_eq!(RGB { red: 255, green: 255, blue: 255 }.luminance(), 1.0);
+    /// ```
+    pub fn luminance(&self) -> f64 {
+        0.2126 * linear(self.red) + 0.7152 * linear(self.green) + 0.0722 * linear(self.blue)
+    }
+
+    /// Get the WCAG contrast ratio between two colors, from 1 for identical colors to 21 for black and white.
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::RGB;
+    /// let black = RGB { red: 0, green: 0, blue: 0 };
+    /// let white = RGB { red: 255, green: 255, blue: 255 };
+    /// let gray = RGB { red: 119, green: 119, blue: 119 };
+    /// assert_eq!(white.contrast_ratio(&black), 21.0);
+    /// assert_eq!(black.contrast_ratio(&white), 21.0);
+    /// assert!(gray.contrast_ratio(&white) < 4.5);
+    /// assert!(gray.contrast_ratio(&black) > 4.5);
+    /// ```
+    pub fn contrast_ratio(&self, other: &RGB) -> f64 {
+        let (l1, l2) = (self.luminance(), other.luminance());
+        (l1.max(l2) + 0.05) / (l1.min(l2) + 0.05)
+    }
+
+    /// Get the name of the CSS named color closest to the color by euclidean distance in RGB space.
+    ///
+    /// # Notes
+    /// Only the "gray" spelling of grays is used. Ties resolve to the name that comes first alphabetically, so `#00ffff` is "aqua" rather than "cyan".
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::RGB;
+    /// assert_eq!(RGB { red: 255, green: 0, blue: 0 }.nearest_name(), "red");
+    /// assert_eq!(RGB { red: 254, green: 0, blue: 1 }.nearest_name(), "red");
+    /// assert_eq!(RGB { red: 70, green: 130, blue: 180 }.nearest_name(), "steelblue");
+    /// assert_eq!(RGB { red: 0, green: 255, blue: 255 }.nearest_name(), "aqua");
+    /// ```
+    pub fn nearest_name(&self) -> &'static str {
+        let distance = |other: &RGB| {
+            [
+                (self.red, other.red),
+                (self.green, other.green),
+                (self.blue, other.blue),
+            ]
+            .iter()
+            .map(|(a, b)| u32::from(a.abs_diff(*b)).pow(2))
+            .sum::<u32>()
+        };
+        named_colors::NAMED_COLORS
+            .iter()
+            .min_by_key(|(_, color)| distance(color))
+            .map(|(name, _)| *name)
+            .unwrap_or_default()
+    }
+
+    /// Get the hue in degrees along with the largest and smallest sub pixels in the range [0, 1].
+    fn hue(&self) -> (f64, f64, f64) {
+        let r = f64::from(self.red) / 255.0;
+        let g = f64::from(self.green) / 255.0;
+        let b = f64::from(self.blue) / 255.0;
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+        let h = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * (((b - r) / delta) + 2.0)
+        } else {
+            60.0 * (((r - g) / delta) + 4.0)
+        };
+        (h, max, min)
+    }
+
+    /// Create a color from a hue in degrees, a chroma and the amount to add to every sub pixel to match the lightness or value.
+    fn from_chroma(h: f64, c: f64, m: f64) -> RGB {
+        let h = h.rem_euclid(360.0);
+        let x = c * (1.0 - ((h / 60.0).rem_euclid(2.0) - 1.0).abs());
+        let (r, g, b) = match h {
+            h if h < 60.0 => (c, x, 0.0),
+            h if h < 120.0 => (x, c, 0.0),
+            h if h < 180.0 => (0.0, c, x),
+            h if h < 240.0 => (0.0, x, c),
+            h if h < 300.0 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        RGB {
+            red: ((r + m) * 255.0).round() as u8,
+            green: ((g + m) * 255.0).round() as u8,
+            blue: ((b + m) * 255.0).round() as u8,
+        }
+    }
+}
+
+/// Convert a gamma encoded sRGB sub pixel into linear light in the range [0, 1].
+pub(crate) fn linear(c: u8) -> f64 {
+    let c = f64::from(c) / 255.0;
+    match c <= 0.04045 {
+        true => c / 12.92,
+        false => ((c + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+/// Convert linear light into a gamma encoded sRGB sub pixel, clamping it to the range [0, 1].
+pub(crate) fn gamma(c: f64) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let c = match c <= 0.0031308 {
+        true => c * 12.92,
+        false => 1.055 * c.powf(1.0 / 2.4) - 0.055,
+    };
+    (c * 255.0).round() as u8
+}
+
+/// Parse a color written as `#rrggbb`, the `#rgb` shorthand, either without the leading `#`, or as `rgb(r, g, b)`.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::{ColorSchemeError, RGB};
+/// assert_eq!("#debabd".parse::<RGB>().unwrap(), RGB { red: 222, green: 186, blue: 189 });
+/// assert_eq!("#abc".parse::<RGB>().unwrap(), RGB { red: 0xaa, green: 0xbb, blue: 0xcc });
+/// assert_eq!("ffffff".parse::<RGB>().unwrap(), RGB { red: 255, green: 255, blue: 255 });
+/// assert_eq!("rgb( 1, 2, 3 )".parse::<RGB>().unwrap(), RGB { red: 1, green: 2, blue: 3 });
+/// assert!(matches!("rgb(300,0,0)".parse::<RGB>(), Err(ColorSchemeError::InvalidHex(_))));
+/// assert!(matches!("#abcd".parse::<RGB>(), Err(ColorSchemeError::InvalidHex(_))));
+/// ```
 impl FromStr for RGB {
-    type Err = anyhow::Error;
+    type Err = ColorSchemeError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let regex = regex::Regex::new(r"^#[0123456789AaBbCcDdEeFf]{6}$").unwrap();
-        match regex.is_match(s) {
-            true => {
-                let hex = s.as_bytes();
-                let red = hex_to_rgb(hex[1], hex[2])?;
-                let green = hex_to_rgb(hex[3], hex[4])?;
-                let blue = hex_to_rgb(hex[5], hex[6])?;
-
-                Ok(RGB { red, green, blue })
-            }
-            false => Err(anyhow::anyhow!(
-                "Inputted string is not a valid hexadecimal RGB value. Example: #FFFFFF"
-            )),
+        let invalid = || ColorSchemeError::InvalidHex(s.to_string());
+        let hex = regex::Regex::new(r"^#?([0-9A-Fa-f]{3}|[0-9A-Fa-f]{6})$").unwrap();
+        let functional =
+            regex::Regex::new(r"^rgb\(\s*(\d+)\s*,\s*(\d+)\s*,\s*(\d+)\s*\)$").unwrap();
+        if let Some(captures) = hex.captures(s) {
+            let digits = captures[1].as_bytes();
+            let digits = match digits.len() {
+                3 => digits.iter().flat_map(|d| [*d, *d]).collect::<Vec<_>>(),
+                _ => digits.to_vec(),
+            };
+            let red = hex_to_rgb(digits[0], digits[1]).ok_or_else(invalid)?;
+            let green = hex_to_rgb(digits[2], digits[3]).ok_or_else(invalid)?;
+            let blue = hex_to_rgb(digits[4], digits[5]).ok_or_else(invalid)?;
+            return Ok(RGB { red, green, blue });
         }
+        let captures = functional.captures(s).ok_or_else(invalid)?;
+        let channel = |i: usize| captures[i].parse::<u8>().map_err(|_| invalid());
+        Ok(RGB {
+            red: channel(1)?,
+            green: channel(2)?,
+            blue: channel(3)?,
+        })
     }
 }
 
-fn hex_to_rgb(msd: u8, lsd: u8) -> anyhow::Result<u8> {
+fn hex_to_rgb(msd: u8, lsd: u8) -> Option<u8> {
     let leading = (u16::from(char_to_u8(msd as char)?)) << 4;
     let smallest = u16::from(char_to_u8(lsd as char)?);
     let ret = leading + smallest;
-    Ok(u8::try_from(ret)?)
+    u8::try_from(ret).ok()
 }
 
-fn char_to_u8(c: char) -> anyhow::Result<u8> {
+fn char_to_u8(c: char) -> Option<u8> {
     let x = c.to_ascii_uppercase() as u8;
 
-    match x.to_ascii_uppercase() as u8 {
-        48..=58 => Ok(x - 48),
-        65..=90 => Ok(x - 55),
-        _ => Err(anyhow::anyhow!("Character cannot be converted to u8.")),
+    match x.to_ascii_uppercase() {
+        48..=58 => Some(x - 48),
+        65..=90 => Some(x - 55),
+        _ => None,
     }
 }
 
 /// Application Name used for XDG compliant directory structure.
 pub const APP_NAME: &str = "color_scheme_generator";
 
-/// Command line executable name for gamut-cli.
-pub const GAMUT_CLI_NAME: &str = "gamut-cli";
-
 pub struct Wallpaper {
     pub path: PathBuf,
     pub centrality: Centrality,
+    /// Last modification time of the image in nanoseconds since the UNIX epoch, used to invalidate cached color themes.
+    pub modified: i64,
+    /// Hash of the contents of the image used to share color themes between copies of an image, empty if unknown.
+    pub content_hash: String,
+    /// Options that change which pixels of the image are analyzed, as given by [`Cli::analysis_parameters`], empty if unknown.
+    pub analysis: String,
+}
+
+/// Color theme of one image in batch or directory mode, or the reason it could not be generated.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct BatchEntry {
+    pub path: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub colors: Option<Vec<RGB>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Coverage of the colors, used to adjust them and not output.
+    #[serde(skip)]
+    pub coverage: Option<Coverage>,
+}
+
+/// Description of how a color theme would be generated, output by [`Cli::explain`].
+#[derive(Serialize, Deserialize)]
+pub struct Explanation {
+    /// Whether the color theme would be read from the cache instead of analyzing the image.
+    pub cache_hit: bool,
+    /// Centrality used to analyze the image.
+    pub centrality: String,
+    /// Color theme options after the defaults are applied.
+    pub color_themes: ColorThemeOption,
+    /// Path of the cache database.
+    pub cache_path: PathBuf,
+}
+
+/// Distribution of the analyzed pixels of an image, output by [`Cli::histogram`].
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Histogram {
+    /// Number of analyzed pixels.
+    pub pixels: usize,
+    /// Number of pixels with every red value from 0 to 255.
+    pub red: Vec<usize>,
+    /// Number of pixels with every green value from 0 to 255.
+    pub green: Vec<usize>,
+    /// Number of pixels with every blue value from 0 to 255.
+    pub blue: Vec<usize>,
+    /// Most prevalent colors along with their number of pixels, most prevalent first.
+    pub prevalent: Vec<ColorCount>,
+}
+
+/// Version and capabilities of the application, output by [`Cli::version_json`].
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct VersionInfo {
+    /// Version of the crate.
+    pub version: String,
+    /// Git commit the application was built from, if it was given at build time.
+    pub git_hash: Option<String>,
+    /// Names of the supported values of [`Cli::serialization_format`].
+    pub formats: Vec<String>,
+    /// Names of the supported values of [`Cli::centrality`].
+    pub centralities: Vec<String>,
+    /// Whether the color schemes are computed natively instead of by an external program.
+    pub native_engine: bool,
+}
+
+/// Color of an image along with the number of pixels it covers.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ColorCount {
+    /// Color of the pixels.
+    pub color: RGB,
+    /// Number of pixels of the color.
+    pub count: usize,
+}
+
+/// Colors selected by a centrality along with the number of analyzed pixels each of them stands for.
+///
+/// # Notes
+/// The prevalent and mode centralities count the pixels of every selected color, k-means the pixels of its cluster
+/// and median cut the pixels of its bucket. The other centralities select colors that do not stand for a group of pixels, so they have no coverage.
+///
+/// Colors are looked up by value, so a color of the color theme that was derived from the selected colors or changed by an adjustment
+/// such as [`Cli::invert`] has no coverage, since it does not appear in the image.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::{ColorCount, Coverage, RGB};
+/// let (red, blue, white) = (RGB { red: 200, green: 30, blue: 30 }, RGB { red: 30, green: 30, blue: 200 }, RGB { red: 255, green: 255, blue: 255 });
+/// let coverage = Coverage {
+///     colors: vec![ColorCount { color: red.clone(), count: 12 }, ColorCount { color: blue.clone(), count: 4 }],
+///     pixels: 16,
+/// };
+/// assert_eq!(coverage.fractions(&[blue.clone(), white.clone(), red.clone()]), vec![Some(0.25), None, Some(0.75)]);
+/// assert_eq!(coverage.counts(&[blue, white, red]), vec![4, 0, 12]);
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Coverage {
+    /// Colors selected by the centrality and the number of analyzed pixels of each one.
+    pub colors: Vec<ColorCount>,
+    /// Number of analyzed pixels.
+    pub pixels: usize,
+}
+
+impl Coverage {
+    /// Get the number of analyzed pixels of every color of a color theme, which is 0 for the colors that were not selected by the centrality.
+    pub fn counts(&self, colors: &[RGB]) -> Vec<usize> {
+        colors
+            .iter()
+            .map(|c| {
+                self.colors
+                    .iter()
+                    .find(|counted| counted.color == *c)
+                    .map_or(0, |counted| counted.count)
+            })
+            .collect()
+    }
+
+    /// Get the fraction of the analyzed pixels every color of a color theme stands for, which is None for the colors that were not selected by the centrality.
+    pub fn fractions(&self, colors: &[RGB]) -> Vec<Option<f64>> {
+        colors
+            .iter()
+            .map(|c| {
+                self.colors
+                    .iter()
+                    .find(|counted| counted.color == *c)
+                    .map(|counted| counted.count as f64 / self.pixels.max(1) as f64)
+            })
+            .collect()
+    }
+}
+
+/// Hash the contents of a file with 64-bit FNV-1a, returned as 16 hexadecimal digits.
+///
+/// # Notes
+/// The hash is not cryptographic, it is only used to recognize identical images under different paths.
+///
+/// # Errors
+/// Will error if the file cannot be read.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::content_hash;
+/// let dir = std::env::temp_dir();
+/// let (a, b, c) = (dir.join("color_scheme_generator_hash_a"), dir.join("color_scheme_generator_hash_b"), dir.join("color_scheme_generator_hash_c"));
+/// std::fs::write(&a, "same bytes").unwrap();
+/// std::fs::write(&b, "same bytes").unwrap();
+/// std::fs::write(&c, "other bytes").unwrap();
+/// assert_eq!(content_hash(&a).unwrap(), content_hash(&b).unwrap());
+/// assert_ne!(content_hash(&a).unwrap(), content_hash(&c).unwrap());
+/// std::fs::write(&a, "").unwrap();
+/// assert_eq!(content_hash(&a).unwrap(), "cbf29ce484222325");
+/// ```
+pub fn content_hash(path: &Path) -> Result<String, ColorSchemeError> {
+    Ok(fnv1a(&std::fs::read(path)?))
+}
+
+/// Hash bytes with 64-bit FNV-1a, returned as 16 hexadecimal digits.
+pub(crate) fn fnv1a(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    });
+    format!("{hash:016x}")
+}
+
+/// Get the last modification time of a file in nanoseconds since the UNIX epoch.
+///
+/// # Errors
+/// Will error if the file metadata cannot be read or the modification time is before the UNIX epoch.
+///
+/// # Examples
+/// ```
+/// # use std::fs::File;
+/// # use std::time::{Duration, SystemTime};
+/// # use color_scheme_generator::common::modified_time;
+/// let path = std::env::temp_dir().join("color_scheme_generator_modified_time.txt");
+/// let file = File::create(&path).unwrap();
+/// file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1)).unwrap();
+/// assert_eq!(modified_time(&path).unwrap(), 1_000_000_000);
+/// ```
+pub fn modified_time(path: &PathBuf) -> Result<i64, ColorSchemeError> {
+    let modified = std::fs::metadata(path)?
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(std::io::Error::other)?;
+    Ok(i64::try_from(modified.as_nanos())?)
+}
+
+/// Get the format of an image from the magic bytes at the start of its contents, or None if they are not the magic bytes of a supported format.
+///
+/// # Notes
+/// The format is detected regardless of the extension of the file, so images piped into stdin or saved without an extension can be decoded.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::detect_image_format;
+/// let mut png = vec![];
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([200, 30, 30]))
+///     .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+///     .unwrap();
+/// assert_eq!(detect_image_format(&png), Some(image::ImageFormat::Png));
+/// assert_eq!(detect_image_format(&[0xff, 0xd8, 0xff, 0xe0, 0x00, 0x10]), Some(image::ImageFormat::Jpeg));
+/// assert_eq!(detect_image_format(b"/home/user/wallpaper.png"), None);
+/// assert_eq!(detect_image_format(&[]), None);
+/// ```
+pub fn detect_image_format(bytes: &[u8]) -> Option<image::ImageFormat> {
+    image::guess_format(bytes).ok()
 }
@@ -0,0 +1,194 @@
+#![deny(unused_extern_crates)]
+#![warn(missing_docs)]
+use crate::common::{Cli, APP_NAME};
+use clap::{Arg, CommandFactory, ValueEnum, ValueHint};
+use serde::{Deserialize, Serialize};
+
+/// Shells that completion scripts can be generated for.
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+pub enum Shell {
+    /// Bash completion script, to be sourced or placed in bash-completion's completions directory.
+    Bash,
+    /// Zsh completion function, to be placed in a directory of `$fpath`.
+    Zsh,
+    /// Fish completions, to be placed in `~/.config/fish/completions`.
+    Fish,
+    /// Elvish argument completer, to be evaluated in `rc.elv`.
+    Elvish,
+}
+
+/// Generate the completion script of the command line arguments of [`Cli`] for a shell.
+///
+/// # Notes
+/// Every visible option is completed, along with the possible values of the options that have them.
+/// The values of the options that take a path, and any other word, are completed as file paths.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::completions::{completions, Shell};
+/// let bash = completions(Shell::Bash);
+/// assert!(bash.contains("complete -F _color_scheme_generator color_scheme_generator"));
+/// assert!(bash.contains("--centrality"));
+/// assert!(bash.contains("--serialization-format"));
+/// assert!(bash.contains("kmeans"));
+/// assert!(!bash.contains("--generate-completions"));
+///
+/// assert!(completions(Shell::Fish).contains("complete -c color_scheme_generator -s c -l centrality"));
+/// assert!(completions(Shell::Zsh).starts_with("#compdef color_scheme_generator"));
+/// assert!(completions(Shell::Elvish).contains("--triadic"));
+/// ```
+pub fn completions(shell: Shell) -> String {
+    let command = Cli::command();
+    let options = command
+        .get_arguments()
+        .filter(|a| a.get_long().is_some() && !a.is_hide_set())
+        .collect::<Vec<_>>();
+    match shell {
+        Shell::Bash => bash(&options),
+        Shell::Zsh => zsh(&options),
+        Shell::Fish => fish(&options),
+        Shell::Elvish => elvish(&options),
+    }
+}
+
+/// Get the long and short forms of an option, long first.
+fn names(arg: &Arg) -> Vec<String> {
+    arg.get_long()
+        .map(|l| format!("--{l}"))
+        .into_iter()
+        .chain(arg.get_short().map(|s| format!("-{s}")))
+        .collect()
+}
+
+/// Get the possible values of an option separated by spaces.
+fn values(arg: &Arg) -> String {
+    arg.get_possible_values()
+        .iter()
+        .filter(|v| !v.is_hide_set())
+        .map(|v| v.get_name().to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Get the first line of the help of an option with single quotes removed.
+fn help(arg: &Arg) -> String {
+    arg.get_help()
+        .map(|h| h.to_string())
+        .unwrap_or_default()
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .replace('\'', "")
+}
+
+/// Whether an option takes a value that is not one of its possible values.
+fn takes_free_value(arg: &Arg) -> bool {
+    arg.get_action().takes_values() && arg.get_possible_values().is_empty()
+}
+
+/// Whether the value of an option is a path.
+fn takes_path(arg: &Arg) -> bool {
+    matches!(
+        arg.get_value_hint(),
+        ValueHint::FilePath | ValueHint::AnyPath | ValueHint::DirPath
+    )
+}
+
+fn bash(options: &[&Arg]) -> String {
+    let cases = options
+        .iter()
+        .filter(|a| a.get_action().takes_values())
+        .map(|a| {
+            let words = match (takes_free_value(a), takes_path(a)) {
+                (true, true) => "COMPREPLY=($(compgen -f -- \"$cur\"))".to_string(),
+                (true, false) => "COMPREPLY=()".to_string(),
+                (false, _) => format!("COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))", values(a)),
+            };
+            format!(
+                "        {})\n            {words}\n            return\n            ;;\n",
+                names(a).join("|")
+            )
+        })
+        .collect::<String>();
+    let flags = options
+        .iter()
+        .flat_map(|a| names(a))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "_{APP_NAME}() {{
+    local cur prev
+    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"
+    prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"
+    case \"$prev\" in
+{cases}    esac
+    if [[ \"$cur\" == -* ]]; then
+        COMPREPLY=($(compgen -W \"{flags}\" -- \"$cur\"))
+    else
+        COMPREPLY=($(compgen -f -- \"$cur\"))
+    fi
+}}
+complete -F _{APP_NAME} {APP_NAME}
+"
+    )
+}
+
+fn zsh(options: &[&Arg]) -> String {
+    let specs = options
+        .iter()
+        .flat_map(|a| {
+            let help = help(a).replace(['[', ']', ':'], "");
+            let value = match (a.get_action().takes_values(), takes_free_value(a)) {
+                (false, _) => String::new(),
+                (true, true) if takes_path(a) => format!(":{}:_files", a.get_id()),
+                (true, true) => format!(":{}: ", a.get_id()),
+                (true, false) => format!(":{}:({})", a.get_id(), values(a)),
+            };
+            names(a)
+                .into_iter()
+                .map(move |name| format!("    '{name}[{help}]{value}' \\\n"))
+        })
+        .collect::<String>();
+    format!("#compdef {APP_NAME}\n_arguments \\\n{specs}    '*:image:_files'\n")
+}
+
+fn fish(options: &[&Arg]) -> String {
+    options
+        .iter()
+        .map(|a| {
+            let short = a
+                .get_short()
+                .map(|s| format!(" -s {s}"))
+                .unwrap_or_default();
+            let long = a.get_long().unwrap_or_default();
+            let value = match (a.get_action().takes_values(), takes_free_value(a)) {
+                (false, _) => String::new(),
+                (true, true) if takes_path(a) => " -r -F".to_string(),
+                (true, true) => " -x".to_string(),
+                (true, false) => format!(" -x -a '{}'", values(a)),
+            };
+            format!(
+                "complete -c {APP_NAME}{short} -l {long}{value} -d '{}'\n",
+                help(a)
+            )
+        })
+        .collect()
+}
+
+fn elvish(options: &[&Arg]) -> String {
+    let flags = options
+        .iter()
+        .flat_map(|a| names(a))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "set edit:completion:arg-completer[{APP_NAME}] = {{|@words|
+    if (has-prefix $words[-1] -) {{
+        put {flags}
+    }} else {{
+        edit:complete-filename $words[-1]
+    }}
+}}
+"
+    )
+}
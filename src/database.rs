@@ -1,14 +1,131 @@
 #![deny(unused_extern_crates)]
 #![warn(missing_docs)]
-use crate::common::{Centrality, ColorThemeOption, Wallpaper, RGB};
+use crate::common::{
+    Centrality, ColorCount, ColorSchemeError, ColorThemeOption, Coverage, Interpolation, Wallpaper,
+    APP_NAME, RGB,
+};
+use log::debug;
 use sqlite::Connection;
 use sqlite::Row;
+use sqlite::Value;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
+
+/// Query to create the wallpaper, color_themes, RGB and coverage tables if they do not exist.
+const CREATE_TABLES: &str = "
+        CREATE TABLE IF NOT EXISTS wallpaper(path TEXT NOT NULL, centrality TEXT NOT NULL, modified INTEGER NOT NULL DEFAULT 0, content_hash TEXT NOT NULL DEFAULT '', analysis TEXT NOT NULL DEFAULT '');
+        CREATE TABLE IF NOT EXISTS color_themes(darker INTEGER NOT NULL, lighter INTEGER NOT NULL, complementary INTEGER NOT NULL, contrast INTEGER NOT NULL, hueOffset INTEGER NOT NULL, triadic INTEGER NOT NULL, quadratic INTEGER NOT NULL, tetratic INTEGER NOT NULL, analogous INTEGER NOT NULL, splitComplementary INTEGER NOT NULL, monochromatic INTEGER NOT NULL, shades INTEGER NOT NULL, tints INTEGER NOT NULL, tones INTEGER NOT NULL, blends INTEGER NOT NULL, interpolation TEXT NOT NULL DEFAULT 'srgb', hueSteps INTEGER NOT NULL DEFAULT 0, paletteSize INTEGER NOT NULL DEFAULT 2, includeSeed INTEGER NOT NULL DEFAULT 0, dominantPair INTEGER NOT NULL DEFAULT 0, wallpaper INTEGER NOT NULL, FOREIGN KEY(wallpaper) REFERENCES wallpaper(ROWID));
+        CREATE TABLE IF NOT EXISTS RGB(RGB TEXT NOT NULL, wallpaper INTEGER NOT NULL, color_themes INTEGER NOT NULL, FOREIGN KEY(wallpaper) REFERENCES wallpaper(ROWID), FOREIGN KEY(color_themes) REFERENCES color_themes(ROWID));
+        CREATE TABLE IF NOT EXISTS coverage(RGB TEXT NOT NULL, count INTEGER NOT NULL, pixels INTEGER NOT NULL, wallpaper INTEGER NOT NULL, color_themes INTEGER NOT NULL, FOREIGN KEY(wallpaper) REFERENCES wallpaper(ROWID), FOREIGN KEY(color_themes) REFERENCES color_themes(ROWID));
+        ";
+
+/// Columns added to the tables after they were first released, in the order they were added.
+///
+/// Every entry is one version of the schema: a database at version `n` has the columns of the first `n` entries.
+/// New columns must be appended here, and to [`CREATE_TABLES`], so existing caches are migrated to them.
+const MIGRATIONS: [(&str, &str, &str); 8] = [
+    ("wallpaper", "modified", "INTEGER NOT NULL DEFAULT 0"),
+    ("wallpaper", "content_hash", "TEXT NOT NULL DEFAULT ''"),
+    ("color_themes", "hueSteps", "INTEGER NOT NULL DEFAULT 0"),
+    ("color_themes", "paletteSize", "INTEGER NOT NULL DEFAULT 2"),
+    (
+        "color_themes",
+        "interpolation",
+        "TEXT NOT NULL DEFAULT 'srgb'",
+    ),
+    ("wallpaper", "analysis", "TEXT NOT NULL DEFAULT ''"),
+    ("color_themes", "includeSeed", "INTEGER NOT NULL DEFAULT 0"),
+    ("color_themes", "dominantPair", "INTEGER NOT NULL DEFAULT 0"),
+];
+
+/// Version of the schema of the cache database created by this version of the application.
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+/// Environment variable with the path of the cache database, used if [`Cli::cache_path`](crate::common::Cli::cache_path) is not given.
+pub const CACHE_PATH_VAR: &str = "COLOR_SCHEME_GENERATOR_CACHE";
+
+/// Get the path of the cache database inside of XDG_CACHE_HOME, creating its parent directory if needed.
+pub fn cache_path() -> Result<PathBuf, ColorSchemeError> {
+    let xdg_dirs = xdg::BaseDirectories::with_prefix(APP_NAME)?;
+    Ok(xdg_dirs.place_cache_file("cache.db")?)
+}
+
+/// Milliseconds a connection waits for another connection to release its lock on the database before erroring.
+pub const BUSY_TIMEOUT: usize = 5000;
+
+/// Times an operation on the database is retried by default if the database is locked, see [`retry`].
+pub const DEFAULT_RETRIES: u8 = 3;
+
+/// Time waited before the first retry of an operation on a locked database, doubled before every following retry.
+pub const RETRY_BACKOFF: Duration = Duration::from_millis(10);
+
+/// Run an operation on the database, retrying it up to `retries` times if it fails because the database is busy or locked.
+///
+/// # Notes
+/// Another connection holding a lock is normally waited for up to [`BUSY_TIMEOUT`], but sqlite gives up immediately
+/// when waiting could deadlock, e.g. when a reader of a write-ahead log tries to write after another connection did.
+/// The operation is retried after [`RETRY_BACKOFF`], doubled before every following retry, and the last error is returned
+/// if every retry fails. Any other error is returned immediately.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::ColorSchemeError;
+/// # use color_scheme_generator::database::retry;
+/// let locked = || sqlite::Error { code: Some(5), message: Some("database is locked".into()) };
+/// let mut attempts = 0;
+/// let result = retry(3, || {
+///     attempts += 1;
+///     match attempts {
+///         1 | 2 => Err(ColorSchemeError::Database(locked())),
+///         _ => Ok(attempts),
+///     }
+/// });
+/// assert_eq!(result.unwrap(), 3);
+///
+/// let mut attempts = 0;
+/// let result = retry(1, || -> Result<(), _> {
+///     attempts += 1;
+///     Err(ColorSchemeError::Database(locked()))
+/// });
+/// assert!(matches!(result, Err(ColorSchemeError::Database(_))));
+/// assert_eq!(attempts, 2);
+///
+/// let mut attempts = 0;
+/// let result = retry(3, || -> Result<(), _> {
+///     attempts += 1;
+///     Err(ColorSchemeError::NotFound)
+/// });
+/// assert!(matches!(result, Err(ColorSchemeError::NotFound)));
+/// assert_eq!(attempts, 1);
+/// ```
+pub fn retry<T, F>(retries: u8, mut operation: F) -> Result<T, ColorSchemeError>
+where
+    F: FnMut() -> Result<T, ColorSchemeError>,
+{
+    let mut backoff = RETRY_BACKOFF;
+    for _ in 0..retries {
+        match operation() {
+            Err(ColorSchemeError::Database(e)) if is_locked(&e) => {
+                debug!("Database is locked, retrying in {backoff:?}.");
+                std::thread::sleep(backoff);
+                backoff *= 2;
+            }
+            result => return result,
+        }
+    }
+    operation()
+}
+
+/// Whether a sqlite error is SQLITE_BUSY or SQLITE_LOCKED, including their extended codes.
+fn is_locked(error: &sqlite::Error) -> bool {
+    matches!(error.code.map(|code| code & 0xff), Some(5 | 6))
+}
 
 /// Hold a sqlite database connection.
 pub struct DatabaseConnection {
     connection: Connection,
+    retries: u8,
 }
 
 impl DatabaseConnection {
@@ -19,9 +136,17 @@ impl DatabaseConnection {
     /// This method creates a sqlite database with three tables: wallpaper, color_themes, and RGB which represent the [`Wallpaper`], [`ColorThemeOption`], and [`RGB`] respectively.
     /// Every color_themes record must have a valid wallpaper record attached to it and every RGB record must have a valid wallpaper and color_themes record attached to it.
     ///
+    /// The version of the schema is stored in the schema_version table. Databases created by older versions of the application
+    /// are migrated to [`SCHEMA_VERSION`] by adding the missing columns in order, keeping their records.
+    ///
+    /// The database uses write-ahead logging and waits up to [`BUSY_TIMEOUT`] for locks,
+    /// so several instances of the application can use the cache at the same time.
+    /// Operations that still fail because the database is locked are retried [`DEFAULT_RETRIES`] times, see [`retry`].
+    ///
     /// # Errors
     ///
     /// If the database file cannot be created, albeit due to insufficient permissions or an invalid path, the method will throw an error.
+    /// Will also error if the database cannot be migrated.
     ///
     /// # Examples
     /// ```
@@ -30,15 +155,216 @@ impl DatabaseConnection {
     /// # let cache_path = ":memory:".parse::<PathBuf>().unwrap();
     /// let database_connection = DatabaseConnection::new(&cache_path).unwrap();
     /// ```
-    pub fn new(path: &PathBuf) -> anyhow::Result<DatabaseConnection> {
-        let conn = sqlite::open(path)?;
-        let query = "
-        CREATE TABLE IF NOT EXISTS wallpaper(path TEXT NOT NULL, centrality TEXT NOT NULL);
-        CREATE TABLE IF NOT EXISTS color_themes(darker INTEGER NOT NULL, lighter INTEGER NOT NULL, complementary INTEGER NOT NULL, contrast INTEGER NOT NULL, hueOffset INTEGER NOT NULL, triadic INTEGER NOT NULL, quadratic INTEGER NOT NULL, tetratic INTEGER NOT NULL, analogous INTEGER NOT NULL, splitComplementary INTEGER NOT NULL, monochromatic INTEGER NOT NULL, shades INTEGER NOT NULL, tints INTEGER NOT NULL, tones INTEGER NOT NULL, blends INTEGER NOT NULL, wallpaper INTEGER NOT NULL, FOREIGN KEY(wallpaper) REFERENCES wallpaper(ROWID));
-        CREATE TABLE IF NOT EXISTS RGB(RGB TEXT NOT NULL, wallpaper INTEGER NOT NULL, color_themes INTEGER NOT NULL, FOREIGN KEY(wallpaper) REFERENCES wallpaper(ROWID), FOREIGN KEY(color_themes) REFERENCES color_themes(ROWID));
-        ";
-        conn.execute(query)?;
-        Ok(DatabaseConnection { connection: conn })
+    ///
+    /// Concurrent connections to the same file wait for each other instead of failing.
+    /// ```
+    /// # use color_scheme_generator::database::DatabaseConnection;
+    /// # use color_scheme_generator::common::{Wallpaper, Centrality};
+    /// let cache_path = std::env::temp_dir().join("color_scheme_generator_concurrent.db");
+    /// # let _ = std::fs::remove_file(&cache_path);
+    /// let threads = ["first.png", "second.png"].map(|path| {
+    ///     let cache_path = cache_path.clone();
+    ///     std::thread::spawn(move || {
+    ///         let database_connection = DatabaseConnection::new(&cache_path)?;
+    ///         let wallpaper = Wallpaper {path : path.into(), centrality: Centrality::Median, modified: 0, content_hash: String::new(), analysis: String::new()};
+    ///         database_connection.insert_wallpaper_record(&wallpaper)
+    ///     })
+    /// });
+    /// for thread in threads {
+    ///     thread.join().unwrap().unwrap();
+    /// }
+    /// ```
+    pub fn new(path: &PathBuf) -> Result<DatabaseConnection, ColorSchemeError> {
+        DatabaseConnection::with_retries(path, DEFAULT_RETRIES)
+    }
+
+    /// Create database cache file and connect to it, retrying every operation up to `retries` times if the database is locked.
+    ///
+    /// # Notes
+    /// Behaves like [`DatabaseConnection::new`], see [`retry`] for when an operation is retried.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::path::PathBuf;
+    /// # use color_scheme_generator::database::DatabaseConnection;
+    /// # let cache_path = ":memory:".parse::<PathBuf>().unwrap();
+    /// let database_connection = DatabaseConnection::with_retries(&cache_path, 0).unwrap();
+    /// database_connection.clear_cache().unwrap();
+    /// ```
+    pub fn with_retries(
+        path: &PathBuf,
+        retries: u8,
+    ) -> Result<DatabaseConnection, ColorSchemeError> {
+        let mut conn = sqlite::open(path)?;
+        conn.set_busy_timeout(BUSY_TIMEOUT)?;
+        retry(retries, || {
+            conn.execute("PRAGMA journal_mode=WAL;")?;
+            conn.execute(CREATE_TABLES)?;
+            Ok(())
+        })?;
+        let database_connection = DatabaseConnection {
+            connection: conn,
+            retries,
+        };
+        database_connection.migrate()?;
+        Ok(database_connection)
+    }
+
+    /// Get the version of the schema of the database, 0 for a database created before the schema was versioned.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::path::PathBuf;
+    /// # use color_scheme_generator::database::{DatabaseConnection, SCHEMA_VERSION};
+    /// # let cache_path = ":memory:".parse::<PathBuf>().unwrap();
+    /// let database_connection = DatabaseConnection::new(&cache_path).unwrap();
+    /// assert_eq!(database_connection.schema_version().unwrap(), SCHEMA_VERSION);
+    /// ```
+    ///
+    /// A database created before the schema was versioned is migrated without losing its records.
+    /// ```
+    /// # use clap::Parser;
+    /// # use color_scheme_generator::common::{Centrality, Cli, Wallpaper, RGB};
+    /// # use color_scheme_generator::database::{DatabaseConnection, SCHEMA_VERSION};
+    /// let cache_path = std::env::temp_dir().join("color_scheme_generator_old_schema.db");
+    /// # let _ = std::fs::remove_file(&cache_path);
+    /// let old = sqlite::open(&cache_path).unwrap();
+    /// old.execute("
+    ///     CREATE TABLE wallpaper(path TEXT NOT NULL, centrality TEXT NOT NULL);
+    ///     CREATE TABLE color_themes(darker INTEGER NOT NULL, lighter INTEGER NOT NULL, complementary INTEGER NOT NULL, contrast INTEGER NOT NULL, hueOffset INTEGER NOT NULL, triadic INTEGER NOT NULL, quadratic INTEGER NOT NULL, tetratic INTEGER NOT NULL, analogous INTEGER NOT NULL, splitComplementary INTEGER NOT NULL, monochromatic INTEGER NOT NULL, shades INTEGER NOT NULL, tints INTEGER NOT NULL, tones INTEGER NOT NULL, blends INTEGER NOT NULL, wallpaper INTEGER NOT NULL, FOREIGN KEY(wallpaper) REFERENCES wallpaper(ROWID));
+    ///     CREATE TABLE RGB(RGB TEXT NOT NULL, wallpaper INTEGER NOT NULL, color_themes INTEGER NOT NULL, FOREIGN KEY(wallpaper) REFERENCES wallpaper(ROWID), FOREIGN KEY(color_themes) REFERENCES color_themes(ROWID));
+    ///     INSERT INTO wallpaper VALUES ('old.png', 'median');
+    ///     INSERT INTO color_themes VALUES (0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1);
+    ///     INSERT INTO RGB VALUES ('#010203', 1, 1);
+    /// ").unwrap();
+    /// drop(old);
+    ///
+    /// let database_connection = DatabaseConnection::new(&cache_path).unwrap();
+    /// assert_eq!(database_connection.schema_version().unwrap(), SCHEMA_VERSION);
+    /// let wallpaper = Wallpaper {path: "old.png".into(), centrality: Centrality::Median, modified: 0, content_hash: String::new(), analysis: String::new()};
+    /// // Blends were interpolated in sRGB before the interpolation could be chosen.
+    /// let ct = Cli::parse_from(["color_scheme_generator", "old.png", "--triadic", "--interpolation", "srgb"]).color_themes;
+    /// assert_eq!(database_connection.select_rgb_records(&wallpaper, &ct).unwrap(), vec![RGB { red: 1, green: 2, blue: 3 }]);
+    /// drop(database_connection);
+    /// assert_eq!(DatabaseConnection::new(&cache_path).unwrap().schema_version().unwrap(), SCHEMA_VERSION);
+    /// ```
+    pub fn schema_version(&self) -> Result<i64, ColorSchemeError> {
+        retry(self.retries, || {
+            let row = self
+                .connection
+                .prepare("SELECT COALESCE(MAX(version), 0) AS version FROM schema_version")?
+                .into_iter()
+                .collect::<Result<Vec<_>, _>>()?;
+            self.get_database_column::<i64>(&row, "version")
+        })
+    }
+
+    /// Apply the [`MIGRATIONS`] after the version of the schema of the database in order, in one transaction.
+    ///
+    /// # Notes
+    /// A column that already exists is not added again, since databases created before the schema was versioned
+    /// may have some of the columns already.
+    fn migrate(&self) -> Result<(), ColorSchemeError> {
+        retry(self.retries, || {
+            self.connection
+                .execute("CREATE TABLE IF NOT EXISTS schema_version(version INTEGER NOT NULL);")?;
+            self.connection.execute("BEGIN IMMEDIATE;")?;
+            let migrated = self.schema_version().and_then(|version| {
+                let applied = usize::try_from(version).unwrap_or_default();
+                for (table, column, definition) in MIGRATIONS.iter().skip(applied) {
+                    if !self.column_exists(table, column)? {
+                        self.connection.execute(format!(
+                            "ALTER TABLE {table} ADD COLUMN {column} {definition}"
+                        ))?;
+                    }
+                }
+                if version < SCHEMA_VERSION {
+                    self.connection.execute(format!(
+                        "DELETE FROM schema_version; INSERT INTO schema_version(version) VALUES ({SCHEMA_VERSION});"
+                    ))?;
+                }
+                Ok(())
+            });
+            match migrated {
+                Ok(()) => self.connection.execute("COMMIT;")?,
+                Err(_) => self.connection.execute("ROLLBACK;")?,
+            }
+            migrated
+        })
+    }
+
+    /// Run an operation on the database in one transaction, which is committed if the operation succeeds and rolled back otherwise.
+    ///
+    /// # Notes
+    /// The color theme of an image is stored in a transaction so that a process killed while storing it,
+    /// such as by Ctrl-C in watch mode, does not leave part of the color theme in the cache.
+    ///
+    /// # Errors
+    /// Will error if the transaction cannot be started, committed or rolled back, or with the error of the operation.
+    ///
+    /// # Examples
+    /// ```
+    /// # use color_scheme_generator::common::{Centrality, ColorSchemeError, Wallpaper};
+    /// # use color_scheme_generator::database::DatabaseConnection;
+    /// let conn = DatabaseConnection::new(&":memory:".into()).unwrap();
+    /// let wallpaper = Wallpaper { path: "/tmp/wallpaper.png".into(), centrality: Centrality::Median, modified: 0, content_hash: String::new(), analysis: String::new() };
+    /// let failed = conn.transaction(|| {
+    ///     conn.insert_wallpaper_record(&wallpaper)?;
+    ///     Err::<(), _>(ColorSchemeError::NotFound)
+    /// });
+    /// assert!(failed.is_err());
+    /// assert!(conn.select_wallpaper_record(&wallpaper).is_err());
+    /// conn.transaction(|| conn.insert_wallpaper_record(&wallpaper)).unwrap();
+    /// assert!(conn.select_wallpaper_record(&wallpaper).is_ok());
+    /// ```
+    pub fn transaction<T, F>(&self, operation: F) -> Result<T, ColorSchemeError>
+    where
+        F: FnOnce() -> Result<T, ColorSchemeError>,
+    {
+        retry(self.retries, || {
+            Ok(self.connection.execute("BEGIN IMMEDIATE;")?)
+        })?;
+        let result = operation();
+        match result {
+            Ok(_) => self.connection.execute("COMMIT;")?,
+            Err(_) => self.connection.execute("ROLLBACK;")?,
+        }
+        result
+    }
+
+    /// Remove every record from the database by dropping and recreating the wallpaper, color_themes, and RGB tables.
+    ///
+    /// # Notes
+    /// This method is safe to call on a freshly created database.
+    ///
+    /// # Errors
+    /// Will error if the tables cannot be dropped or recreated.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::path::PathBuf;
+    /// # use color_scheme_generator::database::DatabaseConnection;
+    /// # use color_scheme_generator::common::{Wallpaper, Centrality};
+    /// # let cache_path = ":memory:".parse::<PathBuf>().unwrap();
+    /// let database_connection = DatabaseConnection::new(&cache_path).unwrap();
+    /// database_connection.clear_cache().unwrap();
+    /// # let wallpaper = Wallpaper {path : "text".parse::<PathBuf>().unwrap(), centrality: Centrality::Prevalent, modified: 0, content_hash: String::new(), analysis: String::new()};
+    /// database_connection.insert_wallpaper_record(&wallpaper).unwrap();
+    /// database_connection.clear_cache().unwrap();
+    /// assert!(database_connection.select_wallpaper_record(&wallpaper).is_err());
+    /// ```
+    pub fn clear_cache(&self) -> Result<(), ColorSchemeError> {
+        retry(self.retries, || {
+            let query = "
+            DROP TABLE IF EXISTS coverage;
+            DROP TABLE IF EXISTS RGB;
+            DROP TABLE IF EXISTS color_themes;
+            DROP TABLE IF EXISTS wallpaper;
+            ";
+            self.connection.execute(query)?;
+            self.connection.execute(CREATE_TABLES)?;
+            Ok(())
+        })
     }
 
     /// Insert a wallpaper record into the database
@@ -54,76 +380,218 @@ impl DatabaseConnection {
     /// # use color_scheme_generator::common::{Wallpaper, Centrality};
     /// # let cache_path = ":memory:".parse::<PathBuf>().unwrap();
     /// let database_connection = DatabaseConnection::new(&cache_path).unwrap();
-    /// # let wallpaper = Wallpaper {path : "text".parse::<PathBuf>().unwrap(), centrality: Centrality::Prevalent};
+    /// # let wallpaper = Wallpaper {path : "text".parse::<PathBuf>().unwrap(), centrality: Centrality::Prevalent, modified: 0, content_hash: String::new(), analysis: String::new()};
     /// database_connection.insert_wallpaper_record(&wallpaper).unwrap();
     /// ```
-    pub fn insert_wallpaper_record(&self, wallpaper: &Wallpaper) -> anyhow::Result<()> {
-        let query = format!(
-            "INSERT INTO wallpaper(path, centrality) VALUES ('{}', '{}')",
-            wallpaper.path.to_str().ok_or(std::fmt::Error)?,
-            wallpaper.centrality
-        );
-        self.connection.execute(query)?;
-        Ok(())
+    pub fn insert_wallpaper_record(&self, wallpaper: &Wallpaper) -> Result<(), ColorSchemeError> {
+        retry(self.retries, || {
+            let query =
+                "INSERT INTO wallpaper(path, centrality, analysis, modified, content_hash) VALUES (?, ?, ?, ?, ?)";
+            let mut statement = self.connection.prepare(query)?;
+            let [path, centrality, analysis] = wallpaper_key(wallpaper)?;
+            statement.bind(
+                &[
+                    path,
+                    centrality,
+                    analysis,
+                    Value::from(wallpaper.modified),
+                    Value::from(wallpaper.content_hash.as_str()),
+                ][..],
+            )?;
+            statement.next()?;
+            Ok(())
+        })
     }
 
     /// Select a wallpaper record  from the database.
     ///
+    /// # Notes
+    /// The record is matched by path, centrality and [`Wallpaper::analysis`] only, the returned [`Wallpaper::modified`] is the one stored in the database
+    /// so it can be compared against the image to detect stale color themes.
+    ///
     /// # Errors
     ///
-    /// Will error if the record is not found in the database.
+    /// Will error with [`ColorSchemeError::NotFound`] if the record is not found in the database.
     ///
     /// # Examples
     /// ```
     /// # use std::path::PathBuf;
     /// # use color_scheme_generator::database::DatabaseConnection;
-    /// # use color_scheme_generator::common::{Wallpaper, Centrality};
+    /// # use color_scheme_generator::common::{ColorSchemeError, Wallpaper, Centrality};
     /// # let cache_path = ":memory:".parse::<PathBuf>().unwrap();
     /// let database_connection = DatabaseConnection::new(&cache_path).unwrap();
-    /// # let wallpaper = Wallpaper {path : "text".parse::<PathBuf>().unwrap(), centrality: Centrality::Prevalent};
+    /// # let wallpaper = Wallpaper {path : "text".parse::<PathBuf>().unwrap(), centrality: Centrality::Prevalent, modified: 0, content_hash: String::new(), analysis: String::new()};
+    /// assert!(matches!(database_connection.select_wallpaper_record(&wallpaper), Err(ColorSchemeError::NotFound)));
     /// # database_connection.insert_wallpaper_record(&wallpaper).unwrap();
     /// # let wallpaper_record = database_connection.select_wallpaper_record(&wallpaper).unwrap();
+    /// let quoted = Wallpaper {path : r"Ryan's wall\paper.png".parse::<PathBuf>().unwrap(), centrality: Centrality::Median, modified: 42, content_hash: String::new(), analysis: String::new()};
+    /// database_connection.insert_wallpaper_record(&quoted).unwrap();
+    /// let (record, _) = database_connection.select_wallpaper_record(&quoted).unwrap();
+    /// assert_eq!(record.path, quoted.path);
+    /// assert_eq!(record.modified, 42);
     /// ```
     pub fn select_wallpaper_record(
         &self,
         wallpaper: &Wallpaper,
-    ) -> anyhow::Result<(Wallpaper, i64)> {
-        let query = format!(
-            "SELECT path, centrality, ROWID as PK FROM wallpaper where path = '{}' AND centrality = '{}'",
-            wallpaper.path.to_str().ok_or(std::fmt::Error)?,
-            wallpaper.centrality
-        );
-        let row = self
-            .connection
-            .prepare(&query)?
-            .into_iter()
-            .map(|r| r.unwrap())
-            .collect::<Vec<_>>();
-        let path = self
-            .get_database_column::<&str>(&row, "path")
-            .iter()
-            .map(PathBuf::from)
-            .collect::<Vec<_>>()
-            .first()
-            .ok_or(std::fmt::Error)?
-            .to_owned();
-        let centrality = self.get_database_column::<&str>(&row, "centrality")?;
-        let centrality = Centrality::from_str(centrality)?;
-        let rowid = row
-            .iter()
-            .map(|r| r.read::<i64, _>("PK"))
-            .collect::<Vec<_>>()
-            .first()
-            .ok_or(std::fmt::Error)?
-            .to_owned();
-        Ok((Wallpaper { path, centrality }, rowid))
+    ) -> Result<(Wallpaper, i64), ColorSchemeError> {
+        retry(self.retries, || {
+            let query = "SELECT path, centrality, analysis, modified, content_hash, ROWID as PK FROM wallpaper where path = ? AND centrality = ? AND analysis = ?";
+            let row = self
+                .connection
+                .prepare(query)?
+                .into_iter()
+                .bind(&wallpaper_key(wallpaper)?[..])?
+                .collect::<Result<Vec<_>, _>>()?;
+            wallpaper_from_row(row.first().ok_or(ColorSchemeError::NotFound)?)
+        })
+    }
+
+    /// Select a wallpaper record with the same [`Wallpaper::content_hash`], centrality and [`Wallpaper::analysis`], regardless of its path.
+    ///
+    /// # Notes
+    /// This finds the color themes of copies, symlinks and moved images that have already been analyzed under another path.
+    /// A [`Wallpaper`] with an empty content hash never matches.
+    ///
+    /// # Errors
+    ///
+    /// Will error if no record is found in the database.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::path::PathBuf;
+    /// # use color_scheme_generator::database::DatabaseConnection;
+    /// # use color_scheme_generator::common::{Wallpaper, Centrality};
+    /// # let cache_path = ":memory:".parse::<PathBuf>().unwrap();
+    /// let database_connection = DatabaseConnection::new(&cache_path).unwrap();
+    /// let original = Wallpaper {path : "original.png".into(), centrality: Centrality::Median, modified: 0, content_hash: "cbf29ce484222325".into(), analysis: String::new()};
+    /// database_connection.insert_wallpaper_record(&original).unwrap();
+    /// let copy = Wallpaper {path : "copy.png".into(), ..original};
+    /// let (record, _) = database_connection.select_wallpaper_by_hash(&copy).unwrap();
+    /// assert_eq!(record.path, PathBuf::from("original.png"));
+    /// let other = Wallpaper {centrality: Centrality::Average, ..copy};
+    /// assert!(database_connection.select_wallpaper_by_hash(&other).is_err());
+    /// ```
+    pub fn select_wallpaper_by_hash(
+        &self,
+        wallpaper: &Wallpaper,
+    ) -> Result<(Wallpaper, i64), ColorSchemeError> {
+        retry(self.retries, || {
+            let query = "SELECT path, centrality, analysis, modified, content_hash, ROWID as PK FROM wallpaper where content_hash = ? AND content_hash != '' AND centrality = ? AND analysis = ? ORDER BY ROWID";
+            let [_, centrality, analysis] = wallpaper_key(wallpaper)?;
+            let row = self
+                .connection
+                .prepare(query)?
+                .into_iter()
+                .bind(
+                    &[
+                        Value::from(wallpaper.content_hash.as_str()),
+                        centrality,
+                        analysis,
+                    ][..],
+                )?
+                .collect::<Result<Vec<_>, _>>()?;
+            wallpaper_from_row(row.first().ok_or(ColorSchemeError::NotFound)?)
+        })
+    }
+
+    /// List every cached wallpaper record along with the number of color themes cached for it, in insertion order.
+    ///
+    /// # Notes
+    /// Only color_themes records with at least one RGB record are counted.
+    ///
+    /// # Errors
+    /// Will error if the records cannot be read from the database.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::path::PathBuf;
+    /// # use clap::Parser;
+    /// # use color_scheme_generator::database::DatabaseConnection;
+    /// # use color_scheme_generator::common::{Centrality, Cli, Wallpaper, RGB};
+    /// # let cache_path = ":memory:".parse::<PathBuf>().unwrap();
+    /// let database_connection = DatabaseConnection::new(&cache_path).unwrap();
+    /// let first = Wallpaper {path : "first.png".into(), centrality: Centrality::Median, modified: 0, content_hash: String::new(), analysis: String::new()};
+    /// let second = Wallpaper {path : "second.png".into(), centrality: Centrality::Average, modified: 0, content_hash: String::new(), analysis: String::new()};
+    /// let color = RGB { red: 0, green: 0, blue: 0 };
+    /// database_connection.insert_wallpaper_record(&first).unwrap();
+    /// database_connection.insert_wallpaper_record(&second).unwrap();
+    /// for args in [["color_scheme_generator", "first.png", "--triadic"], ["color_scheme_generator", "first.png", "--analogous"]] {
+    ///     let ct = Cli::parse_from(args).color_themes;
+    ///     database_connection.insert_color_themes_record(&ct, &first).unwrap();
+    ///     database_connection.insert_rgb_record(&color, &first, &ct).unwrap();
+    ///     database_connection.insert_rgb_record(&color, &first, &ct).unwrap();
+    /// }
+    /// let listing = database_connection.list_cached_wallpapers().unwrap();
+    /// assert_eq!(listing.len(), 2);
+    /// assert_eq!((&listing[0].0.path, listing[0].1), (&first.path, 2));
+    /// assert_eq!((&listing[1].0.path, listing[1].1), (&second.path, 0));
+    /// ```
+    pub fn list_cached_wallpapers(&self) -> Result<Vec<(Wallpaper, usize)>, ColorSchemeError> {
+        retry(self.retries, || {
+            let query = "SELECT wallpaper.path, wallpaper.centrality, wallpaper.analysis, wallpaper.modified, wallpaper.content_hash, wallpaper.ROWID AS PK, COUNT(DISTINCT RGB.color_themes) AS themes
+                         FROM wallpaper
+                         LEFT JOIN color_themes ON color_themes.wallpaper = wallpaper.ROWID
+                         LEFT JOIN RGB ON RGB.color_themes = color_themes.ROWID
+                         GROUP BY wallpaper.ROWID
+                         ORDER BY wallpaper.ROWID";
+            self.connection
+                .prepare(query)?
+                .into_iter()
+                .map(|row| {
+                    let row = row?;
+                    let (wallpaper, _) = wallpaper_from_row(&row)?;
+                    Ok((
+                        wallpaper,
+                        usize::try_from(row.try_read::<i64, _>("themes")?)?,
+                    ))
+                })
+                .collect()
+        })
+    }
+
+    /// Delete every wallpaper record matching the path, centrality and [`Wallpaper::analysis`] along with its color_themes, RGB and coverage records.
+    ///
+    /// # Notes
+    /// Deleting a [`Wallpaper`] that is not in the database is not an error.
+    ///
+    /// # Errors
+    /// Should only error only if the path cannot be converted to a [`&str`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::path::PathBuf;
+    /// # use color_scheme_generator::database::DatabaseConnection;
+    /// # use color_scheme_generator::common::{Wallpaper, Centrality};
+    /// # let cache_path = ":memory:".parse::<PathBuf>().unwrap();
+    /// let database_connection = DatabaseConnection::new(&cache_path).unwrap();
+    /// # let wallpaper = Wallpaper {path : "text".parse::<PathBuf>().unwrap(), centrality: Centrality::Prevalent, modified: 0, content_hash: String::new(), analysis: String::new()};
+    /// database_connection.insert_wallpaper_record(&wallpaper).unwrap();
+    /// database_connection.delete_wallpaper_record(&wallpaper).unwrap();
+    /// assert!(database_connection.select_wallpaper_record(&wallpaper).is_err());
+    /// ```
+    pub fn delete_wallpaper_record(&self, wallpaper: &Wallpaper) -> Result<(), ColorSchemeError> {
+        retry(self.retries, || {
+            let wallpapers =
+                "SELECT ROWID FROM wallpaper WHERE path = ? AND centrality = ? AND analysis = ?";
+            for query in [
+                format!("DELETE FROM coverage WHERE wallpaper IN ({wallpapers})"),
+                format!("DELETE FROM RGB WHERE wallpaper IN ({wallpapers})"),
+                format!("DELETE FROM color_themes WHERE wallpaper IN ({wallpapers})"),
+                format!("DELETE FROM wallpaper WHERE ROWID IN ({wallpapers})"),
+            ] {
+                let mut statement = self.connection.prepare(query)?;
+                statement.bind(&wallpaper_key(wallpaper)?[..])?;
+                statement.next()?;
+            }
+            Ok(())
+        })
     }
 
     /// Insert a color_theme record into the database.
     ///
     /// # Notes
     /// The [`Wallpaper`] must be inserted into the database before a [`ColorThemeOption`] record can be successfully inserted since the [`Wallpaper`] ROWID is referenced by a [`ColorThemeOption`] record.
-    /// The [`ColorThemeOption`] struct must have only 1 field that is not a default value. call_gamut_cli depends on this struct being constructed correctly. Clap and main take care of this normally but special care is needed when interacting with this struct directly.
+    /// Every field of the [`ColorThemeOption`] is stored so each combination of options is cached separately.
     ///
     /// # Errors
     /// Will error if a [`Wallpaper`] record cannot be found inside the database.
@@ -135,7 +603,7 @@ impl DatabaseConnection {
     /// # use color_scheme_generator::common::{Wallpaper, Centrality, ColorThemeOption};
     /// # let cache_path = ":memory:".parse::<PathBuf>().unwrap();
     /// let database_connection = DatabaseConnection::new(&cache_path).unwrap();
-    /// # let wallpaper = Wallpaper {path : "text".parse::<PathBuf>().unwrap(), centrality: Centrality::Prevalent};
+    /// # let wallpaper = Wallpaper {path : "text".parse::<PathBuf>().unwrap(), centrality: Centrality::Prevalent, modified: 0, content_hash: String::new(), analysis: String::new()};
     /// # database_connection.insert_wallpaper_record(&wallpaper).unwrap();
     /// # let color_themes = ColorThemeOption {
     /// #   darker: 0,
@@ -153,6 +621,11 @@ impl DatabaseConnection {
     /// #   tints: 0,
     /// #   tones: 0,
     /// #   blends: 0,
+    /// #   interpolation: Default::default(),
+    /// #   hue_steps: 0,
+    /// #   palette_size: 2,
+    /// #   include_seed: false,
+    /// #   dominant_pair: false,
     /// # };
     /// database_connection.insert_color_themes_record(&color_themes, &wallpaper).unwrap();
     /// ```
@@ -160,45 +633,36 @@ impl DatabaseConnection {
         &self,
         ct: &ColorThemeOption,
         wallpaper: &Wallpaper,
-    ) -> anyhow::Result<()> {
-        let query = format!(
-            "INSERT INTO color_themes
-                                        (darker, 
-                                        lighter,
-                                        complementary,
-                                        contrast,
-                                        hueOffset,
-                                        triadic,
-                                        quadratic,
-                                        tetratic,
-                                        analogous,
-                                        splitComplementary,
-                                        monochromatic,
-                                        shades,
-                                        tints,
-                                        tones,
-                                        blends,
-                                        wallpaper) VALUES
-                                        ({},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{})",
-            ct.darker,
-            ct.lighter,
-            ct.complementary,
-            ct.contrast,
-            ct.hue_offset,
-            ct.triadic,
-            ct.quadratic,
-            ct.tetratic,
-            ct.analogous,
-            ct.split_complementary,
-            ct.monochromatic,
-            ct.shades,
-            ct.tints,
-            ct.tones,
-            ct.blends,
-            self.select_wallpaper_record(wallpaper)?.1
-        );
-        self.connection.execute(query)?;
-        Ok(())
+    ) -> Result<(), ColorSchemeError> {
+        retry(self.retries, || {
+            let query = "INSERT INTO color_themes
+                                            (darker,
+                                            lighter,
+                                            complementary,
+                                            contrast,
+                                            hueOffset,
+                                            triadic,
+                                            quadratic,
+                                            tetratic,
+                                            analogous,
+                                            splitComplementary,
+                                            monochromatic,
+                                            shades,
+                                            tints,
+                                            tones,
+                                            blends,
+                                            interpolation,
+                                            hueSteps,
+                                            paletteSize,
+                                            includeSeed,
+                                            dominantPair,
+                                            wallpaper) VALUES
+                                            (?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?)";
+            let mut statement = self.connection.prepare(query)?;
+            statement.bind(&self.color_themes_parameters(ct, wallpaper)?[..])?;
+            statement.next()?;
+            Ok(())
+        })
     }
 
     /// Select [`ColorThemeOption`] record from the database.
@@ -216,7 +680,7 @@ impl DatabaseConnection {
     /// # use color_scheme_generator::common::{Wallpaper, Centrality, ColorThemeOption};
     /// # let cache_path = ":memory:".parse::<PathBuf>().unwrap();
     /// let database_connection = DatabaseConnection::new(&cache_path).unwrap();
-    /// # let wallpaper = Wallpaper {path : "text".parse::<PathBuf>().unwrap(), centrality: Centrality::Prevalent};
+    /// # let wallpaper = Wallpaper {path : "text".parse::<PathBuf>().unwrap(), centrality: Centrality::Prevalent, modified: 0, content_hash: String::new(), analysis: String::new()};
     /// # database_connection.insert_wallpaper_record(&wallpaper).unwrap();
     /// # let color_themes = ColorThemeOption {
     /// #   darker: 0,
@@ -234,74 +698,86 @@ impl DatabaseConnection {
     /// #   tints: 0,
     /// #   tones: 0,
     /// #   blends: 0,
+    /// #   interpolation: Default::default(),
+    /// #   hue_steps: 0,
+    /// #   palette_size: 2,
+    /// #   include_seed: false,
+    /// #   dominant_pair: false,
     /// # };
     /// # database_connection.insert_color_themes_record(&color_themes, &wallpaper).unwrap();
     /// database_connection.select_color_themes_record(&color_themes, &wallpaper).unwrap();
+    /// let monochromatic = ColorThemeOption { quadratic: false, monochromatic: 5, lighter: 2, ..color_themes };
+    /// database_connection.insert_color_themes_record(&monochromatic, &wallpaper).unwrap();
+    /// let (record, _) = database_connection.select_color_themes_record(&monochromatic, &wallpaper).unwrap();
+    /// assert_eq!(record.monochromatic, 5);
+    /// assert_eq!(record.lighter, 2);
     /// ```
     pub fn select_color_themes_record(
         &self,
         ct: &ColorThemeOption,
         wallpaper: &Wallpaper,
-    ) -> anyhow::Result<(ColorThemeOption, i64)> {
-        let query = format!(
-            "SELECT darker, lighter, complementary, contrast, hueOffset, triadic, quadratic, tetratic, analogous, splitComplementary, monochromatic, shades, tints, tones, blends, ROWID as PK FROM color_themes WHERE darker = {} AND 
-                                        lighter = {} AND
-                                        complementary = {} AND
-                                        contrast = {} AND
-                                        hueOffset = {} AND
-                                        triadic = {} AND
-                                        quadratic = {} AND
-                                        tetratic = {} AND
-                                        analogous = {} AND
-                                        splitComplementary = {} AND
-                                        monochromatic = {} AND
-                                        shades = {} AND
-                                        tints = {} AND
-                                        tones = {} AND
-                                        blends = {} AND
-                                        wallpaper = {}",
-            ct.darker,
-            ct.lighter,
-            ct.complementary,
-            ct.contrast,
-            ct.hue_offset,
-            ct.triadic,
-            ct.quadratic,
-            ct.tetratic,
-            ct.analogous,
-            ct.split_complementary,
-            ct.monochromatic,
-            ct.shades,
-            ct.tints,
-            ct.tones,
-            ct.blends,
-            self.select_wallpaper_record(wallpaper)?.1
-        );
-        let row = self
-            .connection
-            .prepare(&query)?
-            .into_iter()
-            .map(|r| r.unwrap())
-            .collect::<Vec<_>>();
-        let color_themes = ColorThemeOption {
-            darker: u8::try_from(self.get_database_column::<i64>(&row, "darker")?)?,
-            lighter: u8::try_from(self.get_database_column::<i64>(&row, "lighter")?)?,
-            complementary: i64_to_bool(self.get_database_column(&row, "complementary")?),
-            contrast: i64_to_bool(self.get_database_column(&row, "contrast")?),
-            hue_offset: u16::try_from(self.get_database_column::<i64>(&row, "hueOffset")?)?,
-            triadic: i64_to_bool(self.get_database_column(&row, "triadic")?),
-            quadratic: i64_to_bool(self.get_database_column(&row, "quadratic")?),
-            tetratic: i64_to_bool(self.get_database_column(&row, "tetratic")?),
-            analogous: i64_to_bool(self.get_database_column(&row, "analogous")?),
-            split_complementary: i64_to_bool(self.get_database_column(&row, "splitComplementary")?),
-            monochromatic: u8::try_from(self.get_database_column::<i64>(&row, "lighter")?)?,
-            shades: u8::try_from(self.get_database_column::<i64>(&row, "shades")?)?,
-            tints: u8::try_from(self.get_database_column::<i64>(&row, "tints")?)?,
-            tones: u8::try_from(self.get_database_column::<i64>(&row, "tones")?)?,
-            blends: u8::try_from(self.get_database_column::<i64>(&row, "blends")?)?,
-        };
-        let rowid = self.get_database_column::<i64>(&row, "PK")?;
-        Ok((color_themes, rowid))
+    ) -> Result<(ColorThemeOption, i64), ColorSchemeError> {
+        retry(self.retries, || {
+            let query = "SELECT darker, lighter, complementary, contrast, hueOffset, triadic, quadratic, tetratic, analogous, splitComplementary, monochromatic, shades, tints, tones, blends, interpolation, hueSteps, paletteSize, includeSeed, dominantPair, ROWID as PK FROM color_themes WHERE darker = ? AND
+                                            lighter = ? AND
+                                            complementary = ? AND
+                                            contrast = ? AND
+                                            hueOffset = ? AND
+                                            triadic = ? AND
+                                            quadratic = ? AND
+                                            tetratic = ? AND
+                                            analogous = ? AND
+                                            splitComplementary = ? AND
+                                            monochromatic = ? AND
+                                            shades = ? AND
+                                            tints = ? AND
+                                            tones = ? AND
+                                            blends = ? AND
+                                            interpolation = ? AND
+                                            hueSteps = ? AND
+                                            paletteSize = ? AND
+                                            includeSeed = ? AND
+                                            dominantPair = ? AND
+                                            wallpaper = ?";
+            let row = self
+                .connection
+                .prepare(query)?
+                .into_iter()
+                .bind(&self.color_themes_parameters(ct, wallpaper)?[..])?
+                .collect::<Result<Vec<_>, _>>()?;
+            let color_themes = ColorThemeOption {
+                darker: u8::try_from(self.get_database_column::<i64>(&row, "darker")?)?,
+                lighter: u8::try_from(self.get_database_column::<i64>(&row, "lighter")?)?,
+                complementary: i64_to_bool(self.get_database_column(&row, "complementary")?),
+                contrast: i64_to_bool(self.get_database_column(&row, "contrast")?),
+                hue_offset: u16::try_from(self.get_database_column::<i64>(&row, "hueOffset")?)?,
+                triadic: i64_to_bool(self.get_database_column(&row, "triadic")?),
+                quadratic: i64_to_bool(self.get_database_column(&row, "quadratic")?),
+                tetratic: i64_to_bool(self.get_database_column(&row, "tetratic")?),
+                analogous: i64_to_bool(self.get_database_column(&row, "analogous")?),
+                split_complementary: i64_to_bool(
+                    self.get_database_column(&row, "splitComplementary")?,
+                ),
+                monochromatic: u8::try_from(
+                    self.get_database_column::<i64>(&row, "monochromatic")?,
+                )?,
+                shades: u8::try_from(self.get_database_column::<i64>(&row, "shades")?)?,
+                tints: u8::try_from(self.get_database_column::<i64>(&row, "tints")?)?,
+                tones: u8::try_from(self.get_database_column::<i64>(&row, "tones")?)?,
+                blends: u8::try_from(self.get_database_column::<i64>(&row, "blends")?)?,
+                interpolation: Interpolation::from_str(
+                    row.first()
+                        .ok_or(ColorSchemeError::NotFound)?
+                        .try_read::<&str, _>("interpolation")?,
+                )?,
+                hue_steps: u8::try_from(self.get_database_column::<i64>(&row, "hueSteps")?)?,
+                palette_size: u8::try_from(self.get_database_column::<i64>(&row, "paletteSize")?)?,
+                include_seed: i64_to_bool(self.get_database_column(&row, "includeSeed")?),
+                dominant_pair: i64_to_bool(self.get_database_column(&row, "dominantPair")?),
+            };
+            let rowid = self.get_database_column::<i64>(&row, "PK")?;
+            Ok((color_themes, rowid))
+        })
     }
 
     /// Insert [`RGB`] record into the database
@@ -319,7 +795,7 @@ impl DatabaseConnection {
     /// # use color_scheme_generator::common::{Wallpaper, Centrality, ColorThemeOption, RGB};
     /// # let cache_path = ":memory:".parse::<PathBuf>().unwrap();
     /// let database_connection = DatabaseConnection::new(&cache_path).unwrap();
-    /// # let wallpaper = Wallpaper {path : "text".parse::<PathBuf>().unwrap(), centrality: Centrality::Prevalent};
+    /// # let wallpaper = Wallpaper {path : "text".parse::<PathBuf>().unwrap(), centrality: Centrality::Prevalent, modified: 0, content_hash: String::new(), analysis: String::new()};
     /// # database_connection.insert_wallpaper_record(&wallpaper).unwrap();
     /// # let color_themes = ColorThemeOption {
     /// #   darker: 0,
@@ -337,6 +813,11 @@ impl DatabaseConnection {
     /// #   tints: 0,
     /// #   tones: 0,
     /// #   blends: 0,
+    /// #   interpolation: Default::default(),
+    /// #   hue_steps: 0,
+    /// #   palette_size: 2,
+    /// #   include_seed: false,
+    /// #   dominant_pair: false,
     /// # };
     /// # database_connection.insert_color_themes_record(&color_themes, &wallpaper).unwrap();
     /// # let RGB = RGB {red: 255, green: 0, blue: 0};
@@ -347,21 +828,27 @@ impl DatabaseConnection {
         rgb: &RGB,
         wallpaper: &Wallpaper,
         ct: &ColorThemeOption,
-    ) -> anyhow::Result<()> {
-        let query = format!(
-            "INSERT INTO RGB (RGB, wallpaper, color_themes) VALUES ('{}', {}, {})",
-            rgb,
-            self.select_wallpaper_record(wallpaper)?.1,
-            self.select_color_themes_record(ct, wallpaper)?.1
-        );
-        self.connection.execute(query)?;
-        Ok(())
+    ) -> Result<(), ColorSchemeError> {
+        retry(self.retries, || {
+            let query = "INSERT INTO RGB (RGB, wallpaper, color_themes) VALUES (?, ?, ?)";
+            let mut statement = self.connection.prepare(query)?;
+            statement.bind(
+                &[
+                    Value::from(rgb.to_string()),
+                    Value::from(self.select_wallpaper_record(wallpaper)?.1),
+                    Value::from(self.select_color_themes_record(ct, wallpaper)?.1),
+                ][..],
+            )?;
+            statement.next()?;
+            Ok(())
+        })
     }
 
     /// Select  [`RGB`] record in from the database.
     ///
     /// # Notes
     /// A [`Wallpaper`] and [`ColorThemeOption`] must be inserted into the database before a [`RGB`] record can be successfully selected since the [`Wallpaper`] ROWID and [`ColorThemeOption`] ROWID is referenced by a [`RGB`] record.
+    /// Every [`RGB`] record of the color theme is returned in the order it was inserted, so color themes of any length are cached whole.
     ///
     /// # Errors
     /// Will throw an error if:
@@ -375,7 +862,7 @@ impl DatabaseConnection {
     /// # use color_scheme_generator::common::{Wallpaper, Centrality, ColorThemeOption, RGB};
     /// # let cache_path = ":memory:".parse::<PathBuf>().unwrap();
     /// let database_connection = DatabaseConnection::new(&cache_path).unwrap();
-    /// # let wallpaper = Wallpaper {path : "text".parse::<PathBuf>().unwrap(), centrality: Centrality::Prevalent};
+    /// # let wallpaper = Wallpaper {path : "text".parse::<PathBuf>().unwrap(), centrality: Centrality::Prevalent, modified: 0, content_hash: String::new(), analysis: String::new()};
     /// # database_connection.insert_wallpaper_record(&wallpaper).unwrap();
     /// # let color_themes = ColorThemeOption {
     /// #   darker: 0,
@@ -393,37 +880,223 @@ impl DatabaseConnection {
     /// #   tints: 0,
     /// #   tones: 0,
     /// #   blends: 0,
+    /// #   interpolation: Default::default(),
+    /// #   hue_steps: 0,
+    /// #   palette_size: 2,
+    /// #   include_seed: false,
+    /// #   dominant_pair: false,
     /// # };
     /// # database_connection.insert_color_themes_record(&color_themes, &wallpaper).unwrap();
     /// # let RGB = RGB {red: 255, green: 0, blue: 0};
     /// # database_connection.insert_rgb_record(&RGB, &wallpaper, &color_themes).unwrap();
     /// database_connection.select_rgb_records(&wallpaper, &color_themes).unwrap();
+    ///
+    /// let shades = ColorThemeOption { quadratic: false, shades: 8, ..color_themes };
+    /// let palette = color_scheme_generator::theme_calculation::gamut::shades(&RGB {red: 200, green: 30, blue: 30}, 8);
+    /// database_connection.insert_color_themes_record(&shades, &wallpaper).unwrap();
+    /// for color in &palette {
+    ///     database_connection.insert_rgb_record(color, &wallpaper, &shades).unwrap();
+    /// }
+    /// assert_eq!(palette.len(), 8);
+    /// assert_eq!(database_connection.select_rgb_records(&wallpaper, &shades).unwrap(), palette);
     pub fn select_rgb_records(
         &self,
         wallpaper: &Wallpaper,
         ct: &ColorThemeOption,
-    ) -> anyhow::Result<Vec<RGB>> {
-        let query = format!(
-            "SELECT RGB FROM RGB where wallpaper = {} AND color_themes = {} ORDER BY ROWID;",
-            self.select_wallpaper_record(wallpaper)?.1,
-            self.select_color_themes_record(ct, wallpaper)?.1
-        );
-        let colors = self
+    ) -> Result<Vec<RGB>, ColorSchemeError> {
+        retry(self.retries, || {
+            let query =
+                "SELECT RGB FROM RGB where wallpaper = ? AND color_themes = ? ORDER BY ROWID;";
+            let colors = self
+                .connection
+                .prepare(query)?
+                .into_iter()
+                .bind(
+                    &[
+                        self.select_wallpaper_record(wallpaper)?.1,
+                        self.select_color_themes_record(ct, wallpaper)?.1,
+                    ][..],
+                )?
+                .collect::<Result<Vec<_>, _>>()?;
+            let colors = colors
+                .iter()
+                .map(|r| r.read::<&str, _>("RGB"))
+                .map(|r| String::from_str(r).unwrap())
+                .map(|s| RGB::from_str(&s).unwrap())
+                .collect::<Vec<_>>();
+            Ok(colors)
+        })
+    }
+
+    /// Insert the [`Coverage`] of a color theme into the database, one coverage record per counted color.
+    ///
+    /// # Notes
+    /// Both [`ColorThemeOption`] and [`Wallpaper`] records have to be inserted into the database before successfully inserting a [`Coverage`].
+    ///
+    /// # Errors
+    /// Will throw an error if either [`Wallpaper`] or [`ColorThemeOption`] is not found in the database.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::path::PathBuf;
+    /// # use clap::Parser;
+    /// # use color_scheme_generator::database::DatabaseConnection;
+    /// # use color_scheme_generator::common::{Centrality, Cli, ColorCount, Coverage, Wallpaper, RGB};
+    /// # let cache_path = ":memory:".parse::<PathBuf>().unwrap();
+    /// let database_connection = DatabaseConnection::new(&cache_path).unwrap();
+    /// # let wallpaper = Wallpaper {path : "text".parse::<PathBuf>().unwrap(), centrality: Centrality::Prevalent, modified: 0, content_hash: String::new(), analysis: String::new()};
+    /// # database_connection.insert_wallpaper_record(&wallpaper).unwrap();
+    /// let color_themes = Cli::parse_from(["color_scheme_generator", "text"]).color_themes;
+    /// database_connection.insert_color_themes_record(&color_themes, &wallpaper).unwrap();
+    /// let coverage = Coverage {
+    ///     colors: vec![ColorCount { color: RGB { red: 255, green: 0, blue: 0 }, count: 3 }],
+    ///     pixels: 4,
+    /// };
+    /// database_connection.insert_coverage_records(&coverage, &wallpaper, &color_themes).unwrap();
+    /// ```
+    pub fn insert_coverage_records(
+        &self,
+        coverage: &Coverage,
+        wallpaper: &Wallpaper,
+        ct: &ColorThemeOption,
+    ) -> Result<(), ColorSchemeError> {
+        retry(self.retries, || {
+            let query = "INSERT INTO coverage (RGB, count, pixels, wallpaper, color_themes) VALUES (?, ?, ?, ?, ?)";
+            let wallpaper_id = self.select_wallpaper_record(wallpaper)?.1;
+            let color_themes_id = self.select_color_themes_record(ct, wallpaper)?.1;
+            for color in &coverage.colors {
+                let mut statement = self.connection.prepare(query)?;
+                statement.bind(
+                    &[
+                        Value::from(color.color.to_string()),
+                        Value::from(i64::try_from(color.count)?),
+                        Value::from(i64::try_from(coverage.pixels)?),
+                        Value::from(wallpaper_id),
+                        Value::from(color_themes_id),
+                    ][..],
+                )?;
+                statement.next()?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Select the [`Coverage`] of a color theme from the database.
+    ///
+    /// # Notes
+    /// The counted colors are returned in the order they were inserted.
+    ///
+    /// # Errors
+    /// Will throw an error if:
+    /// - [`Wallpaper`] record is not found in the database.
+    /// - [`ColorThemeOption`] record is not found in the database.
+    /// - No coverage record is found in the database, such as for a centrality that does not count pixels.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::path::PathBuf;
+    /// # use clap::Parser;
+    /// # use color_scheme_generator::database::DatabaseConnection;
+    /// # use color_scheme_generator::common::{Centrality, Cli, ColorCount, Coverage, Wallpaper, RGB};
+    /// # let cache_path = ":memory:".parse::<PathBuf>().unwrap();
+    /// let database_connection = DatabaseConnection::new(&cache_path).unwrap();
+    /// # let wallpaper = Wallpaper {path : "text".parse::<PathBuf>().unwrap(), centrality: Centrality::Prevalent, modified: 0, content_hash: String::new(), analysis: String::new()};
+    /// # database_connection.insert_wallpaper_record(&wallpaper).unwrap();
+    /// let color_themes = Cli::parse_from(["color_scheme_generator", "text"]).color_themes;
+    /// database_connection.insert_color_themes_record(&color_themes, &wallpaper).unwrap();
+    /// assert!(database_connection.select_coverage(&wallpaper, &color_themes).is_err());
+    /// let coverage = Coverage {
+    ///     colors: vec![
+    ///         ColorCount { color: RGB { red: 255, green: 0, blue: 0 }, count: 3 },
+    ///         ColorCount { color: RGB { red: 0, green: 0, blue: 255 }, count: 1 },
+    ///     ],
+    ///     pixels: 4,
+    /// };
+    /// database_connection.insert_coverage_records(&coverage, &wallpaper, &color_themes).unwrap();
+    /// assert_eq!(database_connection.select_coverage(&wallpaper, &color_themes).unwrap(), coverage);
+    /// ```
+    pub fn select_coverage(
+        &self,
+        wallpaper: &Wallpaper,
+        ct: &ColorThemeOption,
+    ) -> Result<Coverage, ColorSchemeError> {
+        retry(self.retries, || {
+            let query = "SELECT RGB, count, pixels FROM coverage where wallpaper = ? AND color_themes = ? ORDER BY ROWID;";
+            let rows = self
+                .connection
+                .prepare(query)?
+                .into_iter()
+                .bind(
+                    &[
+                        self.select_wallpaper_record(wallpaper)?.1,
+                        self.select_color_themes_record(ct, wallpaper)?.1,
+                    ][..],
+                )?
+                .collect::<Result<Vec<_>, _>>()?;
+            let pixels = usize::try_from(
+                rows.first()
+                    .ok_or(ColorSchemeError::NotFound)?
+                    .try_read::<i64, _>("pixels")?,
+            )?;
+            let colors = rows
+                .iter()
+                .map(|row| {
+                    Ok(ColorCount {
+                        color: RGB::from_str(row.try_read::<&str, _>("RGB")?)?,
+                        count: usize::try_from(row.try_read::<i64, _>("count")?)?,
+                    })
+                })
+                .collect::<Result<Vec<_>, ColorSchemeError>>()?;
+            Ok(Coverage { colors, pixels })
+        })
+    }
+
+    fn column_exists(&self, table: &str, column: &str) -> Result<bool, ColorSchemeError> {
+        let query = "SELECT COUNT(*) AS count FROM pragma_table_info(?) WHERE name = ?";
+        let row = self
             .connection
-            .prepare(&query)?
+            .prepare(query)?
             .into_iter()
-            .map(|r| r.unwrap())
-            .collect::<Vec<_>>();
-        let colors = colors
-            .iter()
-            .map(|r| r.read::<&str, _>("RGB"))
-            .map(|r| String::from_str(r).unwrap())
-            .map(|s| RGB::from_str(&s).unwrap())
-            .collect::<Vec<_>>();
-        Ok(colors)
+            .bind(&[table, column][..])?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self.get_database_column::<i64>(&row, "count")? > 0)
+    }
+
+    fn color_themes_parameters(
+        &self,
+        ct: &ColorThemeOption,
+        wallpaper: &Wallpaper,
+    ) -> Result<Vec<Value>, ColorSchemeError> {
+        Ok(vec![
+            Value::from(i64::from(ct.darker)),
+            Value::from(i64::from(ct.lighter)),
+            Value::from(i64::from(ct.complementary)),
+            Value::from(i64::from(ct.contrast)),
+            Value::from(i64::from(ct.hue_offset)),
+            Value::from(i64::from(ct.triadic)),
+            Value::from(i64::from(ct.quadratic)),
+            Value::from(i64::from(ct.tetratic)),
+            Value::from(i64::from(ct.analogous)),
+            Value::from(i64::from(ct.split_complementary)),
+            Value::from(i64::from(ct.monochromatic)),
+            Value::from(i64::from(ct.shades)),
+            Value::from(i64::from(ct.tints)),
+            Value::from(i64::from(ct.tones)),
+            Value::from(i64::from(ct.blends)),
+            Value::from(ct.interpolation.to_string()),
+            Value::from(i64::from(ct.hue_steps)),
+            Value::from(i64::from(ct.palette_size)),
+            Value::from(i64::from(ct.include_seed)),
+            Value::from(i64::from(ct.dominant_pair)),
+            Value::from(self.select_wallpaper_record(wallpaper)?.1),
+        ])
     }
 
-    fn get_database_column<'a, T>(&'a self, row: &'a [Row], column: &str) -> anyhow::Result<T>
+    fn get_database_column<'a, T>(
+        &'a self,
+        row: &'a [Row],
+        column: &str,
+    ) -> Result<T, ColorSchemeError>
     where
         T: TryFrom<&'a sqlite::Value, Error = sqlite::Error>,
         T: Clone,
@@ -433,11 +1106,37 @@ impl DatabaseConnection {
             .iter()
             .map(|r| r.read::<T, _>(column))
             .collect::<Vec<_>>();
-        let x = binding.first().ok_or(std::fmt::Error)?;
+        let x = binding.first().ok_or(ColorSchemeError::NotFound)?;
         Ok(*x)
     }
 }
 
+fn wallpaper_from_row(row: &Row) -> Result<(Wallpaper, i64), ColorSchemeError> {
+    Ok((
+        Wallpaper {
+            path: PathBuf::from(row.try_read::<&str, _>("path")?),
+            centrality: Centrality::from_str(row.try_read::<&str, _>("centrality")?)?,
+            modified: row.try_read::<i64, _>("modified")?,
+            content_hash: row.try_read::<&str, _>("content_hash")?.to_string(),
+            analysis: row.try_read::<&str, _>("analysis")?.to_string(),
+        },
+        row.try_read::<i64, _>("PK")?,
+    ))
+}
+
+fn wallpaper_key(wallpaper: &Wallpaper) -> Result<[Value; 3], ColorSchemeError> {
+    Ok([
+        Value::from(
+            wallpaper
+                .path
+                .to_str()
+                .ok_or_else(|| ColorSchemeError::InvalidPath(wallpaper.path.clone()))?,
+        ),
+        Value::from(wallpaper.centrality.to_string()),
+        Value::from(wallpaper.analysis.as_str()),
+    ])
+}
+
 fn i64_to_bool(num: i64) -> bool {
     !matches!(num, 0)
 }
@@ -1,6 +1,750 @@
 /// Module for shared common structs and functions.
 pub mod common;
+/// Module to generate shell completion scripts for the command line arguments.
+pub mod completions;
 /// Module to serve as a cache using a sqlite database.
 pub mod database;
+/// Module to serialize color themes into the supported output formats.
+pub mod output;
+/// Module to download images from URLs.
+pub mod remote;
 /// Module to generate color themes from an image.
 pub mod theme_calculation;
+
+use clap::ValueEnum;
+use common::{
+    content_hash, detect_image_format, fnv1a, modified_time, BatchEntry, Centrality, Cli,
+    ColorSchemeError, Coverage, Explanation, OutputFormat, Sort, VersionInfo, Wallpaper, APP_NAME,
+    RGB,
+};
+use database::DatabaseConnection;
+use log::{debug, info, warn};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Time between checks of the image modification time in watch mode.
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+fn is_image(path: &Path) -> Result<(), ColorSchemeError> {
+    theme_calculation::open_image(path).map(|_| ())
+}
+
+/// Get the version and capabilities of the application.
+///
+/// # Notes
+/// The git commit is read from the `COLOR_SCHEME_GENERATOR_GIT_HASH` environment variable at build time, it is None if it was not set.
+/// The color schemes are always computed natively by [`theme_calculation::gamut`].
+///
+/// # Examples
+/// ```
+/// use color_scheme_generator::version_info;
+/// let info = version_info();
+/// assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+/// assert!(info.formats.contains(&"json".to_string()));
+/// assert!(info.formats.contains(&"gtk-css".to_string()));
+/// assert!(info.centralities.contains(&"median-cut".to_string()));
+/// assert!(info.native_engine);
+///
+/// let json = serde_json::to_value(&info).unwrap();
+/// assert!(json["formats"].is_array());
+/// ```
+pub fn version_info() -> VersionInfo {
+    let names = |values: Vec<Option<clap::builder::PossibleValue>>| {
+        values
+            .into_iter()
+            .flatten()
+            .map(|v| v.get_name().to_string())
+            .collect()
+    };
+    VersionInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_hash: option_env!("COLOR_SCHEME_GENERATOR_GIT_HASH").map(str::to_string),
+        formats: names(
+            OutputFormat::value_variants()
+                .iter()
+                .map(ValueEnum::to_possible_value)
+                .collect(),
+        ),
+        centralities: names(
+            Centrality::value_variants()
+                .iter()
+                .map(ValueEnum::to_possible_value)
+                .collect(),
+        ),
+        native_engine: true,
+    }
+}
+
+/// Get the directory inside of XDG_CACHE_HOME that images read from stdin are stored in, creating it if needed.
+pub fn stdin_dir() -> Result<PathBuf, ColorSchemeError> {
+    Ok(xdg::BaseDirectories::with_prefix(APP_NAME)?.create_cache_directory("stdin")?)
+}
+
+/// Store the bytes of an image read from stdin into `dir` and get its path, or None if the bytes are not an image.
+///
+/// # Notes
+/// The bytes are an image if they start with the magic bytes of a supported image format, see [`detect_image_format`].
+/// The file is named by the hash of the bytes, so piping the same image again reuses the same file and its cached color theme.
+///
+/// # Errors
+/// Will error if the file cannot be written.
+///
+/// # Examples
+/// ```
+/// use clap::Parser;
+/// use color_scheme_generator::{common::{Cli, RGB}, store_image_bytes, theme_calculation::generate_color_theme};
+/// let mut png = vec![];
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([200, 30, 30]))
+///     .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+///     .unwrap();
+/// let dir = std::env::temp_dir().join("color_scheme_generator_stdin");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// let path = store_image_bytes(&png, &dir).unwrap().unwrap();
+/// assert_eq!(path.extension().unwrap(), "png");
+/// assert_eq!(store_image_bytes(&png, &dir).unwrap(), Some(path.clone()));
+/// let cli = Cli::parse_from(["color_scheme_generator", path.to_str().unwrap(), "--triadic"]);
+/// assert_eq!(generate_color_theme(&cli).unwrap()[0], RGB { red: 200, green: 30, blue: 30 });
+///
+/// assert_eq!(store_image_bytes(b"/home/user/wallpaper.png", &dir).unwrap(), None);
+/// ```
+pub fn store_image_bytes(bytes: &[u8], dir: &Path) -> Result<Option<PathBuf>, ColorSchemeError> {
+    let Some(format) = detect_image_format(bytes) else {
+        return Ok(None);
+    };
+    let extension = format.extensions_str().first().copied().unwrap_or("img");
+    let path = dir.join(format!("{}.{extension}", fnv1a(bytes)));
+    if !path.exists() {
+        let partial = path.with_extension("part");
+        std::fs::write(&partial, bytes)?;
+        std::fs::rename(&partial, &path)?;
+    }
+    Ok(Some(path))
+}
+
+/// Remove a directory of stored images, such as the [`stdin_dir`] that [`store_image_bytes`] writes to or the
+/// [`remote::download_dir`], along with every image inside of it.
+///
+/// # Notes
+/// Stored images are kept so that their cached color themes stay valid, [`Cli::clear_cache`] removes them along with the cache.
+/// A directory that does not exist is already removed.
+///
+/// # Errors
+/// Will error if the directory exists but cannot be removed.
+///
+/// # Examples
+/// ```
+/// use color_scheme_generator::{remove_stored_images, store_image_bytes};
+/// let mut png = vec![];
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([200, 30, 30]))
+///     .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+///     .unwrap();
+/// let dir = std::env::temp_dir().join("color_scheme_generator_stored");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// let path = store_image_bytes(&png, &dir).unwrap().unwrap();
+/// remove_stored_images(&dir).unwrap();
+/// assert!(!path.exists() && !dir.exists());
+/// remove_stored_images(&dir).unwrap();
+/// ```
+pub fn remove_stored_images(dir: &Path) -> Result<(), ColorSchemeError> {
+    match std::fs::remove_dir_all(dir) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        removed => Ok(removed?),
+    }
+}
+
+/// Generate the color theme for [`Cli::image`] using the cache at [`Cli::database_path`].
+///
+/// If the image is in the cache and has not been modified since it was cached, return the cached theme.
+/// Else, analyze the image and add the result to the cache.
+/// The arguments are [`Cli::resolved`] first, so without a color scheme option the quadratic color scheme is generated.
+///
+/// # Examples
+/// ```
+/// use clap::Parser;
+/// use color_scheme_generator::{common::Cli, generate};
+/// let dir = std::env::temp_dir().join("color_scheme_generator_generate");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// std::env::set_var("XDG_CACHE_HOME", &dir);
+/// let image = dir.join("image.png");
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([200, 30, 30])).save(&image).unwrap();
+/// let args = Cli::parse_from(["color_scheme_generator", image.to_str().unwrap(), "--triadic"]);
+/// let colors = generate(&args).unwrap();
+/// assert!(!colors.is_empty());
+/// assert_eq!(generate(&args).unwrap(), colors);
+///
+/// let quadratic = Cli::parse_from(["color_scheme_generator", image.to_str().unwrap(), "--quadratic"]);
+/// let args = Cli::parse_from(["color_scheme_generator", image.to_str().unwrap()]);
+/// assert_eq!(generate(&args).unwrap(), generate(&quadratic).unwrap());
+/// assert_eq!(generate(&args).unwrap().len(), 4);
+/// ```
+pub fn generate(cli: &Cli) -> Result<Vec<RGB>, ColorSchemeError> {
+    let conn = DatabaseConnection::with_retries(&cli.database_path()?, cli.db_retries)?;
+    generate_with_connection(cli, &conn)
+}
+
+/// Generate the color theme for [`Cli::image`] using an already open cache.
+///
+/// Behaves like [`generate`] but lets callers that analyze many images reuse one connection.
+///
+/// # Notes
+/// If the image is not cached under its path but an image with the same contents is, the cached color theme is reused
+/// and stored under the new path without analyzing the image again.
+/// A cached color theme is discarded if the modification time or the contents of the image changed since it was cached,
+/// so an image replaced by a copy that kept the old modification time, such as with `cp -p`, is analyzed again.
+///
+/// Color themes are cached by the image, [`Cli::centrality`], every option of [`ColorThemeOption`](common::ColorThemeOption) and the options listed in [`Cli::analysis_parameters`].
+/// Color themes generated from [`Cli::seed_color`] or read from [`Cli::palette_from_file`] are not cached since they are cheap to derive,
+/// and neither are the colors of [`Cli::accent_only`] since they do not depend on the color theme options that the cache is keyed by.
+///
+/// # Errors
+/// The image is checked before the cache is looked up, so an image that was deleted or is no longer an image
+/// returns [`ColorSchemeError::NotAnImage`] instead of its cached color theme.
+///
+/// # Examples
+/// ```
+/// use clap::Parser;
+/// use color_scheme_generator::{common::{content_hash, Centrality, Cli, ColorSchemeError, Wallpaper, RGB}, database::DatabaseConnection, generate_with_connection};
+/// let dir = std::env::temp_dir().join("color_scheme_generator_content_hash");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// let (original, copy) = (dir.join("original.png"), dir.join("copy.png"));
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([200, 30, 30])).save(&original).unwrap();
+/// std::fs::copy(&original, &copy).unwrap();
+/// let conn = DatabaseConnection::new(&":memory:".into()).unwrap();
+/// let cli = |path: &std::path::Path| Cli::parse_from(["color_scheme_generator", path.to_str().unwrap(), "--triadic"]);
+/// generate_with_connection(&cli(&original), &conn).unwrap();
+/// // Replace the cached color theme with one the analysis would never produce.
+/// let analysis = cli(&original).analysis_parameters();
+/// let wallpaper = conn.select_wallpaper_record(&Wallpaper {path: original.clone(), centrality: Centrality::Median, modified: 0, content_hash: String::new(), analysis}).unwrap().0;
+/// let marker = RGB { red: 1, green: 2, blue: 3 };
+/// conn.delete_wallpaper_record(&wallpaper).unwrap();
+/// conn.insert_wallpaper_record(&wallpaper).unwrap();
+/// conn.insert_color_themes_record(&cli(&original).color_themes, &wallpaper).unwrap();
+/// conn.insert_rgb_record(&marker, &wallpaper, &cli(&original).color_themes).unwrap();
+/// assert_eq!(wallpaper.content_hash, content_hash(&copy).unwrap());
+/// assert_eq!(generate_with_connection(&cli(&copy), &conn).unwrap(), vec![marker.clone()]);
+///
+/// // Changing an option that the cache is keyed by analyzes the image again.
+/// for option in [["--palette-size", "3"], ["--sample-size", "2"], ["--region", "top"]] {
+///     let args = Cli::parse_from(["color_scheme_generator", copy.to_str().unwrap(), "--triadic", option[0], option[1]]);
+///     assert_ne!(generate_with_connection(&args, &conn).unwrap(), vec![marker.clone()]);
+/// }
+/// assert_eq!(generate_with_connection(&cli(&copy), &conn).unwrap(), vec![marker]);
+///
+/// let notes = dir.join("notes.txt");
+/// std::fs::write(&notes, "not an image").unwrap();
+/// assert!(matches!(generate_with_connection(&cli(&notes), &conn), Err(ColorSchemeError::NotAnImage(_))));
+///
+/// std::fs::remove_file(&original).unwrap();
+/// assert!(matches!(generate_with_connection(&cli(&original), &conn), Err(ColorSchemeError::Io(_))));
+/// ```
+///
+/// Replacing the contents of an image without changing its modification time analyzes it again.
+/// ```
+/// use clap::Parser;
+/// use color_scheme_generator::{common::{Cli, RGB}, database::DatabaseConnection, generate_with_connection};
+/// let image = std::env::temp_dir().join("color_scheme_generator_same_mtime.png");
+/// let save = |color: [u8; 3]| {
+///     image::RgbImage::from_pixel(4, 4, image::Rgb(color)).save(&image).unwrap();
+///     std::fs::File::options().write(true).open(&image).unwrap()
+///         .set_modified(std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1)).unwrap();
+/// };
+/// let conn = DatabaseConnection::new(&":memory:".into()).unwrap();
+/// let args = Cli::parse_from(["color_scheme_generator", image.to_str().unwrap(), "--complementary", "--include-seed"]);
+/// save([200, 30, 30]);
+/// assert_eq!(generate_with_connection(&args, &conn).unwrap()[0], RGB { red: 200, green: 30, blue: 30 });
+/// save([30, 30, 200]);
+/// assert_eq!(generate_with_connection(&args, &conn).unwrap()[0], RGB { red: 30, green: 30, blue: 200 });
+/// ```
+///
+/// An analysis that takes longer than [`Cli::timeout`] errors, and nothing is cached for it.
+/// ```
+/// use clap::Parser;
+/// use color_scheme_generator::{common::{Cli, ColorSchemeError}, database::DatabaseConnection, explain, generate_with_connection};
+/// let image = std::env::temp_dir().join("color_scheme_generator_timeout.png");
+/// image::RgbImage::from_fn(2048, 2048, |x, y| image::Rgb([x as u8, y as u8, (x ^ y) as u8])).save(&image).unwrap();
+/// let conn = DatabaseConnection::new(&":memory:".into()).unwrap();
+/// let args = Cli::parse_from(["color_scheme_generator", image.to_str().unwrap(), "-c", "kmeans", "--sample-size", "0", "--timeout", "0.001"]);
+/// assert!(matches!(generate_with_connection(&args, &conn), Err(ColorSchemeError::Timeout(_))));
+/// assert!(!explain(&args, &conn, ":memory:".as_ref()).cache_hit);
+/// ```
+pub fn generate_with_connection(
+    cli: &Cli,
+    conn: &DatabaseConnection,
+) -> Result<Vec<RGB>, ColorSchemeError> {
+    Ok(generate_with_coverage(cli, conn)?.0)
+}
+
+/// Generate the color theme for [`Cli::image`] like [`generate_with_connection`], along with the [`Coverage`] of the colors selected by the centrality.
+///
+/// # Notes
+/// The coverage is cached with the color theme, so it is reported for cache hits without analyzing the image again.
+/// A color theme cached without its coverage, by a version that did not cache it, is analyzed again when
+/// [`Cli::percentage`] or [`Sort::Frequency`] needs it. The coverage is None for palette files and in the cases of
+/// [`generate_color_theme_with_coverage`](theme_calculation::generate_color_theme_with_coverage).
+///
+/// # Errors
+/// Will error like [`generate_with_connection`].
+///
+/// # Examples
+/// ```
+/// use clap::Parser;
+/// use color_scheme_generator::{common::{Cli, RGB}, database::DatabaseConnection, generate_with_coverage};
+/// let image = std::env::temp_dir().join("color_scheme_generator_cached_coverage.png");
+/// image::RgbImage::from_fn(4, 4, |x, _| match x < 3 {
+///     true => image::Rgb([200, 30, 30]),
+///     false => image::Rgb([30, 30, 200]),
+/// })
+/// .save(&image)
+/// .unwrap();
+/// let conn = DatabaseConnection::new(&":memory:".into()).unwrap();
+/// let args = Cli::parse_from(["color_scheme_generator", image.to_str().unwrap(), "-c", "prevalent", "--sample-size", "0", "--complementary", "--include-seed"]);
+/// let (colors, coverage) = generate_with_coverage(&args, &conn).unwrap();
+/// assert_eq!(coverage.clone().unwrap().fractions(&colors[..1]), vec![Some(0.75)]);
+/// // The coverage of a cache hit is read from the cache.
+/// assert_eq!(generate_with_coverage(&args, &conn).unwrap(), (colors, coverage));
+/// ```
+pub fn generate_with_coverage(
+    cli: &Cli,
+    conn: &DatabaseConnection,
+) -> Result<(Vec<RGB>, Option<Coverage>), ColorSchemeError> {
+    let cli = &cli.resolved();
+    if let Some(path) = &cli.palette_from_file {
+        return Ok((read_palette(path)?, None));
+    }
+    if cli.seed_color.is_some() {
+        return theme_calculation::generate_color_theme_with_coverage(cli);
+    }
+    let image = cli.image.clone().ok_or(ColorSchemeError::MissingImage)?;
+    is_image(&image)?;
+    if cli.accent_only {
+        return theme_calculation::generate_color_theme_with_coverage(cli);
+    }
+    let wallpaper = image_wallpaper(&image, cli)?;
+    let needs_coverage =
+        (cli.percentage || cli.sort == Sort::Frequency) && cli.centrality.counts_pixels();
+    if let Ok((cached, _)) = conn.select_wallpaper_record(&wallpaper) {
+        if cached.modified != wallpaper.modified || cached.content_hash != wallpaper.content_hash {
+            info!("Image was modified since it was cached. Recomputing color themes.");
+            conn.delete_wallpaper_record(&wallpaper)?;
+        }
+    }
+    if let Ok(colors) = conn.select_rgb_records(&wallpaper, &cli.color_themes) {
+        let coverage = conn.select_coverage(&wallpaper, &cli.color_themes).ok();
+        if coverage.is_some() || !needs_coverage {
+            debug!("Cache hit for {}.", image.display());
+            return Ok((colors, coverage));
+        }
+        info!("Image was cached without its coverage. Recomputing color themes.");
+        conn.delete_wallpaper_record(&wallpaper)?;
+    }
+    let copy = conn
+        .select_wallpaper_by_hash(&wallpaper)
+        .and_then(|(cached, _)| {
+            let colors = conn.select_rgb_records(&cached, &cli.color_themes)?;
+            Ok((
+                colors,
+                conn.select_coverage(&cached, &cli.color_themes).ok(),
+            ))
+        })
+        .ok()
+        .filter(|(_, coverage)| coverage.is_some() || !needs_coverage);
+    let (colors, coverage) = match copy {
+        Some(copy) => {
+            info!("Image is an identical copy of a cached image. Reusing its color themes.");
+            copy
+        }
+        None => {
+            debug!("Cache miss for {}. Analyzing the image.", image.display());
+            theme_calculation::generate_color_theme_with_coverage(cli)?
+        }
+    };
+    conn.transaction(|| {
+        if conn.select_wallpaper_record(&wallpaper).is_err() {
+            conn.insert_wallpaper_record(&wallpaper)?;
+        }
+        conn.insert_color_themes_record(&cli.color_themes, &wallpaper)?;
+        for color in &colors {
+            conn.insert_rgb_record(color, &wallpaper, &cli.color_themes)?;
+        }
+        if let Some(coverage) = &coverage {
+            conn.insert_coverage_records(coverage, &wallpaper, &cli.color_themes)?;
+        }
+        Ok(())
+    })?;
+    Ok((colors, coverage))
+}
+
+/// Read a color theme from a palette file in the schema of the json or yaml [`OutputFormat`], see [`Cli::palette_from_file`].
+///
+/// # Notes
+/// JSON is read as YAML, which it is a subset of. The alpha of the colors, if any, is ignored.
+///
+/// # Errors
+/// Will error if the file cannot be read, is not a list of colors or the list is empty.
+///
+/// # Examples
+/// ```
+/// use clap::Parser;
+/// use color_scheme_generator::{common::{Cli, ColorSchemeError, RGB}, generate, read_palette, theme_calculation::adjust::adjust};
+/// let palette = std::env::temp_dir().join("color_scheme_generator_palette.yaml");
+/// std::fs::write(&palette, "- red: 255\n  green: 0\n  blue: 0\n- red: 0\n  green: 0\n  blue: 255\n").unwrap();
+/// let args = Cli::parse_from(["color_scheme_generator", "--palette-from-file", palette.to_str().unwrap(), "--invert", "--cache-path", ":memory:"]);
+/// assert_eq!(
+///     adjust(generate(&args).unwrap(), None, &args),
+///     vec![RGB { red: 0, green: 255, blue: 255 }, RGB { red: 255, green: 255, blue: 0 }]
+/// );
+///
+/// let json = std::env::temp_dir().join("color_scheme_generator_palette.json");
+/// std::fs::write(&json, r#"[{"red":1,"green":2,"blue":3,"alpha":204}]"#).unwrap();
+/// assert_eq!(read_palette(&json).unwrap(), vec![RGB { red: 1, green: 2, blue: 3 }]);
+/// std::fs::write(&json, "[]").unwrap();
+/// assert!(matches!(read_palette(&json), Err(ColorSchemeError::InvalidPalette { .. })));
+/// std::fs::write(&json, r#"{"colors": []}"#).unwrap();
+/// assert!(matches!(read_palette(&json), Err(ColorSchemeError::InvalidPalette { .. })));
+/// ```
+pub fn read_palette(path: &Path) -> Result<Vec<RGB>, ColorSchemeError> {
+    let invalid = |reason: String| ColorSchemeError::InvalidPalette {
+        path: path.to_path_buf(),
+        reason,
+    };
+    let colors = serde_yml::from_str::<Vec<RGB>>(&std::fs::read_to_string(path)?)
+        .map_err(|e| invalid(e.to_string()))?;
+    match colors.is_empty() {
+        true => Err(invalid("palette has no colors".to_string())),
+        false => Ok(colors),
+    }
+}
+
+/// Describe how the color theme for [`Cli::image`] would be generated without generating it.
+///
+/// # Notes
+/// The color theme is a cache hit if it is cached for the image and neither the modification time nor the contents of the image changed since,
+/// or if it is cached for an identical copy of the image. Color themes from [`Cli::seed_color`], [`Cli::palette_from_file`] and [`Cli::accent_only`] are never cached.
+///
+/// # Examples
+/// ```
+/// use clap::Parser;
+/// use color_scheme_generator::{common::Cli, database::DatabaseConnection, explain, generate_with_connection};
+/// let image = std::env::temp_dir().join("color_scheme_generator_explain.png");
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([200, 30, 30])).save(&image).unwrap();
+/// let cache_path = ":memory:".into();
+/// let conn = DatabaseConnection::new(&cache_path).unwrap();
+/// let args = Cli::parse_from(["color_scheme_generator", image.to_str().unwrap(), "--triadic"]);
+/// let explanation = explain(&args, &conn, &cache_path);
+/// assert!(!explanation.cache_hit);
+/// assert!(serde_json::to_string(&explanation).unwrap().contains(r#""cache_hit":false"#));
+/// assert_eq!(explanation.centrality, "median");
+/// assert!(explanation.color_themes.triadic);
+/// generate_with_connection(&args, &conn).unwrap();
+/// assert!(explain(&args, &conn, &cache_path).cache_hit);
+/// ```
+pub fn explain(cli: &Cli, conn: &DatabaseConnection, cache_path: &Path) -> Explanation {
+    let cli = &cli.resolved();
+    let cache_hit = cli.seed_color.is_none()
+        && cli.palette_from_file.is_none()
+        && !cli.accent_only
+        && cli.image.as_ref().is_some_and(|image| {
+            let Ok(wallpaper) = image_wallpaper(image, cli) else {
+                return false;
+            };
+            let cached = conn
+                .select_wallpaper_record(&wallpaper)
+                .is_ok_and(|(cached, _)| {
+                    cached.modified == wallpaper.modified
+                        && cached.content_hash == wallpaper.content_hash
+                })
+                && conn
+                    .select_rgb_records(&wallpaper, &cli.color_themes)
+                    .is_ok();
+            let copy_cached = conn
+                .select_wallpaper_by_hash(&wallpaper)
+                .is_ok_and(|(cached, _)| {
+                    cached.path != wallpaper.path
+                        && conn.select_rgb_records(&cached, &cli.color_themes).is_ok()
+                });
+            cached || copy_cached
+        });
+    Explanation {
+        cache_hit,
+        centrality: cli.centrality.to_string(),
+        color_themes: cli.color_themes.clone(),
+        cache_path: cache_path.to_path_buf(),
+    }
+}
+
+/// Get the [`Wallpaper`] of an image analyzed with the centrality and analysis options of `cli` as it is stored in the cache.
+fn image_wallpaper(image: &PathBuf, cli: &Cli) -> Result<Wallpaper, ColorSchemeError> {
+    Ok(Wallpaper {
+        path: image.clone(),
+        centrality: cli.centrality,
+        modified: modified_time(image)?,
+        content_hash: content_hash(image)?,
+        analysis: cli.analysis_parameters(),
+    })
+}
+
+/// Generate the color theme of every newline separated image path in `paths`.
+///
+/// # Notes
+/// Blank lines are skipped. An image that cannot be analyzed, for example because it does not exist,
+/// gets an entry with an error instead of stopping the whole batch.
+///
+/// # Examples
+/// ```
+/// use clap::Parser;
+/// use color_scheme_generator::{common::Cli, database::DatabaseConnection, generate_batch};
+/// let dir = std::env::temp_dir().join("color_scheme_generator_batch");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// let red = dir.join("red.png");
+/// let blue = dir.join("blue.png");
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([200, 30, 30])).save(&red).unwrap();
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([30, 30, 200])).save(&blue).unwrap();
+/// let paths = format!("{}\n\n{}\n{}\n", red.display(), dir.join("missing.png").display(), blue.display());
+/// let conn = DatabaseConnection::new(&":memory:".into()).unwrap();
+/// let args = Cli::parse_from(["color_scheme_generator", "--batch", "--triadic"]);
+/// let entries = generate_batch(&args, &conn, &paths);
+/// assert_eq!(entries.len(), 3);
+/// assert_eq!(entries[0].path, red);
+/// assert_eq!(entries[0].colors.as_ref().unwrap().len(), 3);
+/// assert!(entries[1].colors.is_none() && entries[1].error.is_some());
+/// assert!(entries[2].error.is_none());
+/// ```
+pub fn generate_batch(cli: &Cli, conn: &DatabaseConnection, paths: &str) -> Vec<BatchEntry> {
+    paths
+        .lines()
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .map(|path| batch_entry(cli, conn, path))
+        .collect()
+}
+
+/// Generate the color theme of every image inside of the directory `dir`, ordered by path.
+///
+/// # Notes
+/// Files whose contents are not an image are skipped, images are found by their contents so an image without an extension is included.
+/// Subdirectories are only searched if `recursive` is true, and symbolic links to directories are never followed so a link cycle cannot repeat the same images.
+///
+/// # Errors
+/// Will error if a directory cannot be read.
+///
+/// # Examples
+/// ```
+/// use clap::Parser;
+/// use color_scheme_generator::{common::Cli, database::DatabaseConnection, generate_directory};
+/// let dir = std::env::temp_dir().join("color_scheme_generator_directory");
+/// std::fs::create_dir_all(dir.join("nested")).unwrap();
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([200, 30, 30])).save(dir.join("a.png")).unwrap();
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([30, 30, 200])).save(dir.join("b.png")).unwrap();
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([30, 200, 30])).save(dir.join("nested/c.png")).unwrap();
+/// std::fs::write(dir.join("notes.txt"), "not an image").unwrap();
+/// let conn = DatabaseConnection::new(&":memory:".into()).unwrap();
+/// let args = Cli::parse_from(["color_scheme_generator", dir.to_str().unwrap()]);
+/// let entries = generate_directory(&args, &conn, &dir, false).unwrap();
+/// assert_eq!(entries.len(), 2);
+/// assert_eq!(entries[0].path, dir.join("a.png"));
+/// assert_eq!(entries[1].path, dir.join("b.png"));
+/// assert!(entries.iter().all(|e| e.colors.is_some()));
+/// assert_eq!(generate_directory(&args, &conn, &dir, true).unwrap().len(), 3);
+/// ```
+/// ```
+/// use clap::Parser;
+/// use color_scheme_generator::{common::Cli, database::DatabaseConnection, generate_directory};
+/// let dir = std::env::temp_dir().join("color_scheme_generator_directory_links");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([200, 30, 30])).save_with_format(dir.join("gray"), image::ImageFormat::Png).unwrap();
+/// #[cfg(unix)]
+/// if !dir.join("self").exists() {
+///     std::os::unix::fs::symlink(".", dir.join("self")).unwrap();
+/// }
+/// let conn = DatabaseConnection::new(&":memory:".into()).unwrap();
+/// let args = Cli::parse_from(["color_scheme_generator", dir.to_str().unwrap()]);
+/// let entries = generate_directory(&args, &conn, &dir, true).unwrap();
+/// assert_eq!(entries.len(), 1);
+/// assert_eq!(entries[0].path, dir.join("gray"));
+/// ```
+pub fn generate_directory(
+    cli: &Cli,
+    conn: &DatabaseConnection,
+    dir: &Path,
+    recursive: bool,
+) -> Result<Vec<BatchEntry>, ColorSchemeError> {
+    Ok(image_paths(dir, recursive)?
+        .into_iter()
+        .map(|path| batch_entry(cli, conn, path))
+        .collect())
+}
+
+fn image_paths(dir: &Path, recursive: bool) -> Result<Vec<PathBuf>, ColorSchemeError> {
+    let mut paths = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        // The file type of the entry does not follow symbolic links, unlike Path::is_dir.
+        if entry.file_type()?.is_dir() {
+            if recursive {
+                paths.append(&mut image_paths(&path, recursive)?);
+            }
+        } else if is_image(&path).is_ok() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn batch_entry(cli: &Cli, conn: &DatabaseConnection, path: PathBuf) -> BatchEntry {
+    let mut args = cli.clone();
+    args.image = Some(path.clone());
+    match generate_with_coverage(&args, conn) {
+        Ok((colors, coverage)) => BatchEntry {
+            path,
+            colors: Some(colors),
+            error: None,
+            coverage,
+        },
+        Err(e) => BatchEntry {
+            path,
+            colors: None,
+            error: Some(e.to_string()),
+            coverage: None,
+        },
+    }
+}
+
+/// Generate the color theme for [`Cli::image`] and call `emit` with it and its [`Coverage`] every time the image is modified.
+///
+/// # Notes
+/// The modification time of the image is polled every `interval`. When it changes the contents of the image are hashed,
+/// and the image is only analyzed and emitted again if they changed, so touching an unchanged image does not emit it twice.
+/// While the image is missing or cannot be analyzed, for example while it is being replaced, it is skipped until it is modified again.
+///
+/// Watching stops when `emit` returns false or on SIGINT, such as from Ctrl-C. SIGINT does not interrupt an analysis or `emit`,
+/// watching stops once the color theme being generated has been emitted, so its record is written whole.
+/// Every color theme is stored in the cache in one transaction anyway, see [`DatabaseConnection::transaction`],
+/// so a watch killed by another signal leaves either the whole color theme or none of it in the cache.
+///
+/// # Errors
+/// Will error if no image path was given or if `emit` errors.
+///
+/// # Examples
+/// ```
+/// use clap::Parser;
+/// use color_scheme_generator::{common::{Cli, RGB}, database::DatabaseConnection, watch};
+/// use std::time::{Duration, SystemTime};
+/// let image = std::env::temp_dir().join("color_scheme_generator_watch.png");
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([200, 30, 30])).save(&image).unwrap();
+/// let writer = {
+///     let image = image.clone();
+///     std::thread::spawn(move || {
+///         let touch = |seconds| {
+///             let file = std::fs::File::options().write(true).open(&image).unwrap();
+///             file.set_modified(SystemTime::now() + Duration::from_secs(seconds)).unwrap();
+///         };
+///         // Touching the image without changing it does not emit it again.
+///         std::thread::sleep(Duration::from_millis(200));
+///         touch(1);
+///         std::thread::sleep(Duration::from_millis(200));
+///         image::RgbImage::from_pixel(4, 4, image::Rgb([30, 30, 200])).save(&image).unwrap();
+///         touch(2);
+///     })
+/// };
+/// let conn = DatabaseConnection::new(&":memory:".into()).unwrap();
+/// let args = Cli::parse_from(["color_scheme_generator", image.to_str().unwrap(), "--watch", "--triadic"]);
+/// let mut emitted = vec![];
+/// watch(&args, &conn, Duration::from_millis(10), |colors, _| {
+///     emitted.push(colors[0].clone());
+///     Ok(emitted.len() < 2)
+/// })
+/// .unwrap();
+/// writer.join().unwrap();
+/// assert_eq!(emitted[0], RGB { red: 200, green: 30, blue: 30 });
+/// assert_eq!(emitted[1], RGB { red: 30, green: 30, blue: 200 });
+/// ```
+///
+/// SIGINT stops watching after the color theme is emitted.
+/// ```
+/// use clap::Parser;
+/// use color_scheme_generator::{common::Cli, database::DatabaseConnection, watch};
+/// let image = std::env::temp_dir().join("color_scheme_generator_watch_interrupt.png");
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([200, 30, 30])).save(&image).unwrap();
+/// let conn = DatabaseConnection::new(&":memory:".into()).unwrap();
+/// let args = Cli::parse_from(["color_scheme_generator", image.to_str().unwrap(), "--watch"]);
+/// let mut emitted = 0;
+/// watch(&args, &conn, std::time::Duration::from_millis(10), |_, _| {
+///     emitted += 1;
+///     assert_eq!(unsafe { libc::raise(libc::SIGINT) }, 0);
+///     Ok(true)
+/// })
+/// .unwrap();
+/// assert_eq!(emitted, 1);
+/// ```
+pub fn watch<F>(
+    cli: &Cli,
+    conn: &DatabaseConnection,
+    interval: Duration,
+    emit: F,
+) -> anyhow::Result<()>
+where
+    F: FnMut(Vec<RGB>, Option<Coverage>) -> anyhow::Result<bool>,
+{
+    let image = cli
+        .image
+        .clone()
+        .ok_or(anyhow::anyhow!("no image path was given"))?;
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&interrupted);
+    // SAFETY: the action only stores to an atomic, which is async-signal-safe.
+    let sigint = unsafe {
+        signal_hook_registry::register(libc::SIGINT, move || flag.store(true, Ordering::SeqCst))
+    }?;
+    let watched = watch_until(cli, conn, image, interval, &interrupted, emit);
+    signal_hook_registry::unregister(sigint);
+    watched
+}
+
+/// Watch the image like [`watch`] until `emit` returns false or `interrupted` is set.
+fn watch_until<F>(
+    cli: &Cli,
+    conn: &DatabaseConnection,
+    image: PathBuf,
+    interval: Duration,
+    interrupted: &AtomicBool,
+    mut emit: F,
+) -> anyhow::Result<()>
+where
+    F: FnMut(Vec<RGB>, Option<Coverage>) -> anyhow::Result<bool>,
+{
+    let (mut last_modified, mut last_hash) = (None, None);
+    loop {
+        if interrupted.load(Ordering::SeqCst) {
+            info!("Interrupted, stopped watching {}.", image.display());
+            return Ok(());
+        }
+        let modified = modified_time(&image).ok();
+        if modified.is_some() && modified != last_modified {
+            last_modified = modified;
+            let hash = content_hash(&image).ok();
+            if hash.is_some() && hash == last_hash {
+                debug!(
+                    "{} was touched but its contents did not change.",
+                    image.display()
+                );
+                std::thread::sleep(interval);
+                continue;
+            }
+            match generate_with_coverage(cli, conn) {
+                Ok((colors, coverage)) => {
+                    last_hash = hash;
+                    if !emit(colors, coverage)? {
+                        return Ok(());
+                    }
+                }
+                Err(e) => warn!("Could not analyze {}: {e}", image.display()),
+            }
+        }
+        std::thread::sleep(interval);
+    }
+}
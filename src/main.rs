@@ -16,151 +16,451 @@
 //! ```bash
 //! echo PATH_TO_IMAGE | color_scheme_generator
 //! ```
+//! The image itself can also be piped, it is stored in the cache directory by the hash of its contents until `--clear-cache` removes it.
+//! ```bash
+//! cat PATH_TO_IMAGE | color_scheme_generator
+//! ```
 //! ```bash
 //! color_scheme_generator PATH_TO_IMAGE
 //! ```
+//! ```bash
+//! ls ~/Pictures/*.png | color_scheme_generator --batch
+//! ```
+//! ```bash
+//! color_scheme_generator --recursive PATH_TO_DIRECTORY
+//! ```
+//! ```bash
+//! color_scheme_generator --watch -s text PATH_TO_IMAGE
+//! ```
+//! In watch mode the color theme is output again every time the contents of the image change, as a stream of newline delimited records.
+//! Stop watching with Ctrl-C, which stops once the color theme being generated has been output, so no record is cut short.
+//! Use JSON or text output so every record fits on one line.
+//!
+//! A color theme can also be generated from a color instead of an image.
+//! ```bash
+//! color_scheme_generator --seed-color "#ff0000" --triadic
+//! ```
+//!
+//! A palette written by the json or yaml output formats can be read back and transformed without an image.
+//! ```bash
+//! color_scheme_generator --palette-from-file palette.yaml --invert --saturation 20 -s text
+//! ```
+//!
+//! Large images are downsampled to `--sample-size` pixels on their longest edge before they are analyzed. `--sample-rate N` further analyzes only every Nth pixel of the downsampled image,
+//! which is faster but approximates the centrality. The approximation is close for the average and prevalent centralities on large images, `--sample-size 0 --sample-rate 16` analyzes a sixteenth of the full resolution pixels.
+//! ```bash
+//! color_scheme_generator -c average --sample-size 0 --sample-rate 16 PATH_TO_IMAGE
+//! ```
+//!
+//! Images can be downloaded from an `http://` or `https://` URL into the cache directory before they are analyzed, up to 64 MiB.
+//! The certificates of `https://` servers are verified against the certificates trusted by OpenSSL.
+//! ```bash
+//! color_scheme_generator https://example.com/wallpaper.png
+//! ```
+//!
+//! Completion scripts for bash, zsh, fish and elvish can be generated with `--generate-completions`.
+//! ```bash
+//! color_scheme_generator --generate-completions bash > ~/.local/share/bash-completion/completions/color_scheme_generator
+//! ```
+//!
+//! Scripts can check the supported serialization formats and centralities with `--version-json`.
+//! ```bash
+//! color_scheme_generator --version-json
+//! ```
+//!
+//! Programs embedding the application can read its errors from stderr as JSON with `--error-format json`, the exit status is still nonzero.
+//! ```json
+//! {"error":"No such file or directory (os error 2)","kind":"Io"}
+//! ```
+//!
+//! # Cache
+//! - The cache is keyed by the image, the centrality and every color theme option such as `--palette-size`.
+//! - It is also keyed by the options that change which pixels are analyzed: `--sample-size`, `--sample-rate`, `--alpha-threshold`, `--min-lightness`, `--max-lightness`, `--ignore-orientation`, `--region`, `--crop`, `--frame`, `--streaming`, `--deterministic` and `--gamma`.
+//! - Changing any of these options analyzes the image again.
+//! - With `--deterministic` the image is analyzed on a single thread, so the same image always gives byte identical output.
+//! - Several instances of the application can use the cache at the same time.
+//! - An operation on a locked cache is retried `--db-retries` times, 3 by default.
+//! - The cache is stored in `$XDG_CACHE_HOME/color_scheme_generator/cache.db`.
+//! - Another path can be given with `--cache-path` or the `COLOR_SCHEME_GENERATOR_CACHE` environment variable.
+//! - The path `:memory:` keeps the cache only until the application exits.
+//! - `--clear-cache` removes every cached color theme, along with the images that were piped in or downloaded.
+//!
+//! # Configuration
+//! Defaults for the options can be set in `$XDG_CONFIG_HOME/color_scheme_generator/config.toml`, options given on the command line take precedence.
+//! Every line is an `option = value` pair where the option is the long name of a command line option with underscores instead of dashes, and `#` starts a comment.
+//! Only this subset of TOML is supported: strings are quoted, numbers and booleans are not, and tables and arrays are rejected.
+//! An option of the config file that conflicts with one given on the command line, such as `region` and `--crop`, is ignored.
+//! A flag set to true in the config file cannot be turned off on the command line.
+//! ```
+//! centrality = "kmeans"
+//! serialization_format = "text"
+//! sample_size = 256 # faster than the default of 512
+//! triadic = true
+//! ```
 //!
 //! # Output Formats
-//! color_scheme_generator can output to 3 different output formats all of which give an RGB8 value in the form of "bar_color", "workspace_color" and "text_color":
+//! color_scheme_generator can output to the following output formats. Every color of the color theme is output in order, so color themes of any length can be represented.
+//! The formats that label colors use the first color as the bar color, the second color as the workspace color and a readable text color for the bar.
 //! 1. JSON
 //! ```json
-//! [{"bar_color":{"red":222,"green":186,"blue":189},"workspace_color":{"red":33,"green":69,"blue":66},"text_color":{"red":255,"green":255,"blue":255}}]
+//! [{"red":222,"green":186,"blue":189},{"red":33,"green":69,"blue":66},{"red":255,"green":255,"blue":255}]
 //! ```
+//! Use `json-meta` to wrap the colors in an object with the `path` of the image, the `centrality`, the `color_themes` options, the `generated_at` UNIX time and the `text_color`. The text color of every format can be pinned with `--text-color "#ffffff"`.
+//!
 //! 2. YAML
 //! ```yaml
-//! - bar_color:
-//!     red: 222
-//!     green: 186
-//!     blue: 189
-//!   workspace_color:
-//!     red: 33
-//!     green: 69
-//!     blue: 66
-//!   text_color:
-//!     red: 255
-//!     green: 255
-//!     blue: 255
+//! - red: 222
+//!   green: 186
+//!   blue: 189
+//! - red: 33
+//!   green: 69
+//!   blue: 66
+//! - red: 255
+//!   green: 255
+//!   blue: 255
 //! ```
 //! 3. Text
 //! ```bash
-//! DEBABD,214542,FFFFFF
+//! #debabd,#214542,#ffffff
+//! ```
+//! The text output is every color of the color theme separated by commas. Use `--text-no-hash` to omit the leading `#`, e.g. `debabd,214542,ffffff`. Use `--bar-alpha` and `--workspace-alpha` to give the first two colors an opacity, e.g. `--bar-alpha 204` writes `#debabdcc`. They are also written by the CSS output, along with `--text-alpha`, and as an `alpha` field by the JSON and YAML outputs.
+//!
+//! 4. CSS
+//! ```css
+//! :root {
+//!   --bar-color: #debabd;
+//!   --workspace-color: #214542;
+//!   --text-color: #000000;
+//!   --color-0: #debabd;
+//!   --color-1: #214542;
+//!   --color-2: #ffffff;
+//! }
+//! ```
+//!
+//! 5. Pywal
+//!
+//! A pywal compatible `colors.json` whose `special` and `colors.color0` through `colors.color15` keys are filled from the color theme.
+//!
+//! 6. Named
+//! ```bash
+//! bar=thistle, workspace=darkslategray, text=black
+//! ```
+//! Every role is named by the nearest CSS named color.
+//!
+//! 7. Xresources
+//! ```bash
+//! *background: #debabd
+//! *foreground: #000000
+//! *.color0: #debabd
+//! *.color1: #214542
+//! *.color2: #ffffff
+//! ```
+//! The output can be loaded with `xrdb -merge`.
+//!
+//! 8. Alacritty
+//! ```toml
+//! [colors.primary]
+//! background = "#debabd"
+//! foreground = "#000000"
+//!
+//! [colors.normal]
+//! black = "#debabd"
+//! red = "#214542"
+//! green = "#ffffff"
+//! ...
+//! ```
+//! The `colors` table of `alacritty.toml`, the normal and bright colors are filled from the color theme. Use `alacritty-yaml` for the older `alacritty.yml`.
+//!
+//! 9. Base16
+//! ```yaml
+//! scheme: wallpaper
+//! author: color_scheme_generator
+//! base00: '201314'
+//! ...
+//! base0F: ...
+//! ```
+//! A base16 scheme for the base16 templates, named with `--scheme-name`.
+//!
+//! 10. Dunst
+//! ```ini
+//! [urgency_low]
+//!     background = "#debabd"
+//!     foreground = "#000000"
+//!     frame_color = "#214542"
+//! ...
+//! [urgency_critical]
+//!     background = "#214542"
+//!     foreground = "#ffffff"
+//!     frame_color = "#debabd"
+//! ```
+//! The urgency sections of `dunstrc`, the critical urgency uses the workspace color as its background.
+//!
+//! 11. Rofi
+//! ```css
+//! * {
+//!     background: #debabd;
+//!     foreground: #000000;
+//!     selected-background: #214542;
+//!     selected-foreground: #ffffff;
+//! }
+//! ```
+//! The colors of a rofi `.rasi` theme, the selected entry uses the workspace color.
+//!
+//! 12. GTK CSS
+//! ```css
+//! @define-color theme_bg_color #debabd;
+//! @define-color theme_fg_color #000000;
+//! @define-color theme_selected_bg_color #214542;
+//! ...
+//! @define-color accent_bg_color #214542;
+//! @define-color accent_fg_color #ffffff;
+//! ```
+//! The named colors of GTK themes for `~/.config/gtk-4.0/gtk.css`, the accent is the workspace color.
+//!
+//! 13. Hyprland
+//! ```ini
+//! $bar = rgb(debabd)
+//! $workspace = rgb(214542)
+//! $text = rgb(000000)
+//! $color0 = rgb(debabd)
+//! $color1 = rgb(214542)
+//! ```
+//! Variables for `hyprland.conf`, included with `source = ~/.config/hypr/colors.conf`.
+//!
+//! 14. Polybar
+//! ```ini
+//! [colors]
+//! bar = #debabd
+//! workspace = #214542
+//! text = #000000
+//! color0 = #debabd
+//! color1 = #214542
+//! ```
+//! The `[colors]` section of a polybar configuration, referenced as `${colors.bar}`. Use `--alpha 204` to write every color as `#AARRGGBB`.
+//!
+//! 15. Emacs
+//! ```elisp
+//! (custom-set-faces
+//!  '(default ((t (:background "#debabd" :foreground "#000000"))))
+//!  '(mode-line ((t (:background "#214542" :foreground "#ffffff"))))
+//!  '(region ((t (:background "#214542" :foreground "#ffffff"))))
+//!  '(cursor ((t (:background "#214542")))))
+//! (setq color-scheme-generator-colors '("#debabd" "#214542"))
+//! ```
+//! An elisp snippet for `init.el` or Doom's `config.el` setting the faces of Emacs, every color is also in `color-scheme-generator-colors`.
+//!
+//! 16. Env
+//! ```bash
+//! export BAR_COLOR='#debabd'
+//! export WORKSPACE_COLOR='#214542'
+//! export TEXT_COLOR='#000000'
+//! export COLOR0='#debabd'
+//! export COLOR1='#214542'
+//! ```
+//! Shell `export` statements for prompt themes, loaded with `eval "$(color_scheme_generator -s env PATH_TO_IMAGE)"` or `color_scheme_generator -s env PATH_TO_IMAGE | source` in fish.
+//!
+//! Other formats can be written with a template, where `{bar}`, `{workspace}` and `{text}` are the roles and `{colorN}`, `{hexN}`, `{rN}`, `{gN}` and `{bN}` are the colors by index.
+//! ```bash
+//! color_scheme_generator --template "bar={bar} ws={workspace} text={text}" PATH_TO_IMAGE
 //! ```
-//! The text output has the format of `BAR_COLOR,WORKSPACE_COLOR,TEXT_COLOR`.
 
-use clap::Parser;
 use color_scheme_generator::{
-    common::{Centrality, Cli, ColorThemeOption, OutputFormat, Wallpaper, APP_NAME, RGB},
-    database, theme_calculation,
+    common::{read_config, BatchEntry, Cli, ErrorFormat, ErrorReport},
+    completions, database, output, remote,
+    theme_calculation::{
+        self,
+        adjust::{adjust, adjust_with_coverage},
+        swatch::{swatch, SWATCH_HEIGHT, SWATCH_WIDTH},
+    },
+    WATCH_INTERVAL,
 };
-use log::{error, warn};
-use std::io::{stdin, IsTerminal, Read};
-use std::path::PathBuf;
+use log::warn;
+use std::io::{stdin, stdout, IsTerminal, Read, Write};
 
-fn is_image(path: &PathBuf) -> anyhow::Result<()> {
-    image::ImageReader::open(path)?.with_guessed_format()?;
-    Ok(())
+/// Write the output to [`Cli::output`] if given, else print it to stdout.
+fn write_output(args: &Cli, output: &str) -> anyhow::Result<()> {
+    match &args.output {
+        Some(path) => output::write_atomically(path, output),
+        None => {
+            println!("{output}");
+            stdout().flush()?;
+            Ok(())
+        }
+    }
 }
 
-fn is_default_color_theme_arguments(ct: &ColorThemeOption) -> bool {
-    if ct.darker != 0
-        || ct.lighter != 0
-        || ct.complementary
-        || ct.contrast
-        || ct.hue_offset != 0
-        || ct.triadic
-        || ct.quadratic
-        || ct.tetratic
-        || ct.analogous
-        || ct.split_complementary
-        || ct.monochromatic != 0
-        || ct.shades != 0
-        || ct.tints != 0
-        || ct.tones != 0
-        || ct.blends != 0
-    {
-        return false;
-    }
-    true
+/// Apply the adjustments of the arguments to the color theme of every entry.
+fn adjust_entries(entries: &mut [BatchEntry], args: &Cli) {
+    for entry in entries {
+        entry.colors = entry
+            .colors
+            .take()
+            .map(|colors| adjust(colors, entry.coverage.as_ref(), args));
+    }
 }
 
 /// Starting point of the application.
 ///
+/// Runs the application and, if it fails after the arguments are parsed with [`Cli::error_format`] set to [`ErrorFormat::Json`],
+/// prints the [`ErrorReport`] of the error to stderr and exits with status 1.
+fn main() -> anyhow::Result<()> {
+    let mut error_format = ErrorFormat::Text;
+    match (run(&mut error_format), error_format) {
+        (Err(e), ErrorFormat::Json) => {
+            eprintln!("{}", serde_json::to_string(&ErrorReport::from(&e))?);
+            std::process::exit(1);
+        }
+        (result, _) => result,
+    }
+}
+
 /// Check if program is in pipe, if so receive stdin and parse arguments and stdin.
-/// Else, parse the arguments normally.
+/// Else, parse the arguments normally. The config file inside of XDG_CONFIG_HOME provides the defaults of the arguments.
 ///
 /// Creates cache inside of XDG_CACHE_HOME,
 /// check if image is in cache, if so return theme,
 /// else analyze the image and add it to cache.
-fn main() -> anyhow::Result<()> {
-    let mut args = if stdin().is_terminal() {
-        Cli::parse()
-    } else {
-        let mut input = String::new();
+fn run(error_format: &mut ErrorFormat) -> anyhow::Result<()> {
+    let mut input = String::new();
+    let mut args = std::env::args().collect::<Vec<_>>();
+    if !stdin().is_terminal() {
+        let mut bytes = vec![];
         let mut stdin = stdin().lock();
-        while let Ok(x) = stdin.read_to_string(&mut input) {
+        while let Ok(x) = stdin.read_to_end(&mut bytes) {
             if x == 0 {
                 break;
             }
         }
-        let input = String::from(input.trim());
-        let mut args = std::env::args().collect::<Vec<_>>();
-        args.push(input);
-        Cli::parse_from(args.iter())
+        let image = color_scheme_generator::store_image_bytes(
+            &bytes,
+            &color_scheme_generator::stdin_dir()?,
+        )?;
+        input = String::from(String::from_utf8_lossy(&bytes).trim());
+        if let Some(image) = image {
+            args.push(image.to_string_lossy().to_string());
+        } else if !input.is_empty() {
+            args.push(input.clone());
+        }
+    }
+    let mut args = match Cli::parse_with_config(args, &read_config()?) {
+        Ok(args) => args,
+        Err(e) => match e.downcast::<clap::Error>() {
+            Ok(e) => e.exit(),
+            Err(e) => return Err(e),
+        },
     };
+    *error_format = args.error_format;
+    // The piped paths were given as the image so that they satisfy it, whether batch mode is set on the command line or in the config file.
+    if args.batch {
+        args.image = None;
+    }
+    if let Some(shell) = args.generate_completions {
+        print!("{}", completions::completions(shell));
+        return Ok(());
+    }
 
-    stderrlog::new()
-        .module(module_path!())
-        .verbosity(!args.log_level)
-        .init()
-        .unwrap();
+    if args.version_json {
+        let version = color_scheme_generator::version_info();
+        return write_output(&args, &serde_json::to_string(&version)?);
+    }
 
-    if (args.color_themes.tetratic || args.color_themes.blends > 0)
-        && args.centrality != Centrality::Prevalent
-    {
-        warn!("Incompatible centrality argument. Switching to Prevalent.");
-        args.centrality = Centrality::Prevalent
+    args.logger().init().unwrap();
+
+    args = args.resolved();
+
+    if let Some(url) = args.image.as_deref().and_then(remote::url) {
+        args.image = Some(remote::download(url, &remote::download_dir()?)?);
     }
 
-    if is_default_color_theme_arguments(&args.color_themes) {
-        args.color_themes.quadratic = true;
+    if args.explain {
+        let cache_path = args.database_path()?;
+        let conn = database::DatabaseConnection::with_retries(&cache_path, args.db_retries)?;
+        let explanation = color_scheme_generator::explain(&args, &conn, &cache_path);
+        return write_output(&args, &serde_json::to_string(&explanation)?);
     }
 
-    let xdg_dirs = xdg::BaseDirectories::with_prefix(APP_NAME)?;
-    let cache_path = xdg_dirs.place_cache_file("cache.db")?;
-    let conn = database::DatabaseConnection::new(&cache_path)?;
+    if args.histogram {
+        let histogram =
+            theme_calculation::histogram(&theme_calculation::analyzed_pixels(&args)?, &args);
+        return write_output(&args, &serde_json::to_string(&histogram)?);
+    }
 
-    let wallpaper = Wallpaper {
-        path: args.image.clone(),
-        centrality: args.centrality,
-    };
-    let color_themes = match conn.select_rgb_records(&wallpaper, &args.color_themes) {
-        Ok(c) => c,
-        Err(_) => {
-            if is_image(&args.image).is_err() {
-                error!("Inputted file is not an image");
-                std::process::exit(1);
-            }
-            conn.insert_wallpaper_record(&wallpaper)?;
-            conn.insert_color_themes_record(&args.color_themes, &wallpaper)?;
-            let colors = crate::theme_calculation::generate_color_theme(&args)?;
-            for color in &colors {
-                conn.insert_rgb_record(color, &wallpaper, &args.color_themes)?;
-            }
-            colors
-        }
-    };
+    if args.clear_cache {
+        database::DatabaseConnection::with_retries(&args.database_path()?, args.db_retries)?
+            .clear_cache()?;
+        color_scheme_generator::remove_stored_images(&color_scheme_generator::stdin_dir()?)?;
+        color_scheme_generator::remove_stored_images(
+            &color_scheme_generator::remote::download_dir()?,
+        )?;
+        return Ok(());
+    }
 
-    let output: String = match args.serialization_format {
-        OutputFormat::JSON => serde_json::to_string::<Vec<RGB>>(&color_themes)?,
-        OutputFormat::YAML => serde_yml::to_string::<Vec<RGB>>(&color_themes)?,
-        OutputFormat::TEXT => {
-            let mut ret = String::new();
-            color_themes.iter().for_each(|c| ret += &format!("{},", c));
-            let mut ret = String::from(&(&ret)[0..ret.len() - 2]);
-            ret += "\n";
-            ret
+    if args.list_cache {
+        let conn =
+            database::DatabaseConnection::with_retries(&args.database_path()?, args.db_retries)?;
+        let listing = output::serialize_cache_listing(&conn.list_cached_wallpapers()?, &args)?;
+        return write_output(&args, &listing);
+    }
+
+    if args.batch {
+        let conn =
+            database::DatabaseConnection::with_retries(&args.database_path()?, args.db_retries)?;
+        let mut entries = color_scheme_generator::generate_batch(&args, &conn, &input);
+        adjust_entries(&mut entries, &args);
+        write_output(&args, &serde_json::to_string(&entries)?)?;
+        return Ok(());
+    }
+
+    if let Some(dir) = args.image.as_ref().filter(|path| path.is_dir()) {
+        let conn =
+            database::DatabaseConnection::with_retries(&args.database_path()?, args.db_retries)?;
+        let mut entries =
+            color_scheme_generator::generate_directory(&args, &conn, dir, args.recursive)?;
+        adjust_entries(&mut entries, &args);
+        write_output(&args, &serde_json::to_string(&entries)?)?;
+        return Ok(());
+    }
+
+    if args.watch {
+        let conn =
+            database::DatabaseConnection::with_retries(&args.database_path()?, args.db_retries)?;
+        return color_scheme_generator::watch(&args, &conn, WATCH_INTERVAL, |colors, coverage| {
+            let colors = adjust(colors, coverage.as_ref(), &args);
+            write_output(&args, &output::serialize_color_theme(&colors, &args)?)?;
+            Ok(true)
+        });
+    }
+
+    let conn = database::DatabaseConnection::with_retries(&args.database_path()?, args.db_retries)?;
+    let (colors, coverage) = color_scheme_generator::generate_with_coverage(&args, &conn)?;
+    let (color_themes, fractions): (Vec<_>, Vec<_>) =
+        adjust_with_coverage(colors, coverage.as_ref(), &args)
+            .into_iter()
+            .unzip();
+
+    if let Some(path) = &args.swatch {
+        swatch(&color_themes, SWATCH_WIDTH, SWATCH_HEIGHT)
+            .save(path)
+            .map_err(|e| anyhow::anyhow!("Could not write swatch to {}: {e}", path.display()))?;
+    }
+
+    if args.preview_image && stdout().is_terminal() {
+        let term = std::env::var("TERM").ok();
+        let term_program = std::env::var("TERM_PROGRAM").ok();
+        match output::supports_sixel(term.as_deref(), term_program.as_deref()) {
+            true => println!(
+                "{}",
+                output::sixel(&swatch(&color_themes, SWATCH_WIDTH, SWATCH_HEIGHT))
+            ),
+            false => print!("{}", output::preview(&color_themes)),
         }
+    } else if args.preview && stdout().is_terminal() {
+        print!("{}", output::preview(&color_themes));
+    }
+
+    let output = match args.percentage {
+        true => output::serialize_color_theme_with_coverage(&color_themes, &fractions, &args)?,
+        false => output::serialize_color_theme(&color_themes, &args)?,
     };
-    println!("{}", output);
-    Ok(())
+    write_output(&args, &output)
 }
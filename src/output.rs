@@ -0,0 +1,1194 @@
+#![deny(unused_extern_crates)]
+#![warn(missing_docs)]
+use crate::common::{
+    Cli, ColorThemeOption, Interpolation, OutputFormat, Wallpaper, APP_NAME, RGB, RGBA,
+};
+use crate::theme_calculation::gamut;
+use serde::Serialize;
+use std::path::Path;
+
+/// Serialize a color theme into the [`OutputFormat`] given by [`Cli::serialization_format`].
+///
+/// # Notes
+/// The text output is every color separated by a comma, an empty color theme produces an empty string.
+///
+/// The json-meta output is an object with the `path` of the image, the `centrality`, the effective `color_themes` options,
+/// the `generated_at` time in seconds since the UNIX epoch, the `text_color` of the bar color and the `colors` of the color theme.
+///
+/// The output formats that label colors by their role use the first color as the bar color, the second color as the workspace color
+/// and [`gamut::text_color`] of the bar color as the text color. If [`Cli::text_color`] is given it is used instead as every text color,
+/// including the text colors of the other roles.
+///
+/// The css output is a `:root` block of custom properties. The roles are named `--bar-color`, `--workspace-color` and `--text-color`,
+/// and every color is also available by its index as `--color-N`.
+///
+/// If [`Cli::bar_alpha`], [`Cli::workspace_alpha`] or [`Cli::text_alpha`] are given, the colors of their roles are written as `#rrggbbaa` by the text
+/// and css outputs and have an `alpha` field in the json and yaml outputs. The other colors and outputs are unchanged.
+///
+/// The pywal output follows the schema of pywal's `colors.json`. The color theme is cycled to fill `color0` through `color15`,
+/// the background is the bar color and the foreground and cursor are the text color.
+///
+/// The xresources output sets `*background` to the bar color, `*foreground` to the text color and numbers every color as `*.colorN`,
+/// the workspace color being `*.color1`.
+///
+/// The named output gives every role the name of its nearest CSS named color, see [`RGB::nearest_name`].
+///
+/// If [`Cli::template`] is given it is used instead of the serialization format, see [`render_template`].
+///
+/// The alacritty output is the `colors` table of `alacritty.toml`, and alacritty-yaml is the same table for the older `alacritty.yml`.
+/// The primary background is the bar color and the foreground is the text color. The normal colors are the first eight colors of the color theme
+/// and the bright colors are the next eight, a missing normal color is the bar color, or the text color for white, and a missing bright color is its normal color.
+///
+/// The base16 output is a base16 scheme named by [`Cli::scheme_name`]. `base00` through `base07` go from a shade to a tint of the bar color,
+/// `base08` through `base0F` are the other colors of the color theme, filled with colors equally spaced around the color wheel from the bar color if it is too short.
+///
+/// The dunst output is the `[urgency_low]`, `[urgency_normal]` and `[urgency_critical]` sections of `dunstrc`. The low and normal urgencies use the bar color
+/// as the background, the text color as the foreground and the workspace color as the frame, the critical urgency swaps the bar and workspace colors.
+///
+/// The rofi output is a `*` section of a `.rasi` theme, the background is the bar color, the foreground is the text color
+/// and the selected entry uses the workspace color with its own text color.
+///
+/// The gtk-css output defines the named colors of GTK themes with `@define-color`, for `gtk.css` of GTK 3 and 4.
+/// The window and theme background is the bar color, the foreground is the text color and the accent and selection are the workspace color.
+///
+/// The hyprland output assigns the roles and the numbered colors to variables in the functional `rgb(rrggbb)` notation of Hyprland,
+/// as `$bar`, `$workspace`, `$text` and `$colorN`, to be included in `hyprland.conf` with `source`.
+///
+/// The polybar output is a `[colors]` section with the roles and the numbered colors as `bar`, `workspace`, `text` and `colorN`,
+/// to be referenced as `${colors.bar}`. If [`Cli::alpha`] is given every color is written as `#AARRGGBB` with it as the alpha.
+///
+/// The emacs output is an elisp snippet that sets the `default` face to the bar color with the text color, the `mode-line` and `region` faces
+/// to the workspace color with its own text color and the `cursor` to the workspace color, and every color to `color-scheme-generator-colors`.
+///
+/// The env output is a shell `export` statement for every role and numbered color, as `BAR_COLOR`, `WORKSPACE_COLOR`, `TEXT_COLOR` and `COLORN`,
+/// to be evaluated by sh, bash, zsh or fish.
+///
+/// # Errors
+/// Will error if the color theme cannot be serialized by serde_json or serde_yml, or if an empty color theme is serialized to pywal, alacritty, base16, dunst, rofi, gtk-css or emacs.
+///
+/// # Examples
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::output::serialize_color_theme;
+/// let red = RGB { red: 255, green: 0, blue: 0 };
+/// let cyan = RGB { red: 0, green: 255, blue: 255 };
+/// let theme = [red.clone(), cyan.clone()];
+/// let text = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "text"]);
+/// assert_eq!(serialize_color_theme(&[red.clone()], &text).unwrap(), "#ff0000");
+/// assert_eq!(serialize_color_theme(&theme, &text).unwrap(), "#ff0000,#00ffff");
+/// assert_eq!(serialize_color_theme(&[], &text).unwrap(), "");
+/// let no_hash = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "text", "--text-no-hash"]);
+/// assert_eq!(serialize_color_theme(&theme, &no_hash).unwrap(), "ff0000,00ffff");
+///
+/// let xresources = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "xresources"]);
+/// let three = [red.clone(), cyan.clone(), RGB { red: 0, green: 0, blue: 255 }];
+/// assert_eq!(
+///     serialize_color_theme(&three, &xresources).unwrap(),
+///     "*background: #ff0000\n*foreground: #000000\n*.color0: #ff0000\n*.color1: #00ffff\n*.color2: #0000ff"
+/// );
+///
+/// let named = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "named"]);
+/// assert_eq!(serialize_color_theme(&theme, &named).unwrap(), "bar=red, workspace=aqua, text=black");
+///
+/// let css = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "css"]);
+/// assert_eq!(
+///     serialize_color_theme(&theme, &css).unwrap(),
+///     ":root {\n  --bar-color: #ff0000;\n  --workspace-color: #00ffff;\n  --text-color: #000000;\n  --color-0: #ff0000;\n  --color-1: #00ffff;\n}"
+/// );
+/// ```
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::output::serialize_color_theme;
+/// # let theme = [RGB { red: 255, green: 0, blue: 0 }, RGB { red: 0, green: 255, blue: 255 }];
+/// let pywal = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "pywal"]);
+/// let output = serialize_color_theme(&theme, &pywal).unwrap();
+/// let json = serde_json::from_str::<serde_json::Value>(&output).unwrap();
+/// for i in 0..16 {
+///     assert_eq!(json["colors"][format!("color{i}")], ["#ff0000", "#00ffff"][i % 2]);
+/// }
+/// assert_eq!(json["special"]["background"], "#ff0000");
+/// assert_eq!(json["special"]["foreground"], "#000000");
+/// assert_eq!(json["wallpaper"], "wallpaper.png");
+/// ```
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::output::serialize_color_theme;
+/// # let theme = [RGB { red: 255, green: 0, blue: 0 }, RGB { red: 0, green: 255, blue: 255 }];
+/// let alacritty = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "alacritty"]);
+/// let output = serialize_color_theme(&theme, &alacritty).unwrap();
+/// assert!(output.starts_with("[colors.primary]\nbackground = \"#ff0000\"\nforeground = \"#000000\"\n"));
+/// assert!(output.contains("\n\n[colors.normal]\nblack = \"#ff0000\"\nred = \"#00ffff\"\ngreen = \"#ff0000\"\n"));
+/// assert!(output.contains("\nwhite = \"#000000\"\n"));
+/// assert!(output.contains("\n\n[colors.bright]\nblack = \"#ff0000\"\nred = \"#00ffff\"\n"));
+///
+/// let yaml = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "alacritty-yaml"]);
+/// let output = serialize_color_theme(&theme, &yaml).unwrap();
+/// let yaml = serde_yml::from_str::<serde_yml::Value>(&output).unwrap();
+/// assert_eq!(yaml["colors"]["primary"]["background"], "#ff0000");
+/// assert_eq!(yaml["colors"]["normal"]["red"], "#00ffff");
+/// assert_eq!(yaml["colors"]["normal"].as_mapping().unwrap().len(), 8);
+/// ```
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::output::serialize_color_theme;
+/// # let theme = [RGB { red: 255, green: 0, blue: 0 }, RGB { red: 0, green: 255, blue: 255 }];
+/// let base16 = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "base16", "--scheme-name", "Sunset"]);
+/// let output = serialize_color_theme(&theme, &base16).unwrap();
+/// let yaml = serde_yml::from_str::<serde_yml::Value>(&output).unwrap();
+/// assert_eq!(yaml["scheme"], "Sunset");
+/// for i in 0..16 {
+///     let hex = yaml[format!("base{i:02X}")].as_str().unwrap();
+///     assert!(hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()));
+/// }
+/// assert_eq!(yaml["base08"], "00ffff");
+/// let unnamed = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "base16"]);
+/// let yaml = serde_yml::from_str::<serde_yml::Value>(&serialize_color_theme(&theme, &unnamed).unwrap()).unwrap();
+/// assert_eq!(yaml["scheme"], "wallpaper");
+/// ```
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::output::serialize_color_theme;
+/// # let theme = [RGB { red: 255, green: 0, blue: 0 }, RGB { red: 0, green: 255, blue: 255 }];
+/// let polybar = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "polybar"]);
+/// let output = serialize_color_theme(&theme, &polybar).unwrap();
+/// assert_eq!(output, "[colors]\nbar = #ff0000\nworkspace = #00ffff\ntext = #000000\ncolor0 = #ff0000\ncolor1 = #00ffff");
+/// let translucent = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "polybar", "--alpha", "204"]);
+/// let output = serialize_color_theme(&theme, &translucent).unwrap();
+/// assert!(output.contains("\nbar = #ccff0000\n"));
+/// assert!(output.lines().skip(1).all(|l| l.split(" = #").nth(1).unwrap().len() == 8));
+/// assert!(Cli::try_parse_from(["color_scheme_generator", "wallpaper.png", "--alpha", "256"]).is_err());
+///
+/// let meta = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "json-meta", "-c", "kmeans", "--complementary"]);
+/// let json = serde_json::from_str::<serde_json::Value>(&serialize_color_theme(&theme, &meta).unwrap()).unwrap();
+/// assert_eq!(json["path"], "wallpaper.png");
+/// assert_eq!(json["centrality"], "kmeans");
+/// assert_eq!(json["color_themes"]["complementary"], true);
+/// assert!(json["generated_at"].as_u64().unwrap() > 1_700_000_000);
+/// assert_eq!(json["colors"][1]["green"], 255);
+/// ```
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::output::serialize_color_theme;
+/// # let theme = [RGB { red: 255, green: 0, blue: 0 }, RGB { red: 0, green: 255, blue: 255 }];
+/// let dunst = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "dunst"]);
+/// let output = serialize_color_theme(&theme, &dunst).unwrap();
+/// for urgency in ["low", "normal", "critical"] {
+///     let section = output.split(&format!("[urgency_{urgency}]\n")).nth(1).unwrap();
+///     let background = section.lines().find_map(|l| l.trim().strip_prefix("background = ")).unwrap();
+///     assert!(background.len() == 9 && background.starts_with("\"#") && background.ends_with('"'));
+///     assert!(background[2..8].chars().all(|c| c.is_ascii_hexdigit()));
+/// }
+/// assert!(output.contains("[urgency_critical]\n    background = \"#00ffff\"\n"));
+/// ```
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::output::serialize_color_theme;
+/// # let theme = [RGB { red: 255, green: 0, blue: 0 }, RGB { red: 0, green: 255, blue: 255 }];
+/// let rofi = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "rofi"]);
+/// let output = serialize_color_theme(&theme, &rofi).unwrap();
+/// assert!(output.starts_with("* {\n") && output.ends_with('}'));
+/// for (property, hex) in [("background", "#ff0000"), ("foreground", "#000000"), ("selected-background", "#00ffff")] {
+///     assert!(output.contains(&format!("    {property}: {hex};\n")));
+/// }
+/// ```
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::output::serialize_color_theme;
+/// # let theme = [RGB { red: 255, green: 0, blue: 0 }, RGB { red: 0, green: 255, blue: 255 }];
+/// let gtk = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "gtk-css"]);
+/// let output = serialize_color_theme(&theme, &gtk).unwrap();
+/// assert!(output.starts_with("@define-color"));
+/// assert!(output.lines().all(|l| l.starts_with("@define-color ") && l.ends_with(';')));
+/// assert!(output.contains("@define-color theme_bg_color #ff0000;"));
+/// assert!(output.contains("@define-color accent_bg_color #00ffff;"));
+/// ```
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::output::serialize_color_theme;
+/// # let theme = [RGB { red: 255, green: 0, blue: 0 }, RGB { red: 0, green: 255, blue: 255 }];
+/// let hyprland = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "hyprland"]);
+/// let output = serialize_color_theme(&theme, &hyprland).unwrap();
+/// assert!(output.lines().all(|l| l.starts_with('$') && l.contains(" = rgb(") && l.ends_with(')') && !l.contains('#')));
+/// assert_eq!(
+///     output,
+///     "$bar = rgb(ff0000)\n$workspace = rgb(00ffff)\n$text = rgb(000000)\n$color0 = rgb(ff0000)\n$color1 = rgb(00ffff)"
+/// );
+/// ```
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::output::serialize_color_theme;
+/// # let theme = [RGB { red: 255, green: 0, blue: 0 }, RGB { red: 0, green: 255, blue: 255 }];
+/// let meta = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "json-meta", "--text-color", "#ff0000"]);
+/// let output: serde_json::Value = serde_json::from_str(&serialize_color_theme(&theme, &meta).unwrap()).unwrap();
+/// assert_eq!(output["text_color"], serde_json::json!({"red": 255, "green": 0, "blue": 0}));
+/// let css = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "css", "--text-color", "#ff0000"]);
+/// assert!(serialize_color_theme(&theme, &css).unwrap().contains("--text-color: #ff0000;"));
+/// ```
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::output::serialize_color_theme;
+/// # let theme = [RGB { red: 255, green: 0, blue: 0 }, RGB { red: 0, green: 255, blue: 255 }];
+/// let emacs = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "emacs"]);
+/// let output = serialize_color_theme(&theme, &emacs).unwrap();
+/// assert!(output.starts_with("(custom-set-faces\n"));
+/// assert_eq!(output.matches('(').count(), output.matches(')').count());
+/// let face = |name: &str| {
+///     let line = output.lines().find(|l| l.starts_with(&format!(" '({name} "))).unwrap();
+///     line.split('"').skip(1).step_by(2).collect::<Vec<_>>()
+/// };
+/// assert_eq!(face("default"), ["#ff0000", "#000000"]);
+/// assert_eq!(face("mode-line"), ["#00ffff", "#000000"]);
+/// assert_eq!(face("cursor"), ["#00ffff"]);
+/// assert!(output.ends_with("(setq color-scheme-generator-colors '(\"#ff0000\" \"#00ffff\"))"));
+/// ```
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::output::serialize_color_theme;
+/// # let theme = [RGB { red: 255, green: 0, blue: 0 }, RGB { red: 0, green: 255, blue: 255 }];
+/// let text = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "text", "--bar-alpha", "204"]);
+/// assert_eq!(serialize_color_theme(&theme, &text).unwrap(), "#ff0000cc,#00ffff");
+/// let css = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "css", "--workspace-alpha", "0", "--text-alpha", "128"]);
+/// let output = serialize_color_theme(&theme, &css).unwrap();
+/// assert!(output.contains("--bar-color: #ff0000;\n"));
+/// assert!(output.contains("--workspace-color: #00ffff00;\n"));
+/// assert!(output.contains("--text-color: #00000080;\n"));
+/// assert!(output.contains("--color-1: #00ffff00;\n"));
+///
+/// let json = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "--bar-alpha", "204"]);
+/// let output: serde_json::Value = serde_json::from_str(&serialize_color_theme(&theme, &json).unwrap()).unwrap();
+/// assert_eq!(output[0], serde_json::json!({"red": 255, "green": 0, "blue": 0, "alpha": 204}));
+/// assert!(output[1].get("alpha").is_none());
+/// let yaml = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "yaml", "--bar-alpha", "204"]);
+/// let output = serde_yml::from_str::<serde_yml::Value>(&serialize_color_theme(&theme, &yaml).unwrap()).unwrap();
+/// assert_eq!(output[0]["alpha"], 204);
+///
+/// let opaque = Cli::parse_from(["color_scheme_generator", "wallpaper.png"]);
+/// assert_eq!(serialize_color_theme(&theme, &opaque).unwrap(), serde_json::to_string(&theme).unwrap());
+/// ```
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::output::serialize_color_theme;
+/// # let theme = [RGB { red: 255, green: 0, blue: 0 }, RGB { red: 0, green: 255, blue: 255 }];
+/// let env = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "-s", "env"]);
+/// let output = serialize_color_theme(&theme, &env).unwrap();
+/// for line in output.lines() {
+///     let (name, value) = line.strip_prefix("export ").unwrap().split_once('=').unwrap();
+///     assert!(!name.is_empty() && name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_'));
+///     assert!(value.len() == 9 && value.starts_with("'#") && value.ends_with('\''));
+/// }
+/// assert_eq!(
+///     output,
+///     "export BAR_COLOR='#ff0000'\nexport WORKSPACE_COLOR='#00ffff'\nexport TEXT_COLOR='#000000'\nexport COLOR0='#ff0000'\nexport COLOR1='#00ffff'"
+/// );
+/// ```
+pub fn serialize_color_theme(colors: &[RGB], args: &Cli) -> anyhow::Result<String> {
+    if let Some(template) = &args.template {
+        return render_template(template, colors, args.text_color.as_ref());
+    }
+    let text_color = args.text_color.as_ref();
+    let output = match args.serialization_format {
+        OutputFormat::JSON => serde_json::to_string(&with_alpha(colors, args))?,
+        OutputFormat::JSONMETA => serde_json::to_string(&ColorThemeMetadata::new(colors, args))?,
+        OutputFormat::YAML => serde_yml::to_string(&with_alpha(colors, args))?,
+        OutputFormat::TEXT => text(&with_alpha(colors, args), args.text_no_hash),
+        OutputFormat::CSS => css(colors, args),
+        OutputFormat::PYWAL => pywal(colors, args)?,
+        OutputFormat::NAMED => named(colors, text_color),
+        OutputFormat::XRESOURCES => xresources(colors, text_color),
+        OutputFormat::ALACRITTY => toml(&serde_yml::to_value(alacritty(colors, text_color)?)?),
+        OutputFormat::ALACRITTYYAML => serde_yml::to_string(&alacritty(colors, text_color)?)?,
+        OutputFormat::BASE16 => serde_yml::to_string(&base16(colors, args)?)?,
+        OutputFormat::DUNST => dunst(colors, text_color)?,
+        OutputFormat::ROFI => rofi(colors, text_color)?,
+        OutputFormat::GTKCSS => gtk_css(colors, text_color)?,
+        OutputFormat::HYPRLAND => hyprland(colors, text_color),
+        OutputFormat::POLYBAR => polybar(colors, text_color, args.alpha),
+        OutputFormat::EMACS => emacs(colors, text_color)?,
+        OutputFormat::ENV => env(colors, text_color),
+    };
+    Ok(output)
+}
+
+/// Serialize a color theme like [`serialize_color_theme`], annotating every color with the fraction of the image it covers.
+///
+/// # Notes
+/// `coverage` is in the order of the colors, see [`crate::theme_calculation::adjust::adjust_with_coverage`].
+/// The JSON and YAML outputs add a `coverage` field to every color, which is null if it is unknown,
+/// and the text output follows every color with its coverage in percent or `n/a`.
+/// Every other output format, and [`Cli::template`], is serialized without the coverage.
+///
+/// # Errors
+/// Will error if the color theme cannot be serialized, see [`serialize_color_theme`].
+///
+/// # Examples
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::output::serialize_color_theme_with_coverage;
+/// let theme = [RGB { red: 255, green: 0, blue: 0 }, RGB { red: 0, green: 0, blue: 255 }];
+/// let coverage = [Some(0.75), Some(0.25)];
+/// let json = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "--percentage"]);
+/// assert_eq!(
+///     serialize_color_theme_with_coverage(&theme, &coverage, &json).unwrap(),
+///     r#"[{"red":255,"green":0,"blue":0,"coverage":0.75},{"red":0,"green":0,"blue":255,"coverage":0.25}]"#
+/// );
+/// let text = Cli::parse_from(["color_scheme_generator", "wallpaper.png", "--percentage", "-s", "text"]);
+/// assert_eq!(serialize_color_theme_with_coverage(&theme, &coverage, &text).unwrap(), "#ff0000 75.0%,#0000ff 25.0%");
+/// assert_eq!(serialize_color_theme_with_coverage(&theme, &[None, None], &text).unwrap(), "#ff0000 n/a,#0000ff n/a");
+/// ```
+pub fn serialize_color_theme_with_coverage(
+    colors: &[RGB],
+    coverage: &[Option<f64>],
+    args: &Cli,
+) -> anyhow::Result<String> {
+    let colors_with_alpha = with_alpha(colors, args);
+    let covered = colors_with_alpha
+        .iter()
+        .enumerate()
+        .map(|(i, c)| CoveredColor {
+            color: c.clone(),
+            coverage: coverage.get(i).copied().flatten(),
+        })
+        .collect::<Vec<_>>();
+    let output = match (&args.template, &args.serialization_format) {
+        (None, OutputFormat::JSON) => serde_json::to_string(&covered)?,
+        (None, OutputFormat::YAML) => serde_yml::to_string(&covered)?,
+        (None, OutputFormat::TEXT) => text(&colors_with_alpha, args.text_no_hash)
+            .split(',')
+            .zip(&covered)
+            .map(|(c, covered)| match covered.coverage {
+                Some(coverage) => format!("{c} {:.1}%", coverage * 100.0),
+                None => format!("{c} n/a"),
+            })
+            .collect::<Vec<_>>()
+            .join(","),
+        _ => serialize_color_theme(colors, args)?,
+    };
+    Ok(output)
+}
+
+#[derive(Serialize)]
+struct CoveredColor {
+    #[serde(flatten)]
+    color: RGBA,
+    coverage: Option<f64>,
+}
+
+/// Fill the placeholders of a template with the colors of a color theme.
+///
+/// # Notes
+/// The roles are available as `{bar}`, `{workspace}` and `{text}`, which is `text_color` if it is given, and every color by its index as `{colorN}`,
+/// `{hexN}` without the leading `#` and `{rN}`, `{gN}` and `{bN}` for its sub pixels. Braces are escaped by doubling them.
+///
+/// # Errors
+/// Will error if a placeholder is unknown, is not closed, or refers to a color the color theme does not have.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::output::render_template;
+/// let theme = [RGB { red: 255, green: 0, blue: 0 }, RGB { red: 0, green: 255, blue: 255 }];
+/// assert_eq!(render_template("bar={bar} ws={workspace} text={text}", &theme, None).unwrap(), "bar=#ff0000 ws=#00ffff text=#000000");
+/// let white = RGB { red: 255, green: 255, blue: 255 };
+/// assert_eq!(render_template("text={text}", &theme, Some(&white)).unwrap(), "text=#ffffff");
+/// assert_eq!(render_template("{color1} {hex1} rgb({r0}, {g0}, {b0})", &theme, None).unwrap(), "#00ffff 00ffff rgb(255, 0, 0)");
+/// assert_eq!(render_template("{{bar}} {bar}", &theme, None).unwrap(), "{bar} #ff0000");
+///
+/// let error = render_template("{color2}", &theme, None).unwrap_err().to_string();
+/// assert_eq!(error, "placeholder {color2} is out of range, the color theme has 2 colors");
+/// let error = render_template("{background}", &theme, None).unwrap_err().to_string();
+/// assert!(error.starts_with("unknown placeholder {background}, valid placeholders are {bar}, {workspace}, {text}"));
+/// assert!(render_template("{bar", &theme, None).is_err());
+/// assert!(render_template("{workspace}", &theme[..1], None).is_err());
+/// ```
+pub fn render_template(
+    template: &str,
+    colors: &[RGB],
+    text_color: Option<&RGB>,
+) -> anyhow::Result<String> {
+    let roles = roles(colors, text_color);
+    let mut output = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                output.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                output.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+                if !closed {
+                    return Err(anyhow::anyhow!("placeholder {{{name} is not closed"));
+                }
+                output.push_str(&placeholder(&name, &roles, colors)?);
+            }
+            c => output.push(c),
+        }
+    }
+    Ok(output)
+}
+
+/// Get the value of a placeholder of [`render_template`].
+fn placeholder(name: &str, roles: &[(&str, RGB)], colors: &[RGB]) -> anyhow::Result<String> {
+    if let Some((_, color)) = roles.iter().find(|(role, _)| *role == name) {
+        return Ok(color.to_string());
+    }
+    let indexed = ["color", "hex", "r", "g", "b"]
+        .into_iter()
+        .find_map(|prefix| {
+            let index = name.strip_prefix(prefix)?.parse::<usize>().ok()?;
+            Some((prefix, index))
+        });
+    match indexed {
+        Some((prefix, index)) => {
+            let color = colors.get(index).ok_or(anyhow::anyhow!(
+                "placeholder {{{name}}} is out of range, the color theme has {} colors",
+                colors.len()
+            ))?;
+            Ok(match prefix {
+                "color" => color.to_string(),
+                "hex" => color.to_string().trim_start_matches('#').to_string(),
+                "r" => color.red.to_string(),
+                "g" => color.green.to_string(),
+                _ => color.blue.to_string(),
+            })
+        }
+        None if ["bar", "workspace", "text"].contains(&name) => Err(anyhow::anyhow!(
+            "placeholder {{{name}}} is out of range, the color theme has {} colors",
+            colors.len()
+        )),
+        None => Err(anyhow::anyhow!(
+            "unknown placeholder {{{name}}}, valid placeholders are {{bar}}, {{workspace}}, {{text}}, {{colorN}}, {{hexN}}, {{rN}}, {{gN}} and {{bN}} where N is from 0 to {}",
+            colors.len().saturating_sub(1)
+        )),
+    }
+}
+
+/// Render every color of a color theme as a block of 24-bit ANSI background color followed by its hexadecimal value, one color per line.
+///
+/// # Notes
+/// The output contains escape codes so it should only be printed to a terminal.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::output::preview;
+/// let preview = preview(&[RGB { red: 255, green: 0, blue: 128 }]);
+/// assert!(preview.contains("\x1b[48;2;255;0;128m"));
+/// assert!(preview.contains("#ff0080"));
+/// ```
+pub fn preview(colors: &[RGB]) -> String {
+    colors
+        .iter()
+        .map(|c| {
+            format!(
+                "\x1b[48;2;{};{};{}m      \x1b[0m {c}\n",
+                c.red, c.green, c.blue
+            )
+        })
+        .collect::<String>()
+}
+
+/// Terminals whose `TERM` starts with one of these names are known to display sixel images.
+const SIXEL_TERMS: [&str; 5] = ["foot", "mlterm", "yaft", "contour", "wezterm"];
+
+/// Whether a terminal is known to display sixel images, given its `TERM` and `TERM_PROGRAM` environment variables.
+///
+/// # Notes
+/// Terminals are not queried, so only terminals that support sixel in their default configuration are recognized
+/// and every other terminal is assumed not to support it.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::output::supports_sixel;
+/// assert!(supports_sixel(Some("foot"), None));
+/// assert!(supports_sixel(Some("xterm-256color"), Some("WezTerm")));
+/// assert!(!supports_sixel(Some("xterm-256color"), None));
+/// assert!(!supports_sixel(None, None));
+/// ```
+pub fn supports_sixel(term: Option<&str>, term_program: Option<&str>) -> bool {
+    term.is_some_and(|term| SIXEL_TERMS.iter().any(|t| term.starts_with(t)))
+        || term_program == Some("WezTerm")
+}
+
+/// Encode an image as a sixel stream that is displayed inline by sixel capable terminals.
+///
+/// # Notes
+/// Sixel images have at most 256 colors, pixels of further colors are drawn with the nearest of the first 256 colors.
+/// The swatch of a color theme has one color per color of the color theme, so it is encoded exactly.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::output::sixel;
+/// # use color_scheme_generator::theme_calculation::swatch::swatch;
+/// let colors = [RGB { red: 255, green: 0, blue: 0 }, RGB { red: 0, green: 0, blue: 255 }];
+/// let sixel = sixel(&swatch(&colors, 12, 7));
+/// assert!(sixel.starts_with("\x1bPq"));
+/// assert!(sixel.ends_with("\x1b\\"));
+/// assert!(sixel.contains("#0;2;100;0;0") && sixel.contains("#1;2;0;0;100"));
+/// // Six rows of six red pixels, then six blue pixels in the same band, and the last row of the image.
+/// assert!(sixel.contains("#0!6~$#1!6?!6~$-#0!6@$#1!6?!6@$-"));
+/// ```
+pub fn sixel(image: &image::RgbImage) -> String {
+    let mut palette: Vec<image::Rgb<u8>> = vec![];
+    let indexes = image
+        .pixels()
+        .map(|p| match palette.iter().position(|c| c == p) {
+            Some(i) => i,
+            None if palette.len() < 256 => {
+                palette.push(*p);
+                palette.len() - 1
+            }
+            None => (0..palette.len())
+                .min_by_key(|i| {
+                    (0..3)
+                        .map(|c| (i32::from(palette[*i].0[c]) - i32::from(p.0[c])).pow(2))
+                        .sum::<i32>()
+                })
+                .unwrap_or_default(),
+        })
+        .collect::<Vec<_>>();
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let mut sixel = format!("\x1bPq\"1;1;{width};{height}");
+    for (i, c) in palette.iter().enumerate() {
+        let [r, g, b] = c.0.map(|c| (u32::from(c) * 100 + 127) / 255);
+        sixel.push_str(&format!("#{i};2;{r};{g};{b}"));
+    }
+    for band in (0..height).step_by(6) {
+        for color in 0..palette.len() {
+            let sixels = (0..width)
+                .map(|x| {
+                    (0..6)
+                        .filter(|row| band + row < height)
+                        .filter(|row| indexes[(band + row) * width + x] == color)
+                        .fold(0u8, |bits, row| bits | 1 << row)
+                })
+                .collect::<Vec<_>>();
+            let Some(end) = sixels.iter().rposition(|bits| *bits != 0) else {
+                continue;
+            };
+            sixel.push_str(&format!("#{color}"));
+            let mut x = 0;
+            while x <= end {
+                let run = sixels[x..=end]
+                    .iter()
+                    .take_while(|b| **b == sixels[x])
+                    .count();
+                let c = char::from(63 + sixels[x]);
+                match run > 3 {
+                    true => sixel.push_str(&format!("!{run}{c}")),
+                    false => sixel.extend(std::iter::repeat_n(c, run)),
+                }
+                x += run;
+            }
+            sixel.push('$');
+        }
+        sixel.push('-');
+    }
+    sixel.push_str("\x1b\\");
+    sixel
+}
+
+/// Serialize a listing of the cache as given by [`crate::database::DatabaseConnection::list_cached_wallpapers`].
+///
+/// # Notes
+/// JSON and YAML produce a list of records with the path, centrality and number of cached color themes of every wallpaper.
+/// Every other format produces a table with one wallpaper per line and tab separated columns.
+///
+/// # Examples
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Centrality, Cli, Wallpaper};
+/// # use color_scheme_generator::output::serialize_cache_listing;
+/// let wallpaper = Wallpaper {path : "wall.png".into(), centrality: Centrality::Median, modified: 0, content_hash: String::new(), analysis: String::new()};
+/// let args = Cli::parse_from(["color_scheme_generator", "--list-cache"]);
+/// let listing = serialize_cache_listing(&[(wallpaper, 2)], &args).unwrap();
+/// assert_eq!(listing, r#"[{"path":"wall.png","centrality":"median","color_themes":2}]"#);
+/// ```
+pub fn serialize_cache_listing(
+    entries: &[(Wallpaper, usize)],
+    args: &Cli,
+) -> anyhow::Result<String> {
+    let entries = entries
+        .iter()
+        .map(|(wallpaper, color_themes)| CacheEntry {
+            path: wallpaper.path.to_string_lossy().to_string(),
+            centrality: wallpaper.centrality.to_string(),
+            color_themes: *color_themes,
+        })
+        .collect::<Vec<_>>();
+    let output = match args.serialization_format {
+        OutputFormat::JSON | OutputFormat::JSONMETA => serde_json::to_string(&entries)?,
+        OutputFormat::YAML => serde_yml::to_string(&entries)?,
+        _ => entries
+            .iter()
+            .map(|e| format!("{}\t{}\t{}", e.path, e.centrality, e.color_themes))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    Ok(output)
+}
+
+#[derive(Serialize)]
+struct ColorThemeMetadata<'a> {
+    path: Option<&'a Path>,
+    centrality: String,
+    color_themes: &'a ColorThemeOption,
+    generated_at: u64,
+    text_color: Option<RGB>,
+    colors: &'a [RGB],
+}
+
+impl<'a> ColorThemeMetadata<'a> {
+    fn new(colors: &'a [RGB], args: &'a Cli) -> ColorThemeMetadata<'a> {
+        ColorThemeMetadata {
+            path: args.image.as_deref(),
+            centrality: args.centrality.to_string(),
+            color_themes: &args.color_themes,
+            generated_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            text_color: colors
+                .first()
+                .map(|bar| foreground(bar, args.text_color.as_ref())),
+            colors,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CacheEntry {
+    path: String,
+    centrality: String,
+    color_themes: usize,
+}
+
+/// Write `contents` followed by a newline to the file at `path`, replacing it atomically.
+///
+/// # Notes
+/// The contents are written to a temporary file next to `path` which is then renamed over it,
+/// so readers of `path` never see a partially written file.
+///
+/// # Errors
+/// Will error with the path if the file cannot be written.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::output::write_atomically;
+/// let path = std::env::temp_dir().join("color_scheme_generator_output.json");
+/// let colors = vec![RGB { red: 255, green: 0, blue: 128 }];
+/// write_atomically(&path, &serde_json::to_string(&colors).unwrap()).unwrap();
+/// let read = std::fs::read_to_string(&path).unwrap();
+/// assert_eq!(serde_json::from_str::<Vec<RGB>>(&read).unwrap(), colors);
+/// ```
+pub fn write_atomically(path: &Path, contents: &str) -> anyhow::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or(anyhow::anyhow!("{} is not a file path", path.display()))?;
+    let temp_path = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    std::fs::write(&temp_path, format!("{contents}\n"))
+        .and_then(|_| std::fs::rename(&temp_path, path))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&temp_path);
+            anyhow::anyhow!("Could not write output to {}: {e}", path.display())
+        })
+}
+
+/// Get the color of text drawn over `background`, which is `text_color` if it is given.
+fn foreground(background: &RGB, text_color: Option<&RGB>) -> RGB {
+    text_color
+        .cloned()
+        .unwrap_or_else(|| gamut::text_color(background))
+}
+
+/// Label the bar, workspace and text colors of a color theme, skipping the roles the color theme is too short to fill.
+fn roles(colors: &[RGB], text_color: Option<&RGB>) -> Vec<(&'static str, RGB)> {
+    let mut roles = ["bar", "workspace"]
+        .into_iter()
+        .zip(colors.iter().cloned())
+        .collect::<Vec<_>>();
+    if let Some(bar) = colors.first() {
+        roles.push(("text", foreground(bar, text_color)));
+    }
+    roles
+}
+
+/// Get the alpha of a role given by [`Cli::bar_alpha`], [`Cli::workspace_alpha`] or [`Cli::text_alpha`].
+fn role_alpha(role: &str, args: &Cli) -> Option<u8> {
+    match role {
+        "bar" => args.bar_alpha,
+        "workspace" => args.workspace_alpha,
+        "text" => args.text_alpha,
+        _ => None,
+    }
+}
+
+/// Give the bar and workspace colors, the first two colors, their alphas.
+fn with_alpha(colors: &[RGB], args: &Cli) -> Vec<RGBA> {
+    colors
+        .iter()
+        .enumerate()
+        .map(|(i, c)| RGBA {
+            color: c.clone(),
+            alpha: ["bar", "workspace"]
+                .get(i)
+                .and_then(|role| role_alpha(role, args)),
+        })
+        .collect()
+}
+
+/// Write the color theme as CSS custom properties, named by their role and by their index.
+fn css(colors: &[RGB], args: &Cli) -> String {
+    let roles = roles(colors, args.text_color.as_ref())
+        .into_iter()
+        .map(|(role, c)| {
+            let c = RGBA {
+                color: c,
+                alpha: role_alpha(role, args),
+            };
+            format!("  --{role}-color: {c};\n")
+        });
+    let indexes = with_alpha(colors, args)
+        .into_iter()
+        .enumerate()
+        .map(|(i, c)| format!("  --color-{i}: {c};\n"));
+    format!(":root {{\n{}}}", roles.chain(indexes).collect::<String>())
+}
+
+/// Set the X resources of terminal emulators to the roles and the numbered colors of the color theme.
+fn xresources(colors: &[RGB], text_color: Option<&RGB>) -> String {
+    let roles = roles(colors, text_color)
+        .into_iter()
+        .filter_map(|(role, c)| match role {
+            "bar" => Some(format!("*background: {c}")),
+            "text" => Some(format!("*foreground: {c}")),
+            _ => None,
+        });
+    let indexes = colors
+        .iter()
+        .enumerate()
+        .map(|(i, c)| format!("*.color{i}: {c}"));
+    roles.chain(indexes).collect::<Vec<_>>().join("\n")
+}
+
+/// Write the urgency sections of `dunstrc`, the critical urgency using the workspace color as its background.
+fn dunst(colors: &[RGB], text_color: Option<&RGB>) -> anyhow::Result<String> {
+    let bar = colors
+        .first()
+        .ok_or(anyhow::anyhow!("cannot serialize an empty color theme"))?;
+    let workspace = colors.get(1).unwrap_or(bar);
+    let section = |urgency: &str, background: &RGB, frame: &RGB| {
+        format!(
+            "[urgency_{urgency}]\n    background = \"{background}\"\n    foreground = \"{}\"\n    frame_color = \"{frame}\"\n",
+            foreground(background, text_color)
+        )
+    };
+    Ok([
+        section("low", bar, workspace),
+        section("normal", bar, workspace),
+        section("critical", workspace, bar),
+    ]
+    .join("\n")
+    .trim_end()
+    .to_string())
+}
+
+/// Write the colors of a rofi `.rasi` theme, the selected entry using the workspace color as its background.
+fn rofi(colors: &[RGB], text_color: Option<&RGB>) -> anyhow::Result<String> {
+    let bar = colors
+        .first()
+        .ok_or(anyhow::anyhow!("cannot serialize an empty color theme"))?;
+    let workspace = colors.get(1).unwrap_or(bar);
+    Ok(format!(
+        "* {{\n    background: {bar};\n    foreground: {};\n    selected-background: {workspace};\n    selected-foreground: {};\n}}",
+        foreground(bar, text_color),
+        foreground(workspace, text_color)
+    ))
+}
+
+/// Define the named colors of GTK themes, using the workspace color as the accent.
+fn gtk_css(colors: &[RGB], text_color: Option<&RGB>) -> anyhow::Result<String> {
+    let bar = colors
+        .first()
+        .ok_or(anyhow::anyhow!("cannot serialize an empty color theme"))?;
+    let workspace = colors.get(1).unwrap_or(bar);
+    let (text, accent_text) = (
+        foreground(bar, text_color),
+        foreground(workspace, text_color),
+    );
+    Ok([
+        ("theme_bg_color", bar),
+        ("theme_fg_color", &text),
+        ("theme_selected_bg_color", workspace),
+        ("theme_selected_fg_color", &accent_text),
+        ("window_bg_color", bar),
+        ("window_fg_color", &text),
+        ("accent_color", workspace),
+        ("accent_bg_color", workspace),
+        ("accent_fg_color", &accent_text),
+    ]
+    .iter()
+    .map(|(name, c)| format!("@define-color {name} {c};"))
+    .collect::<Vec<_>>()
+    .join("\n"))
+}
+
+/// Assign the roles and the numbered colors of the color theme to Hyprland variables.
+fn hyprland(colors: &[RGB], text_color: Option<&RGB>) -> String {
+    let rgb = |c: &RGB| format!("rgb({})", &c.to_string()[1..]);
+    let roles = roles(colors, text_color)
+        .into_iter()
+        .map(|(role, c)| format!("${role} = {}", rgb(&c)));
+    let indexes = colors
+        .iter()
+        .enumerate()
+        .map(|(i, c)| format!("$color{i} = {}", rgb(c)));
+    roles.chain(indexes).collect::<Vec<_>>().join("\n")
+}
+
+/// Write the roles and the numbered colors of the color theme as the `[colors]` section of a polybar configuration.
+fn polybar(colors: &[RGB], text_color: Option<&RGB>, alpha: Option<u8>) -> String {
+    let hex = |c: &RGB| match alpha {
+        Some(alpha) => format!("#{alpha:02x}{}", &c.to_string()[1..]),
+        None => c.to_string(),
+    };
+    let roles = roles(colors, text_color)
+        .into_iter()
+        .map(|(role, c)| format!("{role} = {}", hex(&c)));
+    let indexes = colors
+        .iter()
+        .enumerate()
+        .map(|(i, c)| format!("color{i} = {}", hex(c)));
+    std::iter::once("[colors]".to_string())
+        .chain(roles)
+        .chain(indexes)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Write an elisp snippet setting the faces of Emacs to the roles of the color theme and every color to a variable.
+fn emacs(colors: &[RGB], text_color: Option<&RGB>) -> anyhow::Result<String> {
+    let bar = colors
+        .first()
+        .ok_or(anyhow::anyhow!("cannot serialize an empty color theme"))?;
+    let workspace = colors.get(1).unwrap_or(bar);
+    let face = |name: &str, background: &RGB, foreground: Option<&RGB>| match foreground {
+        Some(foreground) => {
+            format!(" '({name} ((t (:background \"{background}\" :foreground \"{foreground}\"))))")
+        }
+        None => format!(" '({name} ((t (:background \"{background}\"))))"),
+    };
+    let palette = colors
+        .iter()
+        .map(|c| format!("\"{c}\""))
+        .collect::<Vec<_>>()
+        .join(" ");
+    Ok(format!(
+        "(custom-set-faces\n{}\n{}\n{}\n{})\n(setq color-scheme-generator-colors '({palette}))",
+        face("default", bar, Some(&foreground(bar, text_color))),
+        face(
+            "mode-line",
+            workspace,
+            Some(&foreground(workspace, text_color))
+        ),
+        face(
+            "region",
+            workspace,
+            Some(&foreground(workspace, text_color))
+        ),
+        face("cursor", workspace, None),
+    ))
+}
+
+/// Write the roles and the numbered colors of the color theme as shell `export` statements.
+fn env(colors: &[RGB], text_color: Option<&RGB>) -> String {
+    let roles = roles(colors, text_color)
+        .into_iter()
+        .map(|(role, c)| (format!("{}_COLOR", role.to_uppercase()), c));
+    let indexes = colors
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (format!("COLOR{i}"), c.clone()));
+    roles
+        .chain(indexes)
+        .map(|(name, c)| format!("export {name}={}", shell_quote(&c.to_string())))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Quote a value for the shell in single quotes, so it is never expanded.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Name every role of the color theme by its nearest CSS named color.
+fn named(colors: &[RGB], text_color: Option<&RGB>) -> String {
+    roles(colors, text_color)
+        .into_iter()
+        .map(|(role, c)| format!("{role}={}", c.nearest_name()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Schema of pywal's `colors.json`.
+#[derive(Serialize)]
+struct Pywal {
+    wallpaper: String,
+    alpha: String,
+    special: PywalSpecial,
+    colors: PywalColors,
+}
+
+/// Special colors of pywal's `colors.json`.
+#[derive(Serialize)]
+struct PywalSpecial {
+    background: String,
+    foreground: String,
+    cursor: String,
+}
+
+/// The sixteen terminal colors of pywal's `colors.json`.
+#[derive(Serialize)]
+struct PywalColors {
+    color0: String,
+    color1: String,
+    color2: String,
+    color3: String,
+    color4: String,
+    color5: String,
+    color6: String,
+    color7: String,
+    color8: String,
+    color9: String,
+    color10: String,
+    color11: String,
+    color12: String,
+    color13: String,
+    color14: String,
+    color15: String,
+}
+
+/// Write the color theme as pywal's `colors.json`, cycling the color theme to fill the sixteen terminal colors.
+fn pywal(colors: &[RGB], args: &Cli) -> anyhow::Result<String> {
+    let background = colors
+        .first()
+        .ok_or(anyhow::anyhow!("cannot serialize an empty color theme"))?;
+    let foreground = foreground(background, args.text_color.as_ref());
+    let color = |i: usize| colors[i % colors.len()].to_string();
+    let pywal = Pywal {
+        wallpaper: args
+            .image
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_default(),
+        alpha: String::from("100"),
+        special: PywalSpecial {
+            background: background.to_string(),
+            foreground: foreground.to_string(),
+            cursor: foreground.to_string(),
+        },
+        colors: PywalColors {
+            color0: color(0),
+            color1: color(1),
+            color2: color(2),
+            color3: color(3),
+            color4: color(4),
+            color5: color(5),
+            color6: color(6),
+            color7: color(7),
+            color8: color(8),
+            color9: color(9),
+            color10: color(10),
+            color11: color(11),
+            color12: color(12),
+            color13: color(13),
+            color14: color(14),
+            color15: color(15),
+        },
+    };
+    Ok(serde_json::to_string_pretty(&pywal)?)
+}
+
+#[derive(Serialize)]
+struct Alacritty {
+    colors: AlacrittyColors,
+}
+
+#[derive(Serialize)]
+struct AlacrittyColors {
+    primary: AlacrittyPrimary,
+    normal: AlacrittyPalette,
+    bright: AlacrittyPalette,
+}
+
+#[derive(Serialize)]
+struct AlacrittyPrimary {
+    background: String,
+    foreground: String,
+}
+
+#[derive(Serialize)]
+struct AlacrittyPalette {
+    black: String,
+    red: String,
+    green: String,
+    yellow: String,
+    blue: String,
+    magenta: String,
+    cyan: String,
+    white: String,
+}
+
+impl AlacrittyPalette {
+    fn new(color: impl Fn(usize) -> String) -> AlacrittyPalette {
+        AlacrittyPalette {
+            black: color(0),
+            red: color(1),
+            green: color(2),
+            yellow: color(3),
+            blue: color(4),
+            magenta: color(5),
+            cyan: color(6),
+            white: color(7),
+        }
+    }
+}
+
+/// Fill the colors of an Alacritty configuration from the color theme, defaulting missing colors to the bar and text colors.
+fn alacritty(colors: &[RGB], text_color: Option<&RGB>) -> anyhow::Result<Alacritty> {
+    let background = colors
+        .first()
+        .ok_or(anyhow::anyhow!("cannot serialize an empty color theme"))?;
+    let foreground = foreground(background, text_color);
+    let normal = |i: usize| match (colors.get(i), i) {
+        (Some(c), _) => c.to_string(),
+        (None, 7) => foreground.to_string(),
+        (None, _) => background.to_string(),
+    };
+    let bright = |i: usize| {
+        colors
+            .get(8 + i)
+            .map(|c| c.to_string())
+            .unwrap_or(normal(i))
+    };
+    Ok(Alacritty {
+        colors: AlacrittyColors {
+            primary: AlacrittyPrimary {
+                background: background.to_string(),
+                foreground: foreground.to_string(),
+            },
+            normal: AlacrittyPalette::new(normal),
+            bright: AlacrittyPalette::new(bright),
+        },
+    })
+}
+
+/// Derive the sixteen colors of a base16 scheme from the color theme.
+fn base16(colors: &[RGB], args: &Cli) -> anyhow::Result<serde_yml::Mapping> {
+    let background = colors
+        .first()
+        .ok_or(anyhow::anyhow!("cannot serialize an empty color theme"))?;
+    let (h, s, _) = background.to_hsl();
+    // Keep the grays of the scheme only slightly tinted by the bar color.
+    let s = s.min(0.25);
+    let grays = gamut::blends(
+        &RGB::from_hsl(h, s, 0.1),
+        &RGB::from_hsl(h, s, 0.9),
+        8,
+        Interpolation::Lab,
+    );
+    let steps = gamut::hue_steps(background, 8);
+    let accents = (0..8).map(|i| colors.get(i + 1).unwrap_or(&steps[i]).clone());
+    let name = args.scheme_name.clone().unwrap_or_else(|| {
+        args.image
+            .as_ref()
+            .and_then(|p| p.file_stem())
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or(APP_NAME.to_string())
+    });
+    let mut scheme = serde_yml::Mapping::new();
+    scheme.insert("scheme".into(), name.into());
+    scheme.insert("author".into(), APP_NAME.into());
+    for (i, color) in grays.into_iter().chain(accents).enumerate() {
+        scheme.insert(
+            format!("base{i:02X}").into(),
+            color.to_string().trim_start_matches('#').into(),
+        );
+    }
+    Ok(scheme)
+}
+
+/// Write a serialized struct as TOML, nesting structs as tables named by their dotted path.
+///
+/// # Notes
+/// Only tables and string values are written since those are the only types the output formats use.
+fn toml(value: &serde_yml::Value) -> String {
+    fn table(path: &str, value: &serde_yml::Value, tables: &mut Vec<String>) {
+        let Some(mapping) = value.as_mapping() else {
+            return;
+        };
+        let keys = mapping
+            .iter()
+            .filter_map(|(k, v)| Some((k.as_str()?, v.as_str()?)))
+            .map(|(k, v)| format!("{k} = {v:?}\n"))
+            .collect::<String>();
+        if !keys.is_empty() {
+            tables.push(format!("[{path}]\n{keys}"));
+        }
+        for (key, value) in mapping.iter().filter(|(_, v)| v.is_mapping()) {
+            let key = key.as_str().unwrap_or_default();
+            let path = match path.is_empty() {
+                true => key.to_string(),
+                false => format!("{path}.{key}"),
+            };
+            table(&path, value, tables);
+        }
+    }
+    let mut tables = vec![];
+    table("", value, &mut tables);
+    tables.join("\n").trim_end().to_string()
+}
+
+/// Join every color in the color theme with a comma, optionally without the leading '#' of every color.
+fn text(colors: &[RGBA], no_hash: bool) -> String {
+    colors
+        .iter()
+        .map(|c| match no_hash {
+            true => c.to_string()[1..].to_string(),
+            false => c.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
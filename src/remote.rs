@@ -0,0 +1,285 @@
+#![deny(unused_extern_crates)]
+#![warn(missing_docs)]
+use crate::common::{fnv1a, ColorSchemeError, APP_NAME};
+use openssl::ssl::{SslConnector, SslMethod, SslOptions};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Time a download waits to connect to the server and for every read before erroring.
+pub const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Most redirects followed by a download.
+pub const MAX_REDIRECTS: u8 = 5;
+
+/// Largest image in bytes that is downloaded, larger responses are an error instead of being read into memory.
+pub const MAX_DOWNLOAD_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Largest headers in bytes read from a response on top of [`MAX_DOWNLOAD_SIZE`].
+const MAX_HEADER_SIZE: u64 = 64 * 1024;
+
+/// Get the URL of an image argument if it is an `http://` or `https://` URL instead of a path.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::remote::url;
+/// assert_eq!(url("http://example.com/wall.png".as_ref()), Some("http://example.com/wall.png"));
+/// assert_eq!(url("https://example.com/wall.png".as_ref()), Some("https://example.com/wall.png"));
+/// assert_eq!(url("wall.png".as_ref()), None);
+/// ```
+pub fn url(image: &Path) -> Option<&str> {
+    image
+        .to_str()
+        .filter(|i| i.starts_with("http://") || i.starts_with("https://"))
+}
+
+/// Get the directory inside of XDG_CACHE_HOME that images are downloaded to, creating it if needed.
+pub fn download_dir() -> Result<PathBuf, ColorSchemeError> {
+    Ok(xdg::BaseDirectories::with_prefix(APP_NAME)?.create_cache_directory("downloads")?)
+}
+
+/// Download the image at a URL into `dir` and get its path.
+///
+/// # Notes
+/// The file is named by the hash of the URL, so downloading the same URL again replaces the same file.
+/// The file is only rewritten if the downloaded contents changed, so the color theme cached for it stays valid.
+///
+/// `http://` and `https://` URLs can be downloaded. The certificate of an `https://` server is verified against
+/// the certificates trusted by OpenSSL, which can be changed with the `SSL_CERT_FILE` and `SSL_CERT_DIR` environment variables.
+/// Redirects are followed up to [`MAX_REDIRECTS`] times.
+///
+/// Images larger than [`MAX_DOWNLOAD_SIZE`] are not downloaded. If the response has a `Content-Length`,
+/// a larger length is refused and a body of another length is an error, so a truncated image is never stored.
+///
+/// # Errors
+/// Will error with [`ColorSchemeError::Download`] if the URL is not an `http://` or `https://` URL, the server cannot be reached
+/// or its certificate is not trusted, it does not respond with the image or the image is too large, and with [`ColorSchemeError::Io`] if the file cannot be written.
+///
+/// # Examples
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, ColorSchemeError, RGB};
+/// # use color_scheme_generator::remote::download;
+/// # use color_scheme_generator::theme_calculation::generate_color_theme;
+/// # use std::io::{Read, Write};
+/// let mut png = vec![];
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([200, 30, 30]))
+///     .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+///     .unwrap();
+/// let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// let url = format!("http://{}/wallpaper.png", listener.local_addr().unwrap());
+/// std::thread::spawn(move || {
+///     let (mut stream, _) = listener.accept().unwrap();
+///     let mut request = [0; 1024];
+///     let read = stream.read(&mut request).unwrap();
+///     assert!(request[..read].starts_with(b"GET /wallpaper.png HTTP/1.0\r\n"));
+///     write!(stream, "HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n", png.len()).unwrap();
+///     stream.write_all(&png).unwrap();
+/// });
+/// let dir = std::env::temp_dir().join("color_scheme_generator_download");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// let path = download(&url, &dir).unwrap();
+/// assert_eq!(path.extension().unwrap(), "png");
+/// let cli = Cli::parse_from(["color_scheme_generator", path.to_str().unwrap(), "--triadic"]);
+/// assert_eq!(generate_color_theme(&cli).unwrap()[0], RGB { red: 200, green: 30, blue: 30 });
+///
+/// let error = download("ftp://example.com/wallpaper.png", &dir).unwrap_err();
+/// assert!(matches!(error, ColorSchemeError::Download { .. }));
+/// assert!(matches!(download(&url, &dir), Err(ColorSchemeError::Download { .. })));
+/// ```
+/// An `https://` URL is only downloaded from a server with a trusted certificate.
+/// ```
+/// # use color_scheme_generator::common::ColorSchemeError;
+/// # use color_scheme_generator::remote::download;
+/// # use openssl::{asn1::Asn1Time, hash::MessageDigest, pkey::PKey, rsa::Rsa, x509::{X509, X509NameBuilder}};
+/// # use openssl::ssl::{SslAcceptor, SslMethod};
+/// # use openssl::x509::extension::SubjectAlternativeName;
+/// # use std::io::{Read, Write};
+/// let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+/// let mut name = X509NameBuilder::new().unwrap();
+/// name.append_entry_by_text("CN", "localhost").unwrap();
+/// let name = name.build();
+/// let mut certificate = X509::builder().unwrap();
+/// certificate.set_version(2).unwrap();
+/// certificate.set_subject_name(&name).unwrap();
+/// certificate.set_issuer_name(&name).unwrap();
+/// certificate.set_pubkey(&key).unwrap();
+/// certificate.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+/// certificate.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+/// let localhost = SubjectAlternativeName::new().dns("localhost").build(&certificate.x509v3_context(None, None)).unwrap();
+/// certificate.append_extension(localhost).unwrap();
+/// certificate.sign(&key, MessageDigest::sha256()).unwrap();
+/// let certificate = certificate.build();
+///
+/// let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+/// acceptor.set_private_key(&key).unwrap();
+/// acceptor.set_certificate(&certificate).unwrap();
+/// let acceptor = acceptor.build();
+/// let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// let url = format!("https://localhost:{}/wallpaper.png", listener.local_addr().unwrap().port());
+/// std::thread::spawn(move || {
+///     for stream in listener.incoming() {
+///         let Ok(mut stream) = acceptor.accept(stream.unwrap()) else { continue };
+///         stream.read(&mut [0; 1024]).unwrap();
+///         stream.write_all(b"HTTP/1.0 200 OK\r\nContent-Length: 3\r\n\r\nPNG").unwrap();
+///     }
+/// });
+/// let dir = std::env::temp_dir().join("color_scheme_generator_download_https");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// assert!(matches!(download(&url, &dir), Err(ColorSchemeError::Download { .. })));
+///
+/// let trusted = dir.join("trusted.pem");
+/// std::fs::write(&trusted, certificate.to_pem().unwrap()).unwrap();
+/// std::env::set_var("SSL_CERT_FILE", &trusted);
+/// assert_eq!(std::fs::read(download(&url, &dir).unwrap()).unwrap(), b"PNG");
+/// ```
+/// A body that is shorter than its `Content-Length`, or a `Content-Length` above [`MAX_DOWNLOAD_SIZE`], is an error.
+/// ```
+/// # use color_scheme_generator::common::ColorSchemeError;
+/// # use color_scheme_generator::remote::{download, MAX_DOWNLOAD_SIZE};
+/// # use std::io::{Read, Write};
+/// let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// let url = format!("http://{}/wallpaper.png", listener.local_addr().unwrap());
+/// std::thread::spawn(move || {
+///     for length in [100, MAX_DOWNLOAD_SIZE + 1] {
+///         let (mut stream, _) = listener.accept().unwrap();
+///         stream.read(&mut [0; 1024]).unwrap();
+///         write!(stream, "HTTP/1.0 200 OK\r\nContent-Length: {length}\r\n\r\nPNG").unwrap();
+///     }
+/// });
+/// let dir = std::env::temp_dir().join("color_scheme_generator_download_length");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// for reason in ["3 of the 100 bytes", "larger than"] {
+///     match download(&url, &dir) {
+///         Err(ColorSchemeError::Download { reason: r, .. }) => assert!(r.contains(reason), "{r}"),
+///         other => panic!("{other:?}"),
+///     }
+/// }
+/// ```
+pub fn download(url: &str, dir: &Path) -> Result<PathBuf, ColorSchemeError> {
+    let bytes = get(url, MAX_REDIRECTS).map_err(|reason| ColorSchemeError::Download {
+        url: url.to_string(),
+        reason,
+    })?;
+    let extension = url
+        .rsplit('/')
+        .next()
+        .and_then(|name| name.split(['?', '#']).next())
+        .and_then(|name| Path::new(name).extension())
+        .and_then(|e| e.to_str())
+        .map(|e| format!(".{}", e.to_lowercase()))
+        .unwrap_or_default();
+    let path = dir.join(format!("{}{extension}", fnv1a(url.as_bytes())));
+    if std::fs::read(&path).ok().as_ref() != Some(&bytes) {
+        let partial = path.with_extension("part");
+        std::fs::write(&partial, &bytes)?;
+        std::fs::rename(&partial, &path)?;
+    }
+    Ok(path)
+}
+
+/// Get the body of an `http://` or `https://` URL with HTTP/1.0, following redirects.
+fn get(url: &str, redirects: u8) -> Result<Vec<u8>, String> {
+    let (scheme, rest, port) = match (url.strip_prefix("http://"), url.strip_prefix("https://")) {
+        (Some(rest), _) => ("http", rest, 80),
+        (_, Some(rest)) => ("https", rest, 443),
+        _ => return Err("only http:// and https:// URLs can be downloaded".to_string()),
+    };
+    let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let path = if path.is_empty() { "/" } else { path };
+    let host = authority
+        .rsplit_once(':')
+        .filter(|(_, port)| port.parse::<u16>().is_ok())
+        .map_or(authority, |(host, _)| host);
+    let address = match host == authority {
+        true => format!("{authority}:{port}"),
+        false => authority.to_string(),
+    };
+    let io = |e: std::io::Error| e.to_string();
+    // Try every address, since a name such as localhost can resolve to an IPv6 address the server does not listen on.
+    let mut error = format!("{authority} has no address");
+    let mut connected = None;
+    for address in std::net::ToSocketAddrs::to_socket_addrs(&address).map_err(io)? {
+        match TcpStream::connect_timeout(&address, TIMEOUT) {
+            Ok(stream) => {
+                connected = Some(stream);
+                break;
+            }
+            Err(e) => error = e.to_string(),
+        }
+    }
+    let stream = connected.ok_or(error)?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(io)?;
+    // Send the request in one write so servers reading a single segment get all of it.
+    let request = format!(
+        "GET {path} HTTP/1.0\r\nHost: {authority}\r\nUser-Agent: {APP_NAME}\r\nConnection: close\r\n\r\n"
+    );
+    let mut response = match scheme {
+        "https" => {
+            let tls = |e: openssl::error::ErrorStack| e.to_string();
+            let mut connector = SslConnector::builder(SslMethod::tls()).map_err(tls)?;
+            // Servers often close HTTP/1.0 connections without a TLS close notify. A truncated image is still
+            // detected by its Content-Length.
+            connector.set_options(SslOptions::IGNORE_UNEXPECTED_EOF);
+            let connector = connector.build();
+            let stream = connector
+                .connect(host.trim_start_matches('[').trim_end_matches(']'), stream)
+                .map_err(|e| e.to_string())?;
+            exchange(stream, &request).map_err(io)?
+        }
+        _ => exchange(stream, &request).map_err(io)?,
+    };
+    let header_end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or("the response has no end of headers")?;
+    let headers = String::from_utf8_lossy(&response[..header_end]).to_string();
+    let header = |name: &str| {
+        headers.lines().skip(1).find_map(|l| {
+            l.split_once(':')
+                .filter(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.trim().to_string())
+        })
+    };
+    let status = headers.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1).unwrap_or_default() {
+        "200" => {
+            let body = response.split_off(header_end + 4);
+            let too_large = format!("the image is larger than {MAX_DOWNLOAD_SIZE} bytes");
+            let length = header("content-length")
+                .map(|l| {
+                    l.parse::<u64>()
+                        .map_err(|_| format!("invalid Content-Length {l:?}"))
+                })
+                .transpose()?;
+            match length {
+                Some(length) if length > MAX_DOWNLOAD_SIZE => Err(too_large),
+                Some(length) if length != body.len() as u64 => Err(format!(
+                    "received {} of the {length} bytes of the image",
+                    body.len()
+                )),
+                _ if body.len() as u64 > MAX_DOWNLOAD_SIZE => Err(too_large),
+                _ => Ok(body),
+            }
+        }
+        "301" | "302" | "303" | "307" | "308" if redirects > 0 => {
+            let location = header("location").ok_or("the redirect has no location")?;
+            match location.starts_with('/') {
+                true => get(&format!("{scheme}://{authority}{location}"), redirects - 1),
+                false => get(&location, redirects - 1),
+            }
+        }
+        _ => Err(format!("the server responded with {status:?}")),
+    }
+}
+
+/// Send a request over a stream and read the response, up to [`MAX_DOWNLOAD_SIZE`] and [`MAX_HEADER_SIZE`] bytes.
+fn exchange<S: Read + Write>(mut stream: S, request: &str) -> std::io::Result<Vec<u8>> {
+    stream.write_all(request.as_bytes())?;
+    let mut response = vec![];
+    stream
+        .take(MAX_DOWNLOAD_SIZE + MAX_HEADER_SIZE + 1)
+        .read_to_end(&mut response)?;
+    Ok(response)
+}
@@ -1,218 +1,1722 @@
 #![deny(unused_extern_crates)]
 #![warn(missing_docs)]
-use crate::common::{Centrality, Cli, ColorThemeOption, GAMUT_CLI_NAME, RGB};
-use anyhow::Ok;
+use crate::common::{
+    detect_image_format, linear, Centrality, Cli, ColorCount, ColorSchemeError, ColorThemeOption,
+    Coverage, Crop, Frame, Histogram, Region, RGB,
+};
+use log::{debug, trace, warn};
 use rayon::prelude::*;
-use serde::{Deserialize, Serialize};
-use std::{process::Command, str::FromStr};
-use which::which;
+use std::time::{Duration, Instant};
+
+/// Module to adjust generated color themes before they are output.
+pub mod adjust;
+/// Module to simulate and adjust color themes for color vision deficiencies.
+pub mod cvd;
+/// Module to compute color schemes natively from the colors selected by the centrality.
+pub mod gamut;
+/// Module to analyze images in chunks of rows without collecting their pixels.
+pub mod streaming;
+/// Module to render color themes as images.
+pub mod swatch;
+
+/// Amount of pixels analyzed between two checks of a [`Deadline`].
+const DEADLINE_CHUNK: usize = 1 << 14;
+
+/// Point in time after which the analysis of an image gives up with [`ColorSchemeError::Timeout`], see [`Cli::timeout`].
+///
+/// # Notes
+/// The deadline is checked after the image is decoded and inside of the pixel loops of every centrality, of [`Cli::streaming`]
+/// and of [`Cli::accent_only`], every [`DEADLINE_CHUNK`] pixels, so an analysis that runs out of time stops instead of running to completion.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::ColorSchemeError;
+/// # use color_scheme_generator::theme_calculation::Deadline;
+/// # use std::time::Duration;
+/// assert!(Deadline::NEVER.check().is_ok());
+/// assert!(Deadline::after(None).check().is_ok());
+/// assert!(Deadline::after(Some(Duration::from_secs(60))).check().is_ok());
+/// let deadline = Deadline::after(Some(Duration::ZERO));
+/// assert!(matches!(deadline.check(), Err(ColorSchemeError::Timeout(_))));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    at: Option<Instant>,
+    timeout: Duration,
+}
+
+impl Deadline {
+    /// A deadline that never passes.
+    pub const NEVER: Deadline = Deadline {
+        at: None,
+        timeout: Duration::ZERO,
+    };
+
+    /// Get the deadline that passes once the timeout elapses from now, or [`Deadline::NEVER`] without a timeout.
+    pub fn after(timeout: Option<Duration>) -> Deadline {
+        match timeout {
+            Some(timeout) => Deadline {
+                at: Instant::now().checked_add(timeout),
+                timeout,
+            },
+            None => Deadline::NEVER,
+        }
+    }
+
+    /// Error with [`ColorSchemeError::Timeout`] if the deadline has passed.
+    ///
+    /// # Errors
+    /// Will error if the deadline has passed.
+    pub fn check(&self) -> Result<(), ColorSchemeError> {
+        match self.at {
+            Some(at) if Instant::now() >= at => Err(ColorSchemeError::Timeout(self.timeout)),
+            _ => Ok(()),
+        }
+    }
+}
 
 /// Get a [`Vec<ColorThemeOption>`] for an image based on the centrality and number of themes.
 ///
 /// # Notes
-/// The number_of_themes is ignored and set to 1 if the centrality is either [`Centrality::Average`] or [`Centrality::Median`].
+/// The [`ColorThemeOption::palette_size`](crate::common::ColorThemeOption::palette_size) is ignored and set to 1 if the centrality is [`Centrality::Average`], [`Centrality::VividAverage`], [`Centrality::LabAverage`] or [`Centrality::Median`].
 /// This is due to the fact that for either of these centrality metrics, they give a single result and more results cannot be derived from them.
-/// This is not the case for [`Centrality::Prevalent`] since a list of pixels can be generated using this method.
+/// This is not the case for [`Centrality::Prevalent`], [`Centrality::Mode`], [`Centrality::Kmeans`] and [`Centrality::MedianCut`] since a list of pixels can be generated using these methods.
+///
+/// If [`Cli::seed_color`] is given, the image is not analyzed and the color theme is derived from the seed color instead.
+///
+/// If [`Cli::accent_only`] is given the color theme is only the color chosen by [`accent_pixel`].
+///
+/// If [`Cli::streaming`] is given and the centrality can be streamed, see [`streaming::streaming_centrality`], the pixels are analyzed in chunks of rows.
+///
+/// If [`Cli::deterministic`] is given the image is analyzed on a single thread, so the same image always gives the same color theme.
+///
+/// If [`Cli::timeout`] is given the analysis stops with [`ColorSchemeError::Timeout`] once it runs out of time, see [`Deadline`].
 ///
 /// # Errors
 ///
-/// If the path to the image is invalid (i.e does not exist or is not a valid image) this method will return an error. It is expected that the image
+/// If the path to the image is missing or invalid (i.e does not exist or is not a valid image) this method will return an error. It is expected that the image
 /// path is correct before using this method.
+/// An image that decodes to zero pixels, or whose pixels are all more transparent than [`Cli::alpha_threshold`], will also return an error.
 ///
 /// # Examples
 /// ```
-/// # use std::path::PathBuf;
-/// # use color_scheme_generator::common::{Centrality, Cli, ColorThemeOption, OutputFormat};
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, ColorSchemeError, RGB};
 /// # use color_scheme_generator::theme_calculation::generate_color_theme;
-/// # let color_themes = ColorThemeOption {
-/// #   darker: 0,
-/// #   lighter: 0,
-/// #   complementary: false,
-/// #   contrast: false,
-/// #   hue_offset: 0,
-/// #   triadic: false,
-/// #   quadratic: true,
-/// #   tetratic: false,
-/// #   analogous: false,
-/// #   split_complementary: false,
-/// #   monochromatic: 0,
-/// #   shades: 0,
-/// #   tints: 0,
-/// #   tones: 0,
-/// #   blends: 0,
-/// # };
-/// # let cli = Cli {
-/// #   image : "text".parse::<PathBuf>().unwrap(),
-/// #   centrality: Centrality::Prevalent,
-/// #   serialization_format: OutputFormat::JSON,
-/// #   color_themes : color_themes,
-/// #   log_level: 0,
-/// # };
-/// generate_color_theme(&cli);
-/// ```
-pub fn generate_color_theme(args: &Cli) -> anyhow::Result<Vec<RGB>> {
-    let pixels = image::ImageReader::open(&args.image)?
-        .decode()?
-        .to_rgb8()
+/// let cli = Cli::parse_from(["color_scheme_generator", "text", "--centrality", "prevalent"]);
+/// assert!(matches!(generate_color_theme(&cli), Err(ColorSchemeError::Io(_))));
+///
+/// let notes = std::env::temp_dir().join("color_scheme_generator_notes.txt");
+/// std::fs::write(&notes, "not an image").unwrap();
+/// let cli = Cli::parse_from(["color_scheme_generator", notes.to_str().unwrap()]);
+/// assert!(matches!(generate_color_theme(&cli), Err(ColorSchemeError::NotAnImage(_))));
+///
+/// let cli = Cli::parse_from(["color_scheme_generator", "--seed-color", "#ff0000", "--triadic"]);
+/// assert_eq!(
+///     generate_color_theme(&cli).unwrap(),
+///     vec![
+///         RGB { red: 255, green: 0, blue: 0 },
+///         RGB { red: 0, green: 255, blue: 0 },
+///         RGB { red: 0, green: 0, blue: 255 },
+///     ]
+/// );
+///
+/// let path = std::env::temp_dir().join("color_scheme_generator_half_transparent.png");
+/// image::RgbaImage::from_fn(4, 4, |x, _| match x < 2 {
+///     true => image::Rgba([200, 30, 30, 255]),
+///     false => image::Rgba([0, 0, 0, 0]),
+/// })
+/// .save(&path)
+/// .unwrap();
+/// let path = path.to_str().unwrap();
+/// let cli = Cli::parse_from(["color_scheme_generator", path, "-c", "average", "--complementary", "--include-seed"]);
+/// assert_eq!(generate_color_theme(&cli).unwrap()[0], RGB { red: 200, green: 30, blue: 30 });
+/// ```
+/// The time taken by every step of the analysis is logged at the debug level.
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::Cli;
+/// # use color_scheme_generator::theme_calculation::generate_color_theme;
+/// # use std::sync::Mutex;
+/// struct Logger(Mutex<Vec<String>>);
+/// impl log::Log for Logger {
+///     fn enabled(&self, _: &log::Metadata) -> bool { true }
+///     fn log(&self, record: &log::Record) { self.0.lock().unwrap().push(record.args().to_string()) }
+///     fn flush(&self) {}
+/// }
+/// static LOGGER: Logger = Logger(Mutex::new(vec![]));
+/// log::set_logger(&LOGGER).unwrap();
+/// log::set_max_level(log::LevelFilter::Debug);
+/// let path = std::env::temp_dir().join("color_scheme_generator_timings.png");
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([200, 30, 30])).save(&path).unwrap();
+/// let cli = Cli::parse_from(["color_scheme_generator", path.to_str().unwrap(), "--triadic"]);
+/// generate_color_theme(&cli).unwrap();
+/// let logs = LOGGER.0.lock().unwrap();
+/// assert!(logs.iter().any(|l| l.starts_with("Image decode took")));
+/// assert!(logs.iter().any(|l| l.starts_with("Centrality median took")));
+/// ```
+/// An image analyzed twice with [`Cli::deterministic`] gives identical output.
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::Cli;
+/// # use color_scheme_generator::theme_calculation::generate_color_theme;
+/// let path = std::env::temp_dir().join("color_scheme_generator_deterministic.png");
+/// image::RgbImage::from_fn(64, 64, |x, y| image::Rgb([(x * 4) as u8, (y * 4) as u8, ((x * y) % 256) as u8]))
+///     .save(&path)
+///     .unwrap();
+/// for centrality in ["average", "lab-average", "kmeans", "median-cut"] {
+///     let cli = Cli::parse_from([
+///         "color_scheme_generator", path.to_str().unwrap(), "-c", centrality, "--deterministic", "--tetratic",
+///     ]);
+///     let first = serde_json::to_string(&generate_color_theme(&cli).unwrap()).unwrap();
+///     let second = serde_json::to_string(&generate_color_theme(&cli).unwrap()).unwrap();
+///     assert_eq!(first, second);
+/// }
+/// ```
+pub fn generate_color_theme(args: &Cli) -> Result<Vec<RGB>, ColorSchemeError> {
+    Ok(generate_color_theme_with_coverage(args)?.0)
+}
+
+/// Get the color theme of [`Cli::image`] like [`generate_color_theme`], along with the [`Coverage`] of the colors selected by the centrality.
+///
+/// # Notes
+/// The coverage is counted by the centrality while it selects the colors, so the image is analyzed once.
+/// It is None if the color theme is derived from [`Cli::seed_color`], for [`Cli::accent_only`] and for the centralities that do not count pixels,
+/// which are [`Centrality::Average`], [`Centrality::VividAverage`], [`Centrality::LabAverage`] and [`Centrality::Median`].
+///
+/// # Errors
+/// Will error like [`generate_color_theme`].
+///
+/// # Examples
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::theme_calculation::generate_color_theme_with_coverage;
+/// let path = std::env::temp_dir().join("color_scheme_generator_coverage.png");
+/// image::RgbImage::from_fn(4, 4, |x, _| match x < 3 {
+///     true => image::Rgb([200, 30, 30]),
+///     false => image::Rgb([30, 30, 200]),
+/// })
+/// .save(&path)
+/// .unwrap();
+/// let (red, blue) = (RGB { red: 200, green: 30, blue: 30 }, RGB { red: 30, green: 30, blue: 200 });
+/// for centrality in ["prevalent", "kmeans"] {
+///     let cli = Cli::parse_from(["color_scheme_generator", path.to_str().unwrap(), "-c", centrality, "--sample-size", "0", "--tetratic", "--include-seed"]);
+///     let (colors, coverage) = generate_color_theme_with_coverage(&cli).unwrap();
+///     let fractions = coverage.unwrap().fractions(&colors);
+///     assert_eq!(fractions[colors.iter().position(|c| *c == red).unwrap()], Some(0.75), "{centrality}");
+///     assert_eq!(fractions[colors.iter().position(|c| *c == blue).unwrap()], Some(0.25), "{centrality}");
+///     // The complementary colors of the scheme are not in the image.
+///     assert_eq!(fractions.iter().filter(|f| f.is_none()).count(), colors.len() - 2);
+/// }
+/// let cli = Cli::parse_from(["color_scheme_generator", path.to_str().unwrap(), "-c", "median"]);
+/// assert!(generate_color_theme_with_coverage(&cli).unwrap().1.is_none());
+/// ```
+pub fn generate_color_theme_with_coverage(
+    args: &Cli,
+) -> Result<(Vec<RGB>, Option<Coverage>), ColorSchemeError> {
+    match args.deterministic {
+        true => rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()?
+            .install(|| color_theme_of_image(args)),
+        false => color_theme_of_image(args),
+    }
+}
+
+/// Get the color theme of [`Cli::image`] and its coverage on the current thread pool, see [`generate_color_theme_with_coverage`].
+fn color_theme_of_image(args: &Cli) -> Result<(Vec<RGB>, Option<Coverage>), ColorSchemeError> {
+    if let Some(seed) = &args.seed_color {
+        return Ok(match args.accent_only {
+            true => (vec![seed.clone()], None),
+            false => (gamut::color_theme(&args.color_themes, seed, None), None),
+        });
+    }
+    let deadline = Deadline::after(args.timeout);
+    let image = analyzed_image(args)?;
+    deadline.check()?;
+    if args.accent_only {
+        let accent = accent_pixel_until(&image_pixels(&image, args)?, &deadline)?;
+        return Ok((vec![accent], None));
+    }
+    if args.streaming {
+        let start = Instant::now();
+        match streaming::streaming_selection(&image, args, &deadline)? {
+            Some((bar_color, coverage)) => {
+                debug!(
+                    "Streaming centrality {} took {:?}.",
+                    args.centrality,
+                    start.elapsed()
+                );
+                return Ok((color_theme_from_centrality(bar_color, args), coverage));
+            }
+            None => warn!(
+                "Centrality {} cannot be streamed. Analyzing every pixel instead.",
+                args.centrality
+            ),
+        }
+    }
+    color_theme_from_pixels(&image_pixels(&image, args)?, args, &deadline)
+}
+
+/// Get the pixels of [`Cli::image`] that are analyzed, after it is downsampled, cropped to [`Cli::region`],
+/// its pixels below [`Cli::alpha_threshold`] are removed and only every [`Cli::sample_rate`]th pixel is kept.
+///
+/// # Notes
+/// The sample rate is applied after the image is downsampled to [`Cli::sample_size`], so both reduce the amount of analyzed pixels.
+/// Unlike downsampling, sampling does not blend neighbouring pixels, so the sampled pixels keep the exact colors of the image.
+///
+/// # Errors
+/// Will error if no image is given, it cannot be decoded or it has no opaque pixels.
+///
+/// # Examples
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::Cli;
+/// # use color_scheme_generator::theme_calculation::{analyzed_pixels, generate_color_theme};
+/// let path = std::env::temp_dir().join("color_scheme_generator_sample_rate.png");
+/// let noise = |x: u32, y: u32, c: u32| {
+///     let h = x.wrapping_mul(73856093) ^ y.wrapping_mul(19349663) ^ c.wrapping_mul(83492791);
+///     let h = (h ^ (h >> 13)).wrapping_mul(0x5bd1e995);
+///     ((h ^ (h >> 15)) >> 24) as u8
+/// };
+/// image::RgbImage::from_fn(256, 256, |x, y| image::Rgb([noise(x, y, 1), noise(x, y, 2), noise(x, y, 3)]))
+///     .save(&path)
+///     .unwrap();
+/// let cli = |rate: &str| Cli::parse_from(["color_scheme_generator", path.to_str().unwrap(), "-c", "average", "--complementary", "--sample-rate", rate]);
+/// assert_eq!(analyzed_pixels(&cli("1")).unwrap().len(), 256 * 256);
+/// assert_eq!(analyzed_pixels(&cli("7")).unwrap().len(), (256 * 256 + 6) / 7);
+/// let (full, sampled) = (generate_color_theme(&cli("1")).unwrap(), generate_color_theme(&cli("7")).unwrap());
+/// assert!(full[0].red.abs_diff(sampled[0].red) <= 3);
+/// assert!(full[0].green.abs_diff(sampled[0].green) <= 3);
+/// assert!(full[0].blue.abs_diff(sampled[0].blue) <= 3);
+/// ```
+pub fn analyzed_pixels(args: &Cli) -> Result<Vec<image::Rgb<u8>>, ColorSchemeError> {
+    image_pixels(&analyzed_image(args)?, args)
+}
+
+/// Decode [`Cli::image`], crop it to [`Cli::crop`], downsample it and crop it to [`Cli::region`].
+fn analyzed_image(args: &Cli) -> Result<image::DynamicImage, ColorSchemeError> {
+    let image = args.image.as_ref().ok_or(ColorSchemeError::MissingImage)?;
+    let start = Instant::now();
+    let image = match args.frame {
+        Frame::First => decode_image(image, !args.ignore_orientation)?,
+        frame => match decode_animation(image, frame)? {
+            Some(image) => image,
+            None => decode_image(image, !args.ignore_orientation)?,
+        },
+    };
+    debug!("Image decode took {:?}.", start.elapsed());
+    let start = Instant::now();
+    let image = crop(image, args.crop)?;
+    let image = crop_region(downsample(image, args.sample_size), args.region);
+    debug!(
+        "Downsampling to {}x{} pixels took {:?}.",
+        image.width(),
+        image.height(),
+        start.elapsed()
+    );
+    Ok(image)
+}
+
+/// Get every [`Cli::sample_rate`]th opaque pixel of an image, erroring if a non empty image has none.
+fn image_pixels(
+    image: &image::DynamicImage,
+    args: &Cli,
+) -> Result<Vec<image::Rgb<u8>>, ColorSchemeError> {
+    let pixels = sampled_opaque_pixels(image, args.alpha_threshold, args.sample_rate as usize);
+    if pixels.is_empty() && image.width() > 0 && image.height() > 0 {
+        return Err(ColorSchemeError::NoOpaquePixels);
+    }
+    Ok(pixels)
+}
+
+/// Decode the image at a path, rotating and flipping it as given by its EXIF orientation if apply_orientation is true.
+///
+/// # Notes
+/// Applying the orientation makes the pixels match the image as it is displayed by image viewers,
+/// so region based analysis and swatches agree with what the user sees.
+///
+/// CMYK JPEGs are converted to RGB as they are stored by Adobe applications, with inverted sub pixels.
+///
+/// # Errors
+/// Will error if the file cannot be read or is not a valid image,
+/// or with [`ColorSchemeError::UnsupportedColorSpace`] if the pixels are in a color space that cannot be converted to RGB.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::theme_calculation::decode_image;
+/// let image = image::RgbImage::from_fn(16, 8, |x, _| match x < 8 {
+///     true => image::Rgb([255, 0, 0]),
+///     false => image::Rgb([0, 0, 255]),
+/// });
+/// let mut jpeg = vec![];
+/// image.write_to(&mut std::io::Cursor::new(&mut jpeg), image::ImageFormat::Jpeg).unwrap();
+/// // EXIF segment with an orientation of 6, rotate 90 degrees clockwise.
+/// let exif = b"\xff\xe1\x00\x22Exif\x00\x00MM\x00\x2a\x00\x00\x00\x08\x00\x01\x01\x12\x00\x03\x00\x00\x00\x01\x00\x06\x00\x00\x00\x00\x00\x00";
+/// jpeg.splice(2..2, exif.iter().copied());
+/// let path = std::env::temp_dir().join("color_scheme_generator_rotated.jpg");
+/// std::fs::write(&path, jpeg).unwrap();
+///
+/// let rotated = decode_image(&path, true).unwrap().to_rgb8();
+/// assert_eq!(rotated.dimensions(), (8, 16));
+/// let top = rotated.get_pixel(4, 2);
+/// assert!(top.0[0] > 200 && top.0[2] < 60);
+/// let bottom = rotated.get_pixel(4, 13);
+/// assert!(bottom.0[2] > 200 && bottom.0[0] < 60);
+/// assert_eq!(decode_image(&path, false).unwrap().to_rgb8().dimensions(), (16, 8));
+/// ```
+/// ```
+/// # use color_scheme_generator::theme_calculation::decode_image;
+/// // Baseline 8x8 JPEG with four components, a single red CMYK block stored inverted.
+/// let mut jpeg = vec![0xff, 0xd8];
+/// // Quantization table of ones.
+/// jpeg.extend([0xff, 0xdb, 0x00, 0x43, 0x00]);
+/// jpeg.extend([1; 64]);
+/// // Frame with the C, M, Y and K components.
+/// jpeg.extend([0xff, 0xc0, 0x00, 0x14, 0x08, 0x00, 0x08, 0x00, 0x08, 0x04]);
+/// jpeg.extend([1, 0x11, 0, 2, 0x11, 0, 3, 0x11, 0, 4, 0x11, 0]);
+/// // DC coefficients of category 0, 10 and 11, and an AC table with only the end of block.
+/// jpeg.extend([0xff, 0xc4, 0x00, 0x16, 0x00, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x00, 0x0a, 0x0b]);
+/// jpeg.extend([0xff, 0xc4, 0x00, 0x14, 0x10, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x00]);
+/// jpeg.extend([0xff, 0xda, 0x00, 0x0e, 0x04, 1, 0, 2, 0, 3, 0, 4, 0, 0x00, 0x3f, 0x00]);
+/// // C and K are 255, M and Y are 0.
+/// jpeg.extend([0x7f, 0x84, 0xff, 0x00, 0xd3, 0xff, 0x00, 0x3f, 0xc3, 0xff, 0xd9]);
+/// let path = std::env::temp_dir().join("color_scheme_generator_cmyk.jpg");
+/// std::fs::write(&path, jpeg).unwrap();
+/// let image = decode_image(&path, true).unwrap().to_rgb8();
+/// assert_eq!(image.dimensions(), (8, 8));
+/// assert!(image.pixels().all(|p| *p == image::Rgb([255, 0, 0])));
+/// ```
+pub fn decode_image(
+    path: &std::path::Path,
+    apply_orientation: bool,
+) -> Result<image::DynamicImage, ColorSchemeError> {
+    let mut decoder = open_image(path)?.into_decoder().map_err(decode_error)?;
+    let orientation = image::ImageDecoder::orientation(&mut decoder).map_err(decode_error)?;
+    let mut image = image::DynamicImage::from_decoder(decoder).map_err(decode_error)?;
+    if apply_orientation {
+        image.apply_orientation(orientation);
+    }
+    Ok(image)
+}
+
+/// Open an image for decoding in the format given by the magic bytes at the start of the file, see [`detect_image_format`].
+///
+/// # Notes
+/// The extension of the file is only used for formats without magic bytes, such as TGA.
+///
+/// # Errors
+/// Will error if the file cannot be read, or with [`ColorSchemeError::NotAnImage`] if its format is neither detected nor given by its extension.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::ColorSchemeError;
+/// # use color_scheme_generator::theme_calculation::open_image;
+/// let path = std::env::temp_dir().join("color_scheme_generator_extensionless");
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([200, 30, 30]))
+///     .save_with_format(&path, image::ImageFormat::Png)
+///     .unwrap();
+/// assert_eq!(open_image(&path).unwrap().format(), Some(image::ImageFormat::Png));
+///
+/// std::fs::write(&path, "not an image").unwrap();
+/// assert!(matches!(open_image(&path), Err(ColorSchemeError::NotAnImage(_))));
+/// ```
+pub fn open_image(
+    path: &std::path::Path,
+) -> Result<image::ImageReader<std::io::BufReader<std::fs::File>>, ColorSchemeError> {
+    use std::io::BufRead;
+    let mut file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let format = detect_image_format(file.fill_buf()?)
+        .or_else(|| image::ImageFormat::from_path(path).ok())
+        .ok_or_else(|| {
+            ColorSchemeError::NotAnImage(format!("unknown image format of {}", path.display()))
+        })?;
+    Ok(image::ImageReader::with_format(file, format))
+}
+
+/// Decode a frame of an animated GIF, PNG or WebP image, or the average of every frame, or None if the image is not animated.
+///
+/// # Notes
+/// Every frame is composited onto the canvas of the animation, so a frame that only updates part of the image has the pixels of the frames before it.
+/// The middle frame of an animation with an even number of frames is the first frame of its second half.
+/// The frames of animations are not rotated by an EXIF orientation.
+///
+/// # Errors
+/// Will error if the file cannot be read or if its frames cannot be decoded.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::Frame;
+/// # use color_scheme_generator::theme_calculation::decode_animation;
+/// let path = std::env::temp_dir().join("color_scheme_generator_animated.gif");
+/// let frames = [[255, 0, 0, 255], [0, 0, 255, 255]]
+///     .map(|color| image::Frame::new(image::RgbaImage::from_pixel(4, 4, image::Rgba(color))));
+/// image::codecs::gif::GifEncoder::new(std::fs::File::create(&path).unwrap()).encode_frames(frames).unwrap();
+///
+/// let middle = decode_animation(&path, Frame::Middle).unwrap().unwrap().to_rgb8();
+/// assert_eq!(*middle.get_pixel(0, 0), image::Rgb([0, 0, 255]));
+/// let average = decode_animation(&path, Frame::Average).unwrap().unwrap().to_rgb8();
+/// assert_eq!(*average.get_pixel(0, 0), image::Rgb([128, 0, 128]));
+/// let first = decode_animation(&path, Frame::First).unwrap().unwrap().to_rgb8();
+/// assert_eq!(*first.get_pixel(0, 0), image::Rgb([255, 0, 0]));
+///
+/// let still = std::env::temp_dir().join("color_scheme_generator_still.png");
+/// image::RgbImage::from_pixel(4, 4, image::Rgb([0, 255, 0])).save(&still).unwrap();
+/// assert!(decode_animation(&still, Frame::Average).unwrap().is_none());
+/// ```
+pub fn decode_animation(
+    path: &std::path::Path,
+    frame: Frame,
+) -> Result<Option<image::DynamicImage>, ColorSchemeError> {
+    use image::AnimationDecoder;
+    let reader = open_image(path)?;
+    let format = reader.format();
+    let reader = reader.into_inner();
+    let frames = match format {
+        Some(image::ImageFormat::Gif) => image::codecs::gif::GifDecoder::new(reader)
+            .map_err(decode_error)?
+            .into_frames(),
+        Some(image::ImageFormat::Png) => {
+            let decoder = image::codecs::png::PngDecoder::new(reader).map_err(decode_error)?;
+            if !decoder.is_apng().map_err(decode_error)? {
+                return Ok(None);
+            }
+            decoder.apng().map_err(decode_error)?.into_frames()
+        }
+        Some(image::ImageFormat::WebP) => {
+            let decoder = image::codecs::webp::WebPDecoder::new(reader).map_err(decode_error)?;
+            if !decoder.has_animation() {
+                return Ok(None);
+            }
+            decoder.into_frames()
+        }
+        _ => return Ok(None),
+    };
+    let mut frames = frames
+        .collect_frames()
+        .map_err(decode_error)?
+        .into_iter()
+        .map(|f| f.into_buffer())
+        .collect::<Vec<_>>();
+    if frames.is_empty() {
+        return Ok(None);
+    }
+    let image = match frame {
+        Frame::First => frames.swap_remove(0),
+        Frame::Middle => frames.swap_remove(frames.len() / 2),
+        Frame::Average => {
+            let count = frames.len() as u32;
+            image::RgbaImage::from_fn(frames[0].width(), frames[0].height(), |x, y| {
+                let sums = frames.iter().fold([0u32; 4], |mut sums, f| {
+                    for (sum, sub_pixel) in sums.iter_mut().zip(f.get_pixel(x, y).0) {
+                        *sum += u32::from(sub_pixel);
+                    }
+                    sums
+                });
+                image::Rgba(sums.map(|sum| ((sum + count / 2) / count) as u8))
+            })
+        }
+    };
+    Ok(Some(image::DynamicImage::ImageRgba8(image)))
+}
+
+/// Distinguish the errors of images in color spaces that cannot be converted to RGB from other decoding errors.
+fn decode_error(error: image::ImageError) -> ColorSchemeError {
+    match &error {
+        image::ImageError::Unsupported(e) => match e.kind() {
+            image::error::UnsupportedErrorKind::Color(color) => {
+                ColorSchemeError::UnsupportedColorSpace(format!("{color:?}"))
+            }
+            _ => ColorSchemeError::ImageDecode(error),
+        },
+        // The JPEG decoder reports color spaces it cannot convert as a decoding error.
+        image::ImageError::Decoding(e) if e.to_string().contains("colorspace") => {
+            ColorSchemeError::UnsupportedColorSpace(e.to_string())
+        }
+        _ => ColorSchemeError::ImageDecode(error),
+    }
+}
+
+/// Crop an image to a region, or return it unchanged if region is None.
+///
+/// # Notes
+/// The four corners of [`Region::Corners`] are joined into one image, which does not change the result of any centrality.
+/// Images too small to have the region are returned unchanged.
+///
+/// # Examples
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, Region, RGB};
+/// # use color_scheme_generator::theme_calculation::{crop_region, generate_color_theme};
+/// let image = image::RgbImage::from_fn(8, 8, |x, y| match (2..6).contains(&x) && (2..6).contains(&y) {
+///     true => image::Rgb([200, 30, 30]),
+///     false => image::Rgb([30, 30, 200]),
+/// });
+/// let path = std::env::temp_dir().join("color_scheme_generator_region.png");
+/// image.save(&path).unwrap();
+/// let cli = |region| Cli::parse_from(["color_scheme_generator", path.to_str().unwrap(), "-c", "average", "--complementary", "--include-seed", "--region", region]);
+/// assert_eq!(generate_color_theme(&cli("center")).unwrap()[0], RGB { red: 200, green: 30, blue: 30 });
+/// assert_eq!(generate_color_theme(&cli("corners")).unwrap()[0], RGB { red: 30, green: 30, blue: 200 });
+///
+/// let image = image::DynamicImage::ImageRgb8(image);
+/// assert_eq!(crop_region(image.clone(), Some(Region::Corners)).to_rgb8().dimensions(), (4, 4));
+/// assert_eq!(crop_region(image.clone(), Some(Region::Top)).to_rgb8().dimensions(), (8, 2));
+/// assert_eq!(crop_region(image, None).to_rgb8().dimensions(), (8, 8));
+/// ```
+pub fn crop_region(image: image::DynamicImage, region: Option<Region>) -> image::DynamicImage {
+    let (width, height) = (image.width(), image.height());
+    let (quarter_width, quarter_height) = (width / 4, height / 4);
+    match region {
+        Some(Region::Center) => image.crop_imm(
+            quarter_width,
+            quarter_height,
+            width - 2 * quarter_width,
+            height - 2 * quarter_height,
+        ),
+        Some(Region::Corners) if quarter_width > 0 && quarter_height > 0 => {
+            let mut corners =
+                image::DynamicImage::new(2 * quarter_width, 2 * quarter_height, image.color());
+            for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let corner = image.crop_imm(
+                    x * (width - quarter_width),
+                    y * (height - quarter_height),
+                    quarter_width,
+                    quarter_height,
+                );
+                image::imageops::replace(
+                    &mut corners,
+                    &corner,
+                    i64::from(x * quarter_width),
+                    i64::from(y * quarter_height),
+                );
+            }
+            corners
+        }
+        Some(Region::Top) if quarter_height > 0 => image.crop_imm(0, 0, width, quarter_height),
+        Some(Region::Bottom) if quarter_height > 0 => {
+            image.crop_imm(0, height - quarter_height, width, quarter_height)
+        }
+        _ => image,
+    }
+}
+
+/// Crop an image to a rectangle, or return it unchanged if crop is None.
+///
+/// # Notes
+/// The rectangle is in pixels of the decoded image, after its EXIF orientation is applied and before it is downsampled.
+///
+/// # Errors
+/// Will error with [`ColorSchemeError::CropOutOfBounds`] if the rectangle is not entirely inside of the image.
+///
+/// # Examples
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, ColorSchemeError, Crop, RGB};
+/// # use color_scheme_generator::theme_calculation::{crop, generate_color_theme};
+/// let image = image::RgbImage::from_fn(100, 60, |x, y| match x >= 80 && y < 20 {
+///     true => image::Rgb([200, 200, 30]),
+///     false => image::Rgb([30, 30, 200]),
+/// });
+/// let path = std::env::temp_dir().join("color_scheme_generator_crop.png");
+/// image.save(&path).unwrap();
+/// let cli = |crop| Cli::parse_from(["color_scheme_generator", path.to_str().unwrap(), "-c", "prevalent", "--complementary", "--include-seed", "--crop", crop]);
+/// assert_eq!(generate_color_theme(&cli("80,0,20,20")).unwrap()[0], RGB { red: 200, green: 200, blue: 30 });
+/// assert_eq!(generate_color_theme(&cli("0,0,100,60")).unwrap()[0], RGB { red: 30, green: 30, blue: 200 });
+/// assert!(matches!(
+///     generate_color_theme(&cli("90,0,20,20")),
+///     Err(ColorSchemeError::CropOutOfBounds { width: 100, height: 60, .. })
+/// ));
+///
+/// let image = image::DynamicImage::ImageRgb8(image);
+/// let rectangle = Crop { x: 10, y: 5, width: 30, height: 20 };
+/// assert_eq!(crop(image.clone(), Some(rectangle)).unwrap().to_rgb8().dimensions(), (30, 20));
+/// assert_eq!(crop(image, None).unwrap().to_rgb8().dimensions(), (100, 60));
+/// ```
+pub fn crop(
+    image: image::DynamicImage,
+    crop: Option<Crop>,
+) -> Result<image::DynamicImage, ColorSchemeError> {
+    let Some(crop) = crop else {
+        return Ok(image);
+    };
+    let (width, height) = (image.width(), image.height());
+    let inside = |start: u32, length: u32, size: u32| {
+        start.checked_add(length).is_some_and(|end| end <= size)
+    };
+    match inside(crop.x, crop.width, width) && inside(crop.y, crop.height, height) {
+        true => Ok(image.crop_imm(crop.x, crop.y, crop.width, crop.height)),
+        false => Err(ColorSchemeError::CropOutOfBounds {
+            crop,
+            width,
+            height,
+        }),
+    }
+}
+
+/// Get the pixels of an image whose alpha is at least alpha_threshold, discarding their alpha.
+///
+/// # Notes
+/// Images without an alpha channel are treated as fully opaque.
+/// Grayscale images are expanded to equal red, green and blue sub pixels.
+/// Images with more than 8 bits per channel, such as 16-bit PNGs, are rounded to the nearest 8-bit value with a warning,
+/// since the analysis is done on 8-bit colors.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::theme_calculation::opaque_pixels;
+/// let image = image::DynamicImage::ImageRgba8(image::RgbaImage::from_fn(2, 1, |x, _| match x {
+///     0 => image::Rgba([255, 0, 0, 255]),
+///     _ => image::Rgba([0, 0, 0, 0]),
+/// }));
+/// assert_eq!(opaque_pixels(&image, 1), vec![image::Rgb([255, 0, 0])]);
+/// ```
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::{average_pixel, opaque_pixels};
+/// let gray = image::DynamicImage::ImageLuma16(image::ImageBuffer::from_pixel(4, 4, image::Luma([0x8080u16])));
+/// assert_eq!(average_pixel(&opaque_pixels(&gray, 1)), RGB { red: 128, green: 128, blue: 128 });
+/// // 200 / 257 rounds to 1, truncating the low byte would give 0.
+/// let dark = image::DynamicImage::ImageLuma16(image::ImageBuffer::from_pixel(4, 4, image::Luma([200u16])));
+/// assert_eq!(average_pixel(&opaque_pixels(&dark, 1)), RGB { red: 1, green: 1, blue: 1 });
+/// ```
+pub fn opaque_pixels(image: &image::DynamicImage, alpha_threshold: u8) -> Vec<image::Rgb<u8>> {
+    sampled_opaque_pixels(image, alpha_threshold, 1)
+}
+
+/// Get every sample_rate-th pixel out of the pixels of an image with an alpha of at least alpha_threshold, skipping the others while iterating.
+fn sampled_opaque_pixels(
+    image: &image::DynamicImage,
+    alpha_threshold: u8,
+    sample_rate: usize,
+) -> Vec<image::Rgb<u8>> {
+    let color = image.color();
+    if color.bits_per_pixel() > 8 * u16::from(color.channel_count()) {
+        warn!(
+            "Reducing image with {} bits per pixel to 8 bits per channel.",
+            color.bits_per_pixel()
+        );
+    }
+    image
+        .to_rgba8()
         .pixels()
+        .filter(|p| p.0[3] >= alpha_threshold)
+        .step_by(sample_rate.max(1))
+        .map(|p| image::Rgb([p.0[0], p.0[1], p.0[2]]))
+        .collect::<Vec<_>>()
+}
+
+/// Get the pixels whose HSL lightness in percent is between min_lightness and max_lightness inclusive.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::theme_calculation::lightness_pixels;
+/// let pixels = [image::Rgb([0, 0, 0]), image::Rgb([200, 30, 30]), image::Rgb([255, 255, 255])];
+/// assert_eq!(lightness_pixels(&pixels, 5, 95), vec![image::Rgb([200, 30, 30])]);
+/// assert_eq!(lightness_pixels(&pixels, 0, 100).len(), 3);
+/// ```
+pub fn lightness_pixels(
+    pixels: &[image::Rgb<u8>],
+    min_lightness: u8,
+    max_lightness: u8,
+) -> Vec<image::Rgb<u8>> {
+    let (min, max) = (f64::from(min_lightness), f64::from(max_lightness));
+    pixels
+        .par_iter()
+        .filter(|p| {
+            let (_, _, l) = RGB {
+                red: p.0[0],
+                green: p.0[1],
+                blue: p.0[2],
+            }
+            .to_hsl();
+            (min..=max).contains(&(l * 100.0))
+        })
         .copied()
-        .collect::<Vec<_>>();
-    let bar_color = match args.centrality {
-        Centrality::Average => vec![average_pixel(&pixels)],
-        Centrality::Median => vec![median_pixel(&pixels)],
-        Centrality::Prevalent => prevalent_pixel(&pixels, 2),
+        .collect()
+}
+
+/// Resize an image so that its longest edge is at most sample_size pixels, preserving the aspect ratio.
+///
+/// # Notes
+/// Images that already fit inside of sample_size, or a sample_size of 0, are returned unchanged.
+/// The centralities computed from the downsampled image are close to, but not exactly the same as, the ones from the full resolution image.
+///
+/// # Examples
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::Cli;
+/// # use color_scheme_generator::theme_calculation::{average_pixel, downsample, generate_color_theme_from_pixels};
+/// # use std::time::Instant;
+/// let image = image::DynamicImage::ImageRgb8(image::RgbImage::from_fn(1024, 768, |x, y| {
+///     image::Rgb([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8])
+/// }));
+/// let full = image.to_rgb8().pixels().copied().collect::<Vec<_>>();
+/// let sampled = downsample(image, 128);
+/// assert_eq!((sampled.width(), sampled.height()), (128, 96));
+/// let sampled = sampled.to_rgb8().pixels().copied().collect::<Vec<_>>();
+/// let (full_average, sampled_average) = (average_pixel(&full), average_pixel(&sampled));
+/// assert!(full_average.red.abs_diff(sampled_average.red) <= 3);
+/// assert!(full_average.green.abs_diff(sampled_average.green) <= 3);
+/// assert!(full_average.blue.abs_diff(sampled_average.blue) <= 3);
+///
+/// // Analyzing the downsampled pixels is not slower than analyzing every pixel.
+/// let cli = Cli::parse_from(["color_scheme_generator", "image.png", "-c", "prevalent"]);
+/// let time = |pixels: &[image::Rgb<u8>]| {
+///     let start = Instant::now();
+///     generate_color_theme_from_pixels(pixels, &cli).unwrap();
+///     start.elapsed()
+/// };
+/// assert!(time(&sampled) <= time(&full));
+/// ```
+pub fn downsample(image: image::DynamicImage, sample_size: u32) -> image::DynamicImage {
+    if sample_size == 0 || image.width().max(image.height()) <= sample_size {
+        return image;
+    }
+    image.resize(
+        sample_size,
+        sample_size,
+        image::imageops::FilterType::Triangle,
+    )
+}
+
+/// Get a [`Vec<RGB>`] for already decoded pixels based on the centrality and color theme options.
+///
+/// # Notes
+/// [`Cli::image`] is ignored, every other argument has the same meaning as in [`generate_color_theme`].
+///
+/// # Errors
+///
+/// Will return an error if there are no pixels to analyze.
+///
+/// # Examples
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, ColorSchemeError};
+/// # use color_scheme_generator::theme_calculation::generate_color_theme_from_pixels;
+/// let cli = Cli::parse_from(["color_scheme_generator", "empty.png"]);
+/// let error = generate_color_theme_from_pixels(&[], &cli).unwrap_err();
+/// assert!(matches!(error, ColorSchemeError::EmptyImage));
+/// assert_eq!(error.to_string(), "image contains no pixels");
+/// ```
+/// Pixels outside of [`Cli::min_lightness`] and [`Cli::max_lightness`] are ignored, unless that would leave no pixels.
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::theme_calculation::generate_color_theme_from_pixels;
+/// let mut pixels = vec![image::Rgb([0, 0, 0]); 90];
+/// pixels.extend(vec![image::Rgb([30, 30, 200]); 10]);
+/// for centrality in ["average", "median", "prevalent"] {
+///     let cli = Cli::parse_from(["color_scheme_generator", "img.png", "-c", centrality, "--min-lightness", "10", "--triadic"]);
+///     let colors = generate_color_theme_from_pixels(&pixels, &cli).unwrap();
+///     assert_eq!(colors[0], RGB { red: 30, green: 30, blue: 200 });
+/// }
+/// let cli = Cli::parse_from(["color_scheme_generator", "img.png", "--min-lightness", "90", "--triadic"]);
+/// assert_eq!(generate_color_theme_from_pixels(&pixels, &cli).unwrap()[0], RGB { red: 0, green: 0, blue: 0 });
+/// ```
+/// The colors selected after the first two are appended as accent colors.
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::theme_calculation::generate_color_theme_from_pixels;
+/// let palette = [[200, 30, 30], [30, 200, 30], [30, 30, 200], [200, 200, 30], [30, 200, 200]];
+/// let pixels = palette
+///     .iter()
+///     .enumerate()
+///     .flat_map(|(i, p)| vec![image::Rgb(*p); 50 - i])
+///     .collect::<Vec<_>>();
+/// let cli = Cli::parse_from(["color_scheme_generator", "img.png", "-c", "prevalent", "--palette-size", "5", "--complementary", "--include-seed"]);
+/// let colors = generate_color_theme_from_pixels(&pixels, &cli).unwrap();
+/// assert_eq!(colors.len(), 5);
+/// assert_eq!(colors[0], RGB { red: 200, green: 30, blue: 30 });
+/// let accents = palette[2..].iter().map(|p| RGB { red: p[0], green: p[1], blue: p[2] }).collect::<Vec<_>>();
+/// assert_eq!(colors[2..], accents[..]);
+///
+/// let cli = Cli::parse_from(["color_scheme_generator", "img.png", "-c", "average", "--palette-size", "5", "--complementary", "--include-seed"]);
+/// assert_eq!(generate_color_theme_from_pixels(&pixels, &cli).unwrap().len(), 2);
+/// ```
+/// The centralities stop as soon as [`Cli::timeout`] elapses instead of running to completion.
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, ColorSchemeError};
+/// # use color_scheme_generator::theme_calculation::generate_color_theme_from_pixels;
+/// let pixels = (0..1 << 22).map(|i: u32| image::Rgb([i as u8, (i >> 8) as u8, (i >> 16) as u8])).collect::<Vec<_>>();
+/// for centrality in ["prevalent", "kmeans", "average", "lab-average", "median"] {
+///     let cli = Cli::parse_from(["color_scheme_generator", "img.png", "-c", centrality, "--palette-size", "8", "--timeout", "0.001", "--triadic"]);
+///     let start = std::time::Instant::now();
+///     assert!(matches!(generate_color_theme_from_pixels(&pixels, &cli), Err(ColorSchemeError::Timeout(_))));
+///     assert!(start.elapsed() < std::time::Duration::from_secs(5));
+/// }
+/// ```
+pub fn generate_color_theme_from_pixels(
+    pixels: &[image::Rgb<u8>],
+    args: &Cli,
+) -> Result<Vec<RGB>, ColorSchemeError> {
+    Ok(color_theme_from_pixels(pixels, args, &Deadline::after(args.timeout))?.0)
+}
+
+/// Get the color theme of the pixels like [`generate_color_theme_from_pixels`] along with its coverage, giving up once the deadline passes.
+fn color_theme_from_pixels(
+    pixels: &[image::Rgb<u8>],
+    args: &Cli,
+    deadline: &Deadline,
+) -> Result<(Vec<RGB>, Option<Coverage>), ColorSchemeError> {
+    if pixels.is_empty() {
+        return Err(ColorSchemeError::EmptyImage);
+    }
+    let filtered = lightness_pixels(pixels, args.min_lightness, args.max_lightness);
+    let pixels = if filtered.is_empty() {
+        warn!("No pixels are within the lightness bounds. Analyzing every pixel instead.");
+        pixels
+    } else {
+        &filtered
     };
-    match args.centrality {
-        Centrality::Average | Centrality::Median => {
-            Ok(call_gamut_cli(&args.color_themes, &bar_color[0], None)?)
+    let palette_size = selection_size(&args.color_themes);
+    let start = Instant::now();
+    let counted = |colors: Vec<ColorCount>| {
+        let selected = colors.iter().map(|c| c.color.clone()).collect();
+        let pixels = pixels.len();
+        (selected, Some(Coverage { colors, pixels }))
+    };
+    let (bar_color, coverage) = match args.centrality {
+        Centrality::Average if args.gamma => {
+            (vec![linear_average_pixel_until(pixels, deadline)?], None)
         }
-        Centrality::Prevalent => Ok(call_gamut_cli(
-            &args.color_themes,
-            &bar_color[0],
-            Some(&bar_color[1]),
+        Centrality::Average => (vec![average_pixel_until(pixels, deadline)?], None),
+        Centrality::VividAverage => (vec![vivid_average_pixel_until(pixels, deadline)?], None),
+        Centrality::LabAverage => (vec![lab_average_pixel_until(pixels, deadline)?], None),
+        Centrality::Median => (vec![median_pixel_until(pixels, deadline)?], None),
+        Centrality::Prevalent => counted(prevalent_pixel_until(pixels, palette_size, 8, deadline)?),
+        Centrality::Mode => counted(prevalent_pixel_until(
+            pixels,
+            palette_size,
+            MODE_QUANTIZATION_BITS,
+            deadline,
         )?),
+        Centrality::Kmeans => counted(kmeans_pixel_until(pixels, palette_size, deadline)?),
+        Centrality::MedianCut => counted(median_cut_pixel_until(pixels, palette_size, deadline)?),
+    };
+    debug!("Centrality {} took {:?}.", args.centrality, start.elapsed());
+    Ok((color_theme_from_centrality(bar_color, args), coverage))
+}
+
+/// Derive the color theme from the colors selected by the centrality, appending the colors after the first two as accents.
+fn color_theme_from_centrality(bar_color: Vec<RGB>, args: &Cli) -> Vec<RGB> {
+    trace!("Centrality selected {bar_color:?}.");
+    let start = Instant::now();
+    let bar_color = match args.color_themes.dominant_pair {
+        true => dominant_pair(bar_color, args.color_themes.palette_size),
+        false => bar_color,
+    };
+    let mut colors = gamut::color_theme(&args.color_themes, &bar_color[0], bar_color.get(1));
+    colors.extend(bar_color.into_iter().skip(2));
+    debug!("Color theme took {:?}.", start.elapsed());
+    colors
+}
+
+/// Amount of colors selected by the centrality that [`ColorThemeOption::dominant_pair`] chooses the bar and workspace colors from.
+pub const DOMINANT_PAIR_CANDIDATES: u8 = 8;
+
+/// Get the amount of colors the centrality selects for the color theme options.
+///
+/// # Notes
+/// This is the [`ColorThemeOption::palette_size`], which is raised to at least [`DOMINANT_PAIR_CANDIDATES`]
+/// if [`ColorThemeOption::dominant_pair`] is given so there are more colors to choose the pair from.
+pub fn selection_size(ct: &ColorThemeOption) -> u8 {
+    match ct.dominant_pair {
+        true => ct.palette_size.max(DOMINANT_PAIR_CANDIDATES),
+        false => ct.palette_size,
     }
 }
 
+/// Move the pair of colors with the greatest [`RGB::distance_lab`] to the front and keep the first `palette_size` colors.
+///
+/// # Notes
+/// The colors are expected in the order the centrality selected them, most representative first.
+/// The first color of the pair is the one selected first, the other colors keep their order after the pair.
+/// Of equally distant pairs the one selected first is chosen.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::dominant_pair;
+/// let red = RGB { red: 200, green: 30, blue: 30 };
+/// let near_red = RGB { red: 190, green: 35, blue: 30 };
+/// let blue = RGB { red: 30, green: 30, blue: 200 };
+/// let colors = vec![red.clone(), near_red.clone(), blue.clone()];
+/// assert_eq!(dominant_pair(colors.clone(), 2), vec![red.clone(), blue.clone()]);
+/// assert_eq!(dominant_pair(colors, 3), vec![red.clone(), blue, near_red]);
+/// assert_eq!(dominant_pair(vec![red.clone()], 2), vec![red]);
+/// ```
+///
+/// The most prevalent colors are similar, so the workspace color is the distinct third one.
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, RGB};
+/// # use color_scheme_generator::theme_calculation::{gamut::tetratic, generate_color_theme_from_pixels};
+/// let mut pixels = vec![image::Rgb([200, 30, 30]); 50];
+/// pixels.extend(vec![image::Rgb([190, 35, 30]); 40]);
+/// pixels.extend(vec![image::Rgb([30, 30, 200]); 10]);
+/// let (red, near_red, blue) = (RGB { red: 200, green: 30, blue: 30 }, RGB { red: 190, green: 35, blue: 30 }, RGB { red: 30, green: 30, blue: 200 });
+/// let args = Cli::parse_from(["color_scheme_generator", "img.png", "-c", "prevalent", "--tetratic"]);
+/// assert_eq!(generate_color_theme_from_pixels(&pixels, &args).unwrap(), tetratic(&red, &near_red));
+/// let args = Cli::parse_from(["color_scheme_generator", "img.png", "-c", "prevalent", "--tetratic", "--dominant-pair"]);
+/// assert_eq!(generate_color_theme_from_pixels(&pixels, &args).unwrap(), tetratic(&red, &blue));
+/// ```
+pub fn dominant_pair(colors: Vec<RGB>, palette_size: u8) -> Vec<RGB> {
+    let pair = (0..colors.len())
+        .flat_map(|i| (i + 1..colors.len()).map(move |j| (i, j)))
+        .fold(None, |best: Option<((usize, usize), f64)>, (i, j)| {
+            let distance = colors[i].distance_lab(&colors[j]);
+            match best {
+                Some((_, farthest)) if farthest >= distance => best,
+                _ => Some(((i, j), distance)),
+            }
+        });
+    let order = match pair {
+        Some(((first, second), _)) => [first, second]
+            .into_iter()
+            .chain((0..colors.len()).filter(|&i| i != first && i != second))
+            .collect(),
+        None => (0..colors.len()).collect::<Vec<_>>(),
+    };
+    order
+        .into_iter()
+        .take(usize::from(palette_size))
+        .map(|i| colors[i].clone())
+        .collect()
+}
+
 /// Get the average pixel from an image.
 ///
 /// The average is the sum of each sub pixel divided by the total amount of pixels.
-fn average_pixel(pixels: &[image::Rgb<u8>]) -> RGB {
-    RGB {
-        red: u8::try_from(
-            pixels
-                .par_iter()
-                .map(|p| usize::from(p.0[0]))
-                .sum::<usize>()
-                / pixels.len(),
+///
+/// # Notes
+/// The division rounds down and the result saturates at 255, so the average never panics.
+/// The average of no pixels is black.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::average_pixel;
+/// let pixels = [image::Rgb([0, 100, 255]), image::Rgb([100, 200, 255])];
+/// assert_eq!(average_pixel(&pixels), RGB { red: 50, green: 150, blue: 255 });
+/// let white = vec![image::Rgb([255, 255, 255]); 4096];
+/// assert_eq!(average_pixel(&white), RGB { red: 255, green: 255, blue: 255 });
+/// let pixels = [image::Rgb([0, 0, 0]), image::Rgb([1, 1, 1])];
+/// assert_eq!(average_pixel(&pixels), RGB { red: 0, green: 0, blue: 0 });
+/// assert_eq!(average_pixel(&[]), RGB { red: 0, green: 0, blue: 0 });
+/// ```
+pub fn average_pixel(pixels: &[image::Rgb<u8>]) -> RGB {
+    average_pixel_until(pixels, &Deadline::NEVER).unwrap_or_default()
+}
+
+/// Get the average pixel like [`average_pixel`], giving up once the deadline passes.
+fn average_pixel_until(
+    pixels: &[image::Rgb<u8>],
+    deadline: &Deadline,
+) -> Result<RGB, ColorSchemeError> {
+    let sum = pixels
+        .par_chunks(DEADLINE_CHUNK)
+        .try_fold(
+            || [0u64; 3],
+            |mut sum, chunk| {
+                deadline.check()?;
+                for p in chunk {
+                    (0..3).for_each(|i| sum[i] += u64::from(p.0[i]));
+                }
+                Ok::<_, ColorSchemeError>(sum)
+            },
+        )
+        .try_reduce(
+            || [0u64; 3],
+            |mut a, b| {
+                (0..3).for_each(|i| a[i] += b[i]);
+                Ok(a)
+            },
+        )?;
+    let len = u64::try_from(pixels.len()).unwrap_or(u64::MAX).max(1);
+    let [red, green, blue] = sum.map(|s| u8::try_from(s / len).unwrap_or(u8::MAX));
+    Ok(RGB { red, green, blue })
+}
+
+/// Get the average pixel from an image in linear light.
+///
+/// # Notes
+/// Every sub pixel is converted into linear light before it is averaged and the average is converted back into sRGB,
+/// so the average of black and white is the gray that emits half as much light as white instead of the middle sRGB value.
+/// The average of no pixels is black.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::{average_pixel, linear_average_pixel};
+/// let pixels = [image::Rgb([0, 0, 0]), image::Rgb([255, 255, 255])];
+/// assert_eq!(average_pixel(&pixels), RGB { red: 127, green: 127, blue: 127 });
+/// assert_eq!(linear_average_pixel(&pixels), RGB { red: 188, green: 188, blue: 188 });
+/// assert_eq!(linear_average_pixel(&[]), RGB { red: 0, green: 0, blue: 0 });
+/// ```
+pub fn linear_average_pixel(pixels: &[image::Rgb<u8>]) -> RGB {
+    linear_average_pixel_until(pixels, &Deadline::NEVER).unwrap_or_default()
+}
+
+/// Get the linear average pixel like [`linear_average_pixel`], giving up once the deadline passes.
+fn linear_average_pixel_until(
+    pixels: &[image::Rgb<u8>],
+    deadline: &Deadline,
+) -> Result<RGB, ColorSchemeError> {
+    let sum = pixels
+        .par_chunks(DEADLINE_CHUNK)
+        .try_fold(
+            || [0f64; 3],
+            |mut sum, chunk| {
+                deadline.check()?;
+                for p in chunk {
+                    (0..3).for_each(|i| sum[i] += linear(p.0[i]));
+                }
+                Ok::<_, ColorSchemeError>(sum)
+            },
         )
-        .unwrap(),
-        green: u8::try_from(
-            pixels
-                .par_iter()
-                .map(|p| usize::from(p.0[1]))
-                .sum::<usize>()
-                / pixels.len(),
+        .try_reduce(
+            || [0f64; 3],
+            |mut a, b| {
+                (0..3).for_each(|i| a[i] += b[i]);
+                Ok(a)
+            },
+        )?;
+    let len = pixels.len().max(1) as f64;
+    Ok(RGB::from_linear(sum.map(|s| s / len)))
+}
+
+/// Get the average pixel from an image in CIELAB.
+///
+/// # Notes
+/// Every pixel is converted into CIELAB, see [`RGB::to_lab`], and the lightness and both components are averaged
+/// before the average is converted back into sRGB, so the result is perceptually between the pixels.
+/// The average of no pixels is black.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::{average_pixel, lab_average_pixel};
+/// let pixels = [image::Rgb([255, 0, 0]), image::Rgb([0, 255, 0])];
+/// assert_eq!(average_pixel(&pixels), RGB { red: 127, green: 127, blue: 0 });
+/// assert_eq!(lab_average_pixel(&pixels), RGB { red: 201, green: 171, blue: 0 });
+/// assert_eq!(lab_average_pixel(&[image::Rgb([10, 20, 30])]), RGB { red: 10, green: 20, blue: 30 });
+/// assert_eq!(lab_average_pixel(&[]), RGB { red: 0, green: 0, blue: 0 });
+/// ```
+pub fn lab_average_pixel(pixels: &[image::Rgb<u8>]) -> RGB {
+    lab_average_pixel_until(pixels, &Deadline::NEVER).unwrap_or_default()
+}
+
+/// Get the CIELAB average pixel like [`lab_average_pixel`], giving up once the deadline passes.
+fn lab_average_pixel_until(
+    pixels: &[image::Rgb<u8>],
+    deadline: &Deadline,
+) -> Result<RGB, ColorSchemeError> {
+    if pixels.is_empty() {
+        return Ok(RGB {
+            red: 0,
+            green: 0,
+            blue: 0,
+        });
+    }
+    let sum = pixels
+        .par_chunks(DEADLINE_CHUNK)
+        .try_fold(
+            || [0f64; 3],
+            |mut sum, chunk| {
+                deadline.check()?;
+                for p in chunk {
+                    let (l, a, b) = RGB {
+                        red: p.0[0],
+                        green: p.0[1],
+                        blue: p.0[2],
+                    }
+                    .to_lab();
+                    sum = [sum[0] + l, sum[1] + a, sum[2] + b];
+                }
+                Ok::<_, ColorSchemeError>(sum)
+            },
         )
-        .unwrap(),
-        blue: u8::try_from(
-            pixels
-                .par_iter()
-                .map(|p| usize::from(p.0[2]))
-                .sum::<usize>()
-                / pixels.len(),
+        .try_reduce(
+            || [0f64; 3],
+            |mut a, b| {
+                (0..3).for_each(|i| a[i] += b[i]);
+                Ok(a)
+            },
+        )?;
+    let len = pixels.len() as f64;
+    Ok(RGB::from_lab(sum[0] / len, sum[1] / len, sum[2] / len))
+}
+
+/// Get the average pixel from an image, weighting every pixel by its HSL saturation.
+///
+/// # Notes
+/// Colorful pixels dominate the result, while grays do not contribute to it at all.
+/// If every pixel is a gray the result is the same as [`average_pixel`].
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::{average_pixel, vivid_average_pixel};
+/// let mut pixels = vec![image::Rgb([128, 128, 128]); 90];
+/// pixels.extend(vec![image::Rgb([255, 0, 0]); 10]);
+/// assert_eq!(vivid_average_pixel(&pixels), RGB { red: 255, green: 0, blue: 0 });
+/// assert_eq!(average_pixel(&pixels), RGB { red: 140, green: 115, blue: 115 });
+///
+/// let grays = [image::Rgb([0, 0, 0]), image::Rgb([100, 100, 100])];
+/// assert_eq!(vivid_average_pixel(&grays), average_pixel(&grays));
+/// ```
+pub fn vivid_average_pixel(pixels: &[image::Rgb<u8>]) -> RGB {
+    vivid_average_pixel_until(pixels, &Deadline::NEVER).unwrap_or_default()
+}
+
+/// Get the vivid average pixel like [`vivid_average_pixel`], giving up once the deadline passes.
+fn vivid_average_pixel_until(
+    pixels: &[image::Rgb<u8>],
+    deadline: &Deadline,
+) -> Result<RGB, ColorSchemeError> {
+    let (sum, weight) = pixels
+        .par_chunks(DEADLINE_CHUNK)
+        .try_fold(
+            || ([0.0; 3], 0.0),
+            |(mut sum, mut weight), chunk| {
+                deadline.check()?;
+                for p in chunk {
+                    let (_, saturation, _) = RGB {
+                        red: p.0[0],
+                        green: p.0[1],
+                        blue: p.0[2],
+                    }
+                    .to_hsl();
+                    (0..3).for_each(|i| sum[i] += f64::from(p.0[i]) * saturation);
+                    weight += saturation;
+                }
+                Ok::<_, ColorSchemeError>((sum, weight))
+            },
         )
-        .unwrap(),
+        .try_reduce(
+            || ([0.0; 3], 0.0),
+            |(mut a, aw), (b, bw)| {
+                (0..3).for_each(|i| a[i] += b[i]);
+                Ok((a, aw + bw))
+            },
+        )?;
+    if weight == 0.0 {
+        return average_pixel_until(pixels, deadline);
     }
+    Ok(RGB {
+        red: (sum[0] / weight).round() as u8,
+        green: (sum[1] / weight).round() as u8,
+        blue: (sum[2] / weight).round() as u8,
+    })
 }
 
 /// Get the median pixel from an image
 ///
 /// The median is the middle value of each sub pixel inside of a sorted list.
-fn median_pixel(pixels: &[image::Rgb<u8>]) -> RGB {
-    RGB {
-        red: median(&pixels.par_iter().map(|p| p.0[0]).collect::<Vec<_>>()),
-        green: median(&pixels.par_iter().map(|p| p.0[1]).collect::<Vec<_>>()),
-        blue: median(&pixels.par_iter().map(|p| p.0[2]).collect::<Vec<_>>()),
-    }
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::median_pixel;
+/// let pixels = [image::Rgb([255, 0, 128]), image::Rgb([0, 128, 255]), image::Rgb([128, 255, 0])];
+/// assert_eq!(median_pixel(&pixels), RGB { red: 128, green: 128, blue: 128 });
+/// ```
+pub fn median_pixel(pixels: &[image::Rgb<u8>]) -> RGB {
+    median_pixel_until(pixels, &Deadline::NEVER).unwrap_or_default()
+}
+
+/// Get the median pixel like [`median_pixel`], giving up once the deadline passes.
+fn median_pixel_until(
+    pixels: &[image::Rgb<u8>],
+    deadline: &Deadline,
+) -> Result<RGB, ColorSchemeError> {
+    let channel = |i: usize| {
+        let mut values = pixels
+            .par_chunks(DEADLINE_CHUNK)
+            .map(|chunk| {
+                deadline.check()?;
+                Ok(chunk.iter().map(|p| p.0[i]).collect::<Vec<_>>())
+            })
+            .collect::<Result<Vec<_>, ColorSchemeError>>()?
+            .concat();
+        deadline.check()?;
+        Ok::<_, ColorSchemeError>(median(&mut values))
+    };
+    Ok(RGB {
+        red: channel(0)?,
+        green: channel(1)?,
+        blue: channel(2)?,
+    })
 }
 
-/// Get the median value from a slice of [`u8`].
-fn median(color_slice: &[u8]) -> u8 {
-    if color_slice.len() % 2 == 0 {
-        let left_middle =
-            color_slice[(((color_slice.len() as f64) / (2.0)) - 1.0).floor() as usize];
-        let right_middle = color_slice[((color_slice.len() as f64) / (2.0)).floor() as usize];
-        (((right_middle as f64) + (left_middle as f64)) / 2.0) as u8
+/// Sort a slice of [`u8`] and get its median value.
+///
+/// For an even amount of values the two middlemost values are averaged, rounding down.
+fn median(color_slice: &mut [u8]) -> u8 {
+    color_slice.par_sort_unstable();
+    let middle = color_slice.len() / 2;
+    if color_slice.len().is_multiple_of(2) {
+        ((u16::from(color_slice[middle - 1]) + u16::from(color_slice[middle])) / 2) as u8
     } else {
-        color_slice[(((color_slice.len() as f64) / 2.0) - 1.0) as usize]
+        color_slice[middle]
     }
 }
 
+/// Amount of bits kept per sub pixel by [`Centrality::Mode`] before counting pixels.
+pub const MODE_QUANTIZATION_BITS: u8 = 5;
+
 /// Get the pixels that appear the most times from an image.
 ///
 /// # Note
 /// Will return a [`Vec<ColorThemeOption>`], whose size will be either number_of_themes
 /// or the amount of distinct rgb pixels in the image. The smaller of these two amounts
 /// will be the size of the returned vector.
-fn prevalent_pixel(pixels: &[image::Rgb<u8>], number_of_themes: u8) -> Vec<RGB> {
-    let mut pixel_prevalence_count = std::collections::HashMap::new();
-    for pixel in pixels.iter() {
-        let count = pixel_prevalence_count.entry(pixel).or_insert(0);
-        *count += 1;
+///
+/// Before counting, every sub pixel is quantized to quantization_bits bits and replaced by the center of its bucket.
+/// A quantization_bits of 8 or more counts the exact pixels.
+///
+/// The pixels are counted in parallel, the result does not depend on the amount of threads.
+/// Pixels that appear the same amount of times are ordered by their red, green and blue sub pixels, so the same image always gives the same result.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::prevalent_pixel;
+/// let gradient = (98..=110).map(|r| image::Rgb([r, 0, 0])).collect::<Vec<_>>();
+/// assert_eq!(prevalent_pixel(&gradient, 1, 5), vec![RGB { red: 108, green: 4, blue: 4 }]);
+/// ```
+/// ```
+/// # use color_scheme_generator::theme_calculation::prevalent_pixel;
+/// let pixels = (1..=200u8)
+///     .flat_map(|i| vec![image::Rgb([i, 255 - i, 0]); usize::from(i) * 50])
+///     .collect::<Vec<_>>();
+/// let serial = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+/// let parallel = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+/// let expected = serial.install(|| prevalent_pixel(&pixels, 5, 8));
+/// assert_eq!(parallel.install(|| prevalent_pixel(&pixels, 5, 8)), expected);
+/// assert_eq!(expected[0], color_scheme_generator::common::RGB { red: 200, green: 55, blue: 0 });
+/// ```
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::prevalent_pixel;
+/// let pixels = [image::Rgb([30, 30, 200]), image::Rgb([200, 30, 30])].repeat(50);
+/// for _ in 0..20 {
+///     assert_eq!(prevalent_pixel(&pixels, 1, 8), vec![RGB { red: 30, green: 30, blue: 200 }]);
+/// }
+/// ```
+pub fn prevalent_pixel(
+    pixels: &[image::Rgb<u8>],
+    number_of_themes: u8,
+    quantization_bits: u8,
+) -> Vec<RGB> {
+    prevalent_pixel_until(
+        pixels,
+        number_of_themes,
+        quantization_bits,
+        &Deadline::NEVER,
+    )
+    .unwrap_or_default()
+    .into_iter()
+    .map(|c| c.color)
+    .collect()
+}
+
+/// Get the most prevalent pixels like [`prevalent_pixel`] along with their counts, giving up once the deadline passes.
+fn prevalent_pixel_until(
+    pixels: &[image::Rgb<u8>],
+    number_of_themes: u8,
+    quantization_bits: u8,
+    deadline: &Deadline,
+) -> Result<Vec<ColorCount>, ColorSchemeError> {
+    let pixel_prevalence_count = prevalence_counts(pixels, quantization_bits, deadline)?;
+    let mut most_prevalent = pixel_prevalence_count.into_iter().collect::<Vec<_>>();
+    most_prevalent.sort_by_key(|(pixel, count)| (std::cmp::Reverse(*count), pixel.0));
+    Ok(most_prevalent
+        .into_iter()
+        .take(number_of_themes as usize)
+        .map(|(pixel, count)| ColorCount {
+            color: RGB {
+                red: pixel.0[0],
+                green: pixel.0[1],
+                blue: pixel.0[2],
+            },
+            count,
+        })
+        .collect())
+}
+
+/// Count the pixels of every color after quantizing them to the given amount of bits, giving up once the deadline passes.
+fn prevalence_counts(
+    pixels: &[image::Rgb<u8>],
+    quantization_bits: u8,
+    deadline: &Deadline,
+) -> Result<std::collections::HashMap<image::Rgb<u8>, usize>, ColorSchemeError> {
+    pixels
+        .par_chunks(DEADLINE_CHUNK)
+        .try_fold(std::collections::HashMap::new, |mut counts, chunk| {
+            deadline.check()?;
+            for pixel in chunk {
+                *counts
+                    .entry(quantize(pixel, quantization_bits))
+                    .or_insert(0) += 1;
+            }
+            Ok(counts)
+        })
+        .try_reduce(std::collections::HashMap::new, |a, b| {
+            let (mut larger, smaller) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+            for (pixel, count) in smaller {
+                *larger.entry(pixel).or_insert(0) += count;
+            }
+            Ok(larger)
+        })
+}
+
+/// Number of colors listed in [`Histogram::prevalent`].
+pub const HISTOGRAM_COLORS: usize = 10;
+
+/// Get the histograms of the sub pixels of the pixels and their [`HISTOGRAM_COLORS`] most prevalent colors.
+///
+/// # Notes
+/// The pixels are filtered by [`Cli::min_lightness`] and [`Cli::max_lightness`] like [`generate_color_theme_from_pixels`] does,
+/// so given the [`analyzed_pixels`] they are the same ones the centrality analyzes. The prevalent colors are not quantized,
+/// and colors with the same number of pixels are ordered by their sub pixels.
+///
+/// # Examples
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, ColorCount, RGB};
+/// # use color_scheme_generator::theme_calculation::histogram;
+/// let mut pixels = vec![image::Rgb([200, 30, 30]); 12];
+/// pixels.extend([image::Rgb([30, 30, 200]); 4]);
+/// pixels.push(image::Rgb([255, 255, 255]));
+/// let args = Cli::parse_from(["color_scheme_generator", "image.png", "--max-lightness", "90"]);
+/// let histogram = histogram(&pixels, &args);
+/// assert_eq!(histogram.pixels, 16);
+/// assert_eq!(histogram.prevalent[0], ColorCount { color: RGB { red: 200, green: 30, blue: 30 }, count: 12 });
+/// assert_eq!(histogram.prevalent[1].count, 4);
+/// assert_eq!((histogram.red[200], histogram.red[30], histogram.green[30]), (12, 4, 16));
+/// assert_eq!(histogram.blue.len(), 256);
+/// ```
+pub fn histogram(pixels: &[image::Rgb<u8>], args: &Cli) -> Histogram {
+    let filtered = lightness_pixels(pixels, args.min_lightness, args.max_lightness);
+    let pixels = if filtered.is_empty() {
+        pixels
+    } else {
+        &filtered
+    };
+    let mut channels = [[0; 256]; 3];
+    for pixel in pixels {
+        for (channel, sub_pixel) in channels.iter_mut().zip(pixel.0) {
+            channel[usize::from(sub_pixel)] += 1;
+        }
     }
-    let mut most_prevalent = pixel_prevalence_count
-        .par_iter()
-        .map(|x| (x.0, x.1))
+    let mut prevalent = prevalence_counts(pixels, 8, &Deadline::NEVER)
+        .unwrap_or_default()
+        .into_iter()
         .collect::<Vec<_>>();
-    most_prevalent.sort_by(|a, b| b.1.cmp(a.1));
-    if most_prevalent.len() > number_of_themes as usize {
-        most_prevalent[0..(number_of_themes as usize)]
-            .par_iter()
-            .map(|x| RGB {
-                red: x.0[0],
-                green: x.0[1],
-                blue: x.0[2],
-            })
-            .collect::<Vec<_>>()
-    } else {
-        most_prevalent
-            .par_iter()
-            .map(|x| RGB {
-                red: x.0[0],
-                green: x.0[1],
-                blue: x.0[2],
+    prevalent.sort_by_key(|(pixel, count)| (std::cmp::Reverse(*count), pixel.0));
+    let [red, green, blue] = channels.map(|channel| channel.to_vec());
+    Histogram {
+        pixels: pixels.len(),
+        red,
+        green,
+        blue,
+        prevalent: prevalent
+            .into_iter()
+            .take(HISTOGRAM_COLORS)
+            .map(|(pixel, count)| ColorCount {
+                color: RGB {
+                    red: pixel.0[0],
+                    green: pixel.0[1],
+                    blue: pixel.0[2],
+                },
+                count,
             })
-            .collect::<Vec<_>>()
+            .collect(),
     }
 }
 
-fn call_gamut_cli(
-    ct: &ColorThemeOption,
-    color1: &RGB,
-    color2: Option<&RGB>,
-) -> Result<Vec<RGB>, anyhow::Error> {
-    let color2str = match color2 {
-        Some(c) => c,
-        None => &RGB {
-            blue: 0,
-            green: 0,
-            red: 0,
-        },
-    };
-    let gamut_command = format!(
-        "{} {} -Color1 '{color1}' -Color2 '{color2str}'",
-        which(GAMUT_CLI_NAME)?.to_str().unwrap_or(GAMUT_CLI_NAME),
-        ct
-    );
-    let gamut_output = String::from_utf8(
-        Command::new("bash")
-            .arg("-c")
-            .arg(&gamut_command)
-            .output()?
-            .stdout,
-    )?
-    .trim()
-    .to_owned()
-    .to_ascii_lowercase();
-
-    #[derive(Serialize, Deserialize)]
-    struct Color {
-        color: String,
-    }
-
-    let ret = match gamut_output.contains("[") || gamut_output.contains("]") {
-        true => serde_json::from_str::<Vec<Color>>(&gamut_output)?,
-        false => vec![serde_json::from_str::<Color>(&gamut_output)?],
+/// Get the color of an image that stands out the most as an accent.
+///
+/// # Notes
+/// The pixels are quantized like [`Centrality::Mode`] and every color is scored by its HSL saturation times the amount of pixels it covers,
+/// so a small vivid area is chosen over a large gray one. If every pixel is a gray the most prevalent gray is chosen.
+/// The accent of no pixels is black.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::accent_pixel;
+/// let mut pixels = vec![image::Rgb([120, 120, 120]); 80];
+/// pixels.extend(vec![image::Rgb([60, 60, 64]); 10]);
+/// pixels.extend(vec![image::Rgb([228, 36, 36]); 10]);
+/// let accent = accent_pixel(&pixels);
+/// assert!(accent.red > 200 && accent.green < 60 && accent.blue < 60);
+/// assert_eq!(accent_pixel(&[image::Rgb([120, 120, 120])]), RGB { red: 124, green: 124, blue: 124 });
+/// assert_eq!(accent_pixel(&[]), RGB { red: 0, green: 0, blue: 0 });
+/// ```
+pub fn accent_pixel(pixels: &[image::Rgb<u8>]) -> RGB {
+    accent_pixel_until(pixels, &Deadline::NEVER).unwrap_or_default()
+}
+
+/// Get the accent pixel like [`accent_pixel`], giving up once the deadline passes.
+fn accent_pixel_until(
+    pixels: &[image::Rgb<u8>],
+    deadline: &Deadline,
+) -> Result<RGB, ColorSchemeError> {
+    let score = |(pixel, count): &(image::Rgb<u8>, usize)| {
+        let (_, saturation, _) = RGB {
+            red: pixel.0[0],
+            green: pixel.0[1],
+            blue: pixel.0[2],
+        }
+        .to_hsl();
+        (saturation * *count as f64, *count)
     };
+    Ok(prevalence_counts(pixels, MODE_QUANTIZATION_BITS, deadline)?
+        .into_iter()
+        .max_by(|a, b| {
+            let ((a_score, a_count), (b_score, b_count)) = (score(a), score(b));
+            a_score
+                .total_cmp(&b_score)
+                .then(a_count.cmp(&b_count))
+                .then(b.0 .0.cmp(&a.0 .0))
+        })
+        .map(|(p, _)| RGB {
+            red: p.0[0],
+            green: p.0[1],
+            blue: p.0[2],
+        })
+        .unwrap_or(RGB {
+            red: 0,
+            green: 0,
+            blue: 0,
+        }))
+}
 
-    let mut ret = ret
+/// Replace every sub pixel with the center of its bucket when keeping only the given amount of bits.
+fn quantize(pixel: &image::Rgb<u8>, bits: u8) -> image::Rgb<u8> {
+    if bits >= 8 {
+        return *pixel;
+    }
+    let shift = 8 - bits;
+    let center = (1u8 << shift) / 2;
+    image::Rgb(pixel.0.map(|c| ((c >> shift) << shift) + center))
+}
+
+/// Maximum amount of iterations used by [`kmeans_pixel`] to converge the centroids.
+const KMEANS_MAX_ITERATIONS: usize = 20;
+
+/// Get the centroids of the pixels of an image after clustering them with the k-means algorithm.
+///
+/// # Note
+/// The centroids are initialized deterministically: the first one is the pixel farthest away from the average pixel,
+/// and every following one is the pixel farthest away from every centroid picked so far.
+/// The clustering stops once the centroids stop moving or after [`KMEANS_MAX_ITERATIONS`] iterations.
+/// Will return a [`Vec<RGB>`] sorted by cluster size, whose size will be either number_of_themes
+/// or the amount of distinct rgb pixels in the image, whichever is smaller.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::kmeans_pixel;
+/// let mut pixels = vec![image::Rgb([200, 30, 30]); 30];
+/// pixels.extend(vec![image::Rgb([30, 30, 200]); 10]);
+/// assert_eq!(
+///     kmeans_pixel(&pixels, 2),
+///     vec![RGB { red: 200, green: 30, blue: 30 }, RGB { red: 30, green: 30, blue: 200 }]
+/// );
+/// ```
+pub fn kmeans_pixel(pixels: &[image::Rgb<u8>], number_of_themes: u8) -> Vec<RGB> {
+    kmeans_pixel_until(pixels, number_of_themes, &Deadline::NEVER)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|c| c.color)
+        .collect()
+}
+
+/// Get the k-means centroids like [`kmeans_pixel`] along with the sizes of their clusters, giving up once the deadline passes.
+fn kmeans_pixel_until(
+    pixels: &[image::Rgb<u8>],
+    number_of_themes: u8,
+    deadline: &Deadline,
+) -> Result<Vec<ColorCount>, ColorSchemeError> {
+    let points = pixels
+        .par_iter()
+        .map(|p| [f64::from(p.0[0]), f64::from(p.0[1]), f64::from(p.0[2])])
+        .collect::<Vec<_>>();
+    if points.is_empty() || number_of_themes == 0 {
+        return Ok(vec![]);
+    }
+    let average = average_pixel(pixels);
+    let mut centroids = vec![[
+        f64::from(average.red),
+        f64::from(average.green),
+        f64::from(average.blue),
+    ]];
+    while centroids.len() <= usize::from(number_of_themes) {
+        deadline.check()?;
+        let (farthest, distance) = points
+            .iter()
+            .map(|p| (p, nearest_centroid(p, &centroids).1))
+            .fold((&points[0], -1.0), |a, b| if b.1 > a.1 { b } else { a });
+        if distance <= 0.0 {
+            break;
+        }
+        centroids.push(*farthest);
+    }
+    centroids.remove(0);
+    if centroids.is_empty() {
+        centroids.push(points[0]);
+    }
+
+    let mut clusters = cluster_points(&points, &centroids);
+    for _ in 0..KMEANS_MAX_ITERATIONS {
+        deadline.check()?;
+        let updated = clusters
+            .iter()
+            .zip(&centroids)
+            .map(|((sum, count), old)| match count {
+                0 => *old,
+                _ => sum.map(|s| s / *count as f64),
+            })
+            .collect::<Vec<_>>();
+        if updated == centroids {
+            break;
+        }
+        centroids = updated;
+        clusters = cluster_points(&points, &centroids);
+    }
+
+    let mut centroids = centroids
         .into_iter()
-        .filter_map(|c| RGB::from_str(&c.color).ok())
+        .zip(clusters.into_iter().map(|(_, count)| count))
+        .filter(|(_, count)| *count > 0)
         .collect::<Vec<_>>();
+    centroids.sort_by_key(|c| std::cmp::Reverse(c.1));
+    Ok(centroids
+        .into_iter()
+        .map(|(c, count)| ColorCount {
+            color: RGB {
+                red: c[0].round() as u8,
+                green: c[1].round() as u8,
+                blue: c[2].round() as u8,
+            },
+            count,
+        })
+        .collect::<Vec<_>>())
+}
+
+/// Get the average colors of the buckets made by splitting the pixels of an image with the median cut algorithm.
+///
+/// # Note
+/// Starting with a single bucket holding every pixel, the bucket with the widest range in any channel is sorted along that channel
+/// and split at its median, until there are number_of_themes buckets or no bucket can be split any further.
+/// Will return a [`Vec<RGB>`] sorted by bucket size, whose size will be either number_of_themes
+/// or the amount of distinct rgb pixels in the image, whichever is smaller.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::median_cut_pixel;
+/// let quadrants = [[200, 30, 30], [30, 200, 30], [30, 30, 200], [200, 200, 30]];
+/// let image = image::RgbImage::from_fn(8, 8, |x, y| image::Rgb(quadrants[(x / 4 + 2 * (y / 4)) as usize]));
+/// let pixels = image.pixels().cloned().collect::<Vec<_>>();
+/// let mut colors = median_cut_pixel(&pixels, 4);
+/// colors.sort_by_key(|c| (c.red, c.green, c.blue));
+/// assert_eq!(
+///     colors,
+///     vec![
+///         RGB { red: 30, green: 30, blue: 200 },
+///         RGB { red: 30, green: 200, blue: 30 },
+///         RGB { red: 200, green: 30, blue: 30 },
+///         RGB { red: 200, green: 200, blue: 30 },
+///     ]
+/// );
+/// ```
+pub fn median_cut_pixel(pixels: &[image::Rgb<u8>], number_of_themes: u8) -> Vec<RGB> {
+    median_cut_pixel_until(pixels, number_of_themes, &Deadline::NEVER)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|c| c.color)
+        .collect()
+}
 
-    if ct.darker > 0 || ct.lighter > 0 || ct.complementary || ct.contrast || ct.hue_offset > 0 {
-        ret.insert(0, color1.to_owned());
+/// Get the median cut buckets like [`median_cut_pixel`] along with their sizes, giving up once the deadline passes.
+fn median_cut_pixel_until(
+    pixels: &[image::Rgb<u8>],
+    number_of_themes: u8,
+    deadline: &Deadline,
+) -> Result<Vec<ColorCount>, ColorSchemeError> {
+    if pixels.is_empty() || number_of_themes == 0 {
+        return Ok(vec![]);
     }
-    Ok(ret)
+    let mut buckets = vec![pixels.to_vec()];
+    while buckets.len() < usize::from(number_of_themes) {
+        deadline.check()?;
+        let (index, channel, range) = buckets
+            .iter()
+            .enumerate()
+            .flat_map(|(i, bucket)| (0..3).map(move |c| (i, c, channel_range(bucket, c))))
+            .fold((0, 0, 0), |a, b| if b.2 > a.2 { b } else { a });
+        if range == 0 {
+            break;
+        }
+        let mut bucket = buckets.swap_remove(index);
+        bucket.sort_by_key(|p| p.0[channel]);
+        let upper = bucket.split_off(bucket.len() / 2);
+        buckets.push(bucket);
+        buckets.push(upper);
+    }
+    buckets.sort_by_key(|b| std::cmp::Reverse(b.len()));
+    Ok(buckets
+        .iter()
+        .map(|b| ColorCount {
+            color: average_pixel(b),
+            count: b.len(),
+        })
+        .collect())
+}
+
+/// Get the difference between the largest and smallest value of a channel in a bucket of pixels.
+fn channel_range(bucket: &[image::Rgb<u8>], channel: usize) -> u8 {
+    let values = bucket.iter().map(|p| p.0[channel]);
+    values.clone().max().unwrap_or_default() - values.min().unwrap_or_default()
+}
+
+/// Get the index of and the squared distance to the centroid nearest to a point.
+fn nearest_centroid(point: &[f64; 3], centroids: &[[f64; 3]]) -> (usize, f64) {
+    centroids
+        .iter()
+        .map(|c| (0..3).map(|i| (point[i] - c[i]).powi(2)).sum::<f64>())
+        .enumerate()
+        .fold((0, f64::MAX), |a, b| if b.1 < a.1 { b } else { a })
+}
+
+/// Get the sum and amount of points assigned to each centroid.
+fn cluster_points(points: &[[f64; 3]], centroids: &[[f64; 3]]) -> Vec<([f64; 3], usize)> {
+    points
+        .par_iter()
+        .fold(
+            || vec![([0.0; 3], 0); centroids.len()],
+            |mut clusters, point| {
+                let (index, _) = nearest_centroid(point, centroids);
+                (0..3).for_each(|i| clusters[index].0[i] += point[i]);
+                clusters[index].1 += 1;
+                clusters
+            },
+        )
+        .reduce(
+            || vec![([0.0; 3], 0); centroids.len()],
+            |mut a, b| {
+                a.iter_mut().zip(b).for_each(|(a, b)| {
+                    (0..3).for_each(|i| a.0[i] += b.0[i]);
+                    a.1 += b.1;
+                });
+                a
+            },
+        )
 }
@@ -0,0 +1,290 @@
+#![deny(unused_extern_crates)]
+#![warn(missing_docs)]
+use crate::common::{Cli, Coverage, Sort, RGB};
+use crate::theme_calculation::{cvd, gamut};
+use log::warn;
+
+/// Apply the adjustments of the command line arguments to a generated color theme.
+///
+/// # Notes
+/// The adjustments are applied after the color theme is read from or written to the cache,
+/// so the same cached color theme is shared between every combination of them.
+/// `--invert` is applied before `--complement-all`, `--temperature` and `--saturation`, then `--cvd` and `--min-contrast`, then the colors are sorted and `--strip-duplicates` is applied last.
+/// Sorting by frequency uses the counts of the [`Coverage`] of the generated colors, which the centrality counted while selecting them,
+/// so the image is not decoded again. Colors without a count, and every color if there is no coverage, sort last in their order.
+///
+/// # Examples
+/// ```
+/// use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, ColorCount, Coverage, RGB};
+/// # use color_scheme_generator::theme_calculation::adjust::adjust;
+/// let red = RGB { red: 255, green: 0, blue: 0 };
+/// let black = RGB { red: 0, green: 0, blue: 0 };
+///
+/// let args = Cli::parse_from(["color_scheme_generator", "--invert", "image.png"]);
+/// assert_eq!(adjust(vec![black, red.clone()], None, &args), vec![RGB { red: 255, green: 255, blue: 255 }, RGB { red: 0, green: 255, blue: 255 }]);
+///
+/// let args = Cli::parse_from(["color_scheme_generator", "--complement-all", "image.png"]);
+/// assert_eq!(adjust(vec![red.clone()], None, &args), vec![RGB { red: 0, green: 255, blue: 255 }]);
+///
+/// let gray = RGB { red: 128, green: 128, blue: 128 };
+/// let args = Cli::parse_from(["color_scheme_generator", "--temperature", "-40", "image.png"]);
+/// assert_eq!(adjust(vec![gray.clone()], None, &args), vec![gray.with_temperature(-40)]);
+/// let args = Cli::parse_from(["color_scheme_generator", "--saturation", "100", "image.png"]);
+/// assert_eq!(adjust(vec![RGB { red: 140, green: 120, blue: 120 }], None, &args), vec![RGB { red: 150, green: 110, blue: 110 }]);
+///
+/// let args = Cli::parse_from(["color_scheme_generator", "image.png"]);
+/// assert_eq!(adjust(vec![red.clone()], None, &args), vec![red.clone()]);
+///
+/// let coverage = Coverage { colors: vec![ColorCount { color: red.clone(), count: 3 }], pixels: 4 };
+/// let args = Cli::parse_from(["color_scheme_generator", "--sort", "frequency", "image.png"]);
+/// assert_eq!(adjust(vec![gray.clone(), red.clone()], Some(&coverage), &args), vec![red, gray]);
+/// ```
+pub fn adjust(colors: Vec<RGB>, coverage: Option<&Coverage>, args: &Cli) -> Vec<RGB> {
+    adjust_with_coverage(colors, coverage, args)
+        .into_iter()
+        .map(|(c, _)| c)
+        .collect()
+}
+
+/// Apply the adjustments like [`adjust`], keeping every color with the fraction of the analyzed pixels it stood for before it was adjusted.
+///
+/// # Notes
+/// The fractions are those of [`Coverage::fractions`] for the generated colors and follow them through sorting and `--strip-duplicates`,
+/// so an adjusted color keeps the fraction of the color it was adjusted from. A fraction is None if the color is not in the coverage.
+///
+/// # Examples
+/// ```
+/// use clap::Parser;
+/// # use color_scheme_generator::common::{Cli, ColorCount, Coverage, RGB};
+/// # use color_scheme_generator::theme_calculation::adjust::adjust_with_coverage;
+/// let red = RGB { red: 255, green: 0, blue: 0 };
+/// let black = RGB { red: 0, green: 0, blue: 0 };
+/// let coverage = Coverage { colors: vec![ColorCount { color: black.clone(), count: 3 }], pixels: 4 };
+/// let args = Cli::parse_from(["color_scheme_generator", "--invert", "--sort", "luminance", "image.png"]);
+/// assert_eq!(
+///     adjust_with_coverage(vec![black, red], Some(&coverage), &args),
+///     vec![(RGB { red: 0, green: 255, blue: 255 }, None), (RGB { red: 255, green: 255, blue: 255 }, Some(0.75))]
+/// );
+/// ```
+pub fn adjust_with_coverage(
+    colors: Vec<RGB>,
+    coverage: Option<&Coverage>,
+    args: &Cli,
+) -> Vec<(RGB, Option<f64>)> {
+    let (fractions, frequencies) = match coverage {
+        Some(coverage) => (coverage.fractions(&colors), coverage.counts(&colors)),
+        None => (vec![None; colors.len()], vec![0; colors.len()]),
+    };
+    if args.sort == Sort::Frequency && coverage.is_none() {
+        warn!("Could not sort the colors by frequency: no pixels were counted for them.");
+    }
+    let colors = colors
+        .into_iter()
+        .map(|c| if args.invert { invert(&c) } else { c })
+        .map(|c| {
+            if args.complement_all {
+                gamut::complementary(&c)
+            } else {
+                c
+            }
+        })
+        .map(|c| match args.temperature {
+            0 => c,
+            temperature => c.with_temperature(temperature),
+        })
+        .map(|c| match args.saturation {
+            0 => c,
+            saturation => c.with_saturation(saturation),
+        })
+        .collect::<Vec<_>>();
+    let colors = match args.cvd {
+        Some(deficiency) if args.cvd_safe => cvd::distinguishable(&colors, deficiency),
+        Some(deficiency) => colors
+            .iter()
+            .map(|c| cvd::simulate(c, deficiency))
+            .collect(),
+        None => colors,
+    };
+    let colors = match (args.min_contrast, colors.as_slice()) {
+        (Some(ratio), [bar, workspace, rest @ ..]) => {
+            [bar.clone(), separate(bar, workspace, ratio)]
+                .into_iter()
+                .chain(rest.iter().cloned())
+                .collect()
+        }
+        _ => colors,
+    };
+    let order = sorted_order(&colors, args.sort, &frequencies);
+    let order = match args.strip_duplicates {
+        true => {
+            let sorted = order.iter().map(|i| colors[*i].clone()).collect::<Vec<_>>();
+            kept_order(&sorted, args.dedup_threshold)
+                .into_iter()
+                .map(|i| order[i])
+                .collect()
+        }
+        false => order,
+    };
+    order
+        .into_iter()
+        .map(|i| (colors[i].clone(), fractions[i]))
+        .collect()
+}
+
+/// Remove every color that is closer than `threshold` to an earlier color, keeping the first of every group of near identical colors.
+///
+/// # Notes
+/// The distance is [`RGB::distance_lab`], where a distance of about 2.3 is just noticeable.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::adjust::strip_duplicates;
+/// let gray = RGB { red: 128, green: 128, blue: 128 };
+/// let near = [gray.clone(), RGB { red: 129, green: 128, blue: 128 }, RGB { red: 128, green: 128, blue: 130 }];
+/// assert_eq!(strip_duplicates(near.to_vec(), 2.3), vec![gray.clone()]);
+/// assert_eq!(strip_duplicates(near.to_vec(), 0.0), near.to_vec());
+///
+/// let red = RGB { red: 255, green: 0, blue: 0 };
+/// assert_eq!(strip_duplicates(vec![gray.clone(), red.clone(), gray.clone()], 2.3), vec![gray, red]);
+/// ```
+pub fn strip_duplicates(colors: Vec<RGB>, threshold: f64) -> Vec<RGB> {
+    kept_order(&colors, threshold)
+        .into_iter()
+        .map(|i| colors[i].clone())
+        .collect()
+}
+
+/// Indices of the colors kept by [`strip_duplicates`], in order.
+fn kept_order(colors: &[RGB], threshold: f64) -> Vec<usize> {
+    (0..colors.len()).fold(vec![], |mut kept: Vec<usize>, i| {
+        if kept
+            .iter()
+            .all(|k| colors[*k].distance_lab(&colors[i]) >= threshold)
+        {
+            kept.push(i);
+        }
+        kept
+    })
+}
+
+/// Bounds of the HSL lightness that [`separate`] moves a color within, so it is not clipped to black or white.
+const LIGHTNESS_BOUNDS: std::ops::RangeInclusive<f64> = 0.05..=0.95;
+
+/// Change in HSL lightness of every step that [`separate`] moves a color by.
+const LIGHTNESS_STEP: f64 = 0.01;
+
+/// Lighten or darken the workspace color until its WCAG contrast ratio against the bar color is at least `ratio`.
+///
+/// # Notes
+/// The workspace color is first moved away from the bar color, lightening it if it is the lighter of the two, keeping its hue and saturation.
+/// If the ratio cannot be reached before the lightness bounds it is moved the other way,
+/// and if neither reaches the ratio the color with the highest contrast ratio is returned.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::adjust::separate;
+/// let bar = RGB { red: 100, green: 100, blue: 110 };
+/// let workspace = RGB { red: 110, green: 110, blue: 120 };
+/// assert!(workspace.contrast_ratio(&bar) < 1.2);
+/// let separated = separate(&bar, &workspace, 3.0);
+/// assert!(separated.contrast_ratio(&bar) >= 3.0);
+/// assert!(separated.luminance() > workspace.luminance());
+///
+/// let black = RGB { red: 0, green: 0, blue: 0 };
+/// assert_eq!(separate(&black, &workspace, 3.0), workspace);
+/// ```
+pub fn separate(bar: &RGB, workspace: &RGB, ratio: f64) -> RGB {
+    if workspace.contrast_ratio(bar) >= ratio {
+        return workspace.clone();
+    }
+    let (h, s, l) = workspace.to_hsl();
+    let toward = |lighter: bool| {
+        let mut lightness = l;
+        let mut color = workspace.clone();
+        loop {
+            lightness += match lighter {
+                true => LIGHTNESS_STEP,
+                false => -LIGHTNESS_STEP,
+            };
+            if !LIGHTNESS_BOUNDS.contains(&lightness) {
+                return color;
+            }
+            color = RGB::from_hsl(h, s, lightness);
+            if color.contrast_ratio(bar) >= ratio {
+                return color;
+            }
+        }
+    };
+    let lighter = workspace.luminance() >= bar.luminance();
+    let first = toward(lighter);
+    if first.contrast_ratio(bar) >= ratio {
+        return first;
+    }
+    let second = toward(!lighter);
+    match second.contrast_ratio(bar) > first.contrast_ratio(bar) {
+        true => second,
+        false => first,
+    }
+}
+
+/// Sort the colors of a color theme, keeping the order of colors that are equal by the sort.
+///
+/// # Notes
+/// `frequencies` are the counts used by [`Sort::Frequency`], in the order of the colors, and are ignored by the other sorts.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::{Sort, RGB};
+/// # use color_scheme_generator::theme_calculation::adjust::sort;
+/// let white = RGB { red: 255, green: 255, blue: 255 };
+/// let blue = RGB { red: 0, green: 0, blue: 255 };
+/// let green = RGB { red: 0, green: 255, blue: 0 };
+/// let black = RGB { red: 0, green: 0, blue: 0 };
+/// let palette = vec![white.clone(), blue.clone(), green.clone(), black.clone()];
+/// assert_eq!(sort(palette.clone(), Sort::Luminance, &[]), vec![black.clone(), blue.clone(), green.clone(), white.clone()]);
+/// assert_eq!(sort(palette.clone(), Sort::Hue, &[]), vec![white.clone(), black.clone(), green.clone(), blue.clone()]);
+/// assert_eq!(sort(palette.clone(), Sort::Frequency, &[1, 5, 3, 5]), vec![blue, black, green, white]);
+/// assert_eq!(sort(palette.clone(), Sort::None, &[]), palette);
+/// ```
+pub fn sort(colors: Vec<RGB>, sort: Sort, frequencies: &[usize]) -> Vec<RGB> {
+    sorted_order(&colors, sort, frequencies)
+        .into_iter()
+        .map(|i| colors[i].clone())
+        .collect()
+}
+
+/// Indices of the colors in the order of [`sort`].
+fn sorted_order(colors: &[RGB], sort: Sort, frequencies: &[usize]) -> Vec<usize> {
+    let mut colors = colors.iter().enumerate().collect::<Vec<_>>();
+    match sort {
+        Sort::None => {}
+        Sort::Hue => colors.sort_by(|(_, c1), (_, c2)| c1.to_hsl().0.total_cmp(&c2.to_hsl().0)),
+        Sort::Luminance => {
+            colors.sort_by(|(_, c1), (_, c2)| c1.luminance().total_cmp(&c2.luminance()))
+        }
+        Sort::Frequency => colors.sort_by_key(|(i, _)| {
+            std::cmp::Reverse(frequencies.get(*i).copied().unwrap_or_default())
+        }),
+    }
+    colors.into_iter().map(|(i, _)| i).collect()
+}
+
+/// Get the color with every channel inverted.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::adjust::invert;
+/// assert_eq!(invert(&RGB { red: 0, green: 0, blue: 0 }), RGB { red: 255, green: 255, blue: 255 });
+/// assert_eq!(invert(&RGB { red: 222, green: 186, blue: 189 }), RGB { red: 33, green: 69, blue: 66 });
+/// ```
+pub fn invert(color: &RGB) -> RGB {
+    RGB {
+        red: 255 - color.red,
+        green: 255 - color.green,
+        blue: 255 - color.blue,
+    }
+}
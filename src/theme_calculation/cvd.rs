@@ -0,0 +1,108 @@
+#![deny(unused_extern_crates)]
+#![warn(missing_docs)]
+use crate::common::{gamma, linear, Cvd, RGB};
+
+/// Smallest CIE76 distance between two simulated colors for them to be distinguishable.
+pub const MIN_DISTANCE: f64 = 20.0;
+
+/// Change in CIE L* lightness of every step that a color is moved by [`distinguishable`].
+const LIGHTNESS_STEP: f64 = 5.0;
+
+/// Most steps that a color is moved by [`distinguishable`].
+const MAX_STEPS: usize = 20;
+
+/// Simulation matrices of Machado, Oliveira and Fernandes (2009) at full severity, applied to linear sRGB.
+fn matrix(cvd: Cvd) -> [[f64; 3]; 3] {
+    match cvd {
+        Cvd::Protanopia => [
+            [0.152286, 1.052583, -0.204868],
+            [0.114503, 0.786281, 0.099216],
+            [-0.003882, -0.048116, 1.051998],
+        ],
+        Cvd::Deuteranopia => [
+            [0.367322, 0.860646, -0.227968],
+            [0.280085, 0.672501, 0.047413],
+            [-0.011820, 0.042940, 0.968881],
+        ],
+        Cvd::Tritanopia => [
+            [1.255528, -0.076749, -0.178779],
+            [-0.078411, 0.930809, 0.147602],
+            [0.004733, 0.691367, 0.303900],
+        ],
+    }
+}
+
+/// Get the color as it is seen with a color vision deficiency.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::{Cvd, RGB};
+/// # use color_scheme_generator::theme_calculation::cvd::simulate;
+/// let red = simulate(&RGB { red: 255, green: 0, blue: 0 }, Cvd::Protanopia);
+/// let green = simulate(&RGB { red: 0, green: 255, blue: 0 }, Cvd::Protanopia);
+/// assert!(green.red.abs_diff(red.red) < 255);
+/// assert!(green.green.abs_diff(red.green) < 255);
+///
+/// let gray = RGB { red: 128, green: 128, blue: 128 };
+/// assert_eq!(simulate(&gray, Cvd::Deuteranopia), gray);
+/// ```
+pub fn simulate(color: &RGB, cvd: Cvd) -> RGB {
+    let c = [linear(color.red), linear(color.green), linear(color.blue)];
+    let [r, g, b] = matrix(cvd).map(|row| row[0] * c[0] + row[1] * c[1] + row[2] * c[2]);
+    RGB {
+        red: gamma(r),
+        green: gamma(g),
+        blue: gamma(b),
+    }
+}
+
+/// CIE76 distance between two colors as they are seen with a color vision deficiency.
+fn simulated_distance(c1: &RGB, c2: &RGB, cvd: Cvd) -> f64 {
+    simulate(c1, cvd).distance_lab(&simulate(c2, cvd))
+}
+
+/// Move the colors of a color theme apart in lightness so they stay distinguishable with a color vision deficiency.
+///
+/// # Notes
+/// The colors are adjusted in order, the first color is never changed and every other color is moved away
+/// from the nearest earlier color until they are [`MIN_DISTANCE`] apart when simulated, or a limit of steps is reached.
+/// Colors that are already distinguishable are not changed.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::{Cvd, RGB};
+/// # use color_scheme_generator::theme_calculation::cvd::{distinguishable, simulate, MIN_DISTANCE};
+/// let red = RGB { red: 200, green: 40, blue: 40 };
+/// let olive = RGB { red: 120, green: 110, blue: 40 };
+/// let lab_distance = |c1: &RGB, c2: &RGB| simulate(c1, Cvd::Protanopia).distance_lab(&simulate(c2, Cvd::Protanopia));
+/// assert!(lab_distance(&red, &olive) < MIN_DISTANCE);
+///
+/// let safe = distinguishable(&[red.clone(), olive], Cvd::Protanopia);
+/// assert_eq!(safe[0], red);
+/// assert!(lab_distance(&safe[0], &safe[1]) >= MIN_DISTANCE);
+/// ```
+pub fn distinguishable(colors: &[RGB], cvd: Cvd) -> Vec<RGB> {
+    let mut safe: Vec<RGB> = Vec::with_capacity(colors.len());
+    for color in colors {
+        let (mut l, a, b) = color.to_lab();
+        let mut adjusted = color.clone();
+        for _ in 0..MAX_STEPS {
+            let nearest = safe.iter().min_by(|c1, c2| {
+                simulated_distance(c1, &adjusted, cvd)
+                    .total_cmp(&simulated_distance(c2, &adjusted, cvd))
+            });
+            let nearest = match nearest {
+                Some(n) if simulated_distance(n, &adjusted, cvd) < MIN_DISTANCE => n,
+                _ => break,
+            };
+            let lighter = l >= nearest.to_lab().0;
+            l += match (lighter && l + LIGHTNESS_STEP <= 100.0) || l < LIGHTNESS_STEP {
+                true => LIGHTNESS_STEP,
+                false => -LIGHTNESS_STEP,
+            };
+            adjusted = RGB::from_lab(l, a, b);
+        }
+        safe.push(adjusted);
+    }
+    safe
+}
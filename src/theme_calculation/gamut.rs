@@ -0,0 +1,594 @@
+#![deny(unused_extern_crates)]
+#![warn(missing_docs)]
+use crate::common::{ColorThemeOption, Interpolation, RGB};
+use std::collections::VecDeque;
+use std::sync::{LazyLock, Mutex, PoisonError};
+
+/// The most recently derived color themes of [`color_theme`].
+static COLOR_THEMES: LazyLock<ColorThemeCache> = LazyLock::new(ColorThemeCache::default);
+
+/// Most color themes memoized by a [`ColorThemeCache`].
+pub const COLOR_THEME_CACHE_SIZE: usize = 64;
+
+/// Key of a memoized color theme: the options, color1 and color2 if the options use it.
+type ColorThemeKey = (ColorThemeOption, RGB, Option<RGB>);
+
+/// Memoization of the [`COLOR_THEME_CACHE_SIZE`] most recently used color themes, keyed by the colors and [`ColorThemeOption`] they are derived from.
+///
+/// # Notes
+/// Only what a color theme is derived from is part of the key. [`ColorThemeOption::palette_size`] and [`ColorThemeOption::dominant_pair`]
+/// only change which colors the centrality selects, and color2 is only part of the key if [`ColorThemeOption::tetratic`] or
+/// [`ColorThemeOption::blends`] use it. Once the cache is full, the least recently used color theme is forgotten.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::{ColorThemeOption, RGB};
+/// # use color_scheme_generator::theme_calculation::gamut::{triadic, ColorThemeCache, COLOR_THEME_CACHE_SIZE};
+/// # let color_themes = ColorThemeOption {
+/// #   darker: 0,
+/// #   lighter: 0,
+/// #   complementary: false,
+/// #   contrast: false,
+/// #   hue_offset: 0,
+/// #   triadic: true,
+/// #   quadratic: false,
+/// #   tetratic: false,
+/// #   analogous: false,
+/// #   split_complementary: false,
+/// #   monochromatic: 0,
+/// #   shades: 0,
+/// #   tints: 0,
+/// #   tones: 0,
+/// #   blends: 0,
+/// #   interpolation: Default::default(),
+/// #   hue_steps: 0,
+/// #   palette_size: 2,
+/// #   include_seed: false,
+/// #   dominant_pair: false,
+/// # };
+/// let cache = ColorThemeCache::default();
+/// let (red, black) = (RGB { red: 255, green: 0, blue: 0 }, RGB { red: 0, green: 0, blue: 0 });
+/// let mut computations = 0;
+/// for _ in 0..2 {
+///     let colors = cache.get_or_insert_with(&color_themes, &red, &black, || {
+///         computations += 1;
+///         triadic(&red)
+///     });
+///     assert_eq!(colors, triadic(&red));
+/// }
+/// assert_eq!(computations, 1);
+///
+/// // Neither the palette size nor an unused color2 derive another color theme.
+/// let other = ColorThemeOption { palette_size: 4, ..color_themes.clone() };
+/// cache.get_or_insert_with(&other, &red, &red, || unreachable!());
+///
+/// // The least recently used color theme is forgotten once the cache is full.
+/// for hue in 1..=COLOR_THEME_CACHE_SIZE as u8 {
+///     let ct = ColorThemeOption { hue_steps: hue, ..color_themes.clone() };
+///     cache.get_or_insert_with(&ct, &red, &black, || vec![]);
+/// }
+/// cache.get_or_insert_with(&color_themes, &red, &black, || {
+///     computations += 1;
+///     triadic(&red)
+/// });
+/// assert_eq!(computations, 2);
+/// ```
+#[derive(Default)]
+pub struct ColorThemeCache {
+    themes: Mutex<VecDeque<(ColorThemeKey, Vec<RGB>)>>,
+}
+
+impl ColorThemeCache {
+    /// Get the color theme derived from color1, color2 and ct, computing it with derive if it is not memoized yet.
+    pub fn get_or_insert_with<F>(
+        &self,
+        ct: &ColorThemeOption,
+        color1: &RGB,
+        color2: &RGB,
+        derive: F,
+    ) -> Vec<RGB>
+    where
+        F: FnOnce() -> Vec<RGB>,
+    {
+        let key = (
+            ColorThemeOption {
+                palette_size: 0,
+                dominant_pair: false,
+                ..ct.clone()
+            },
+            color1.clone(),
+            (ct.tetratic || ct.blends > 0).then(|| color2.clone()),
+        );
+        let mut themes = self.lock();
+        let hit = themes.iter().position(|(k, _)| *k == key);
+        if let Some(theme) = hit.and_then(|i| themes.remove(i)) {
+            let colors = theme.1.clone();
+            themes.push_front(theme);
+            return colors;
+        }
+        drop(themes);
+        let colors = derive();
+        let mut themes = self.lock();
+        themes.push_front((key, colors.clone()));
+        themes.truncate(COLOR_THEME_CACHE_SIZE);
+        colors
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<(ColorThemeKey, Vec<RGB>)>> {
+        self.themes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Generate the color theme described by a [`ColorThemeOption`] from the colors selected by the centrality.
+///
+/// # Notes
+/// Any combination of [`ColorThemeOption`] fields can be set and the resulting colors are concatenated in the order the fields are declared.
+/// The options that derive a single color (darker, lighter, complementary, contrast and hue_offset) only add the derived color, not color1.
+/// The schemes of the other options start with color1 or are derived entirely from it. color1 is only prepended by [`ColorThemeOption::include_seed`].
+/// color2 is only used by [`ColorThemeOption::tetratic`] and [`ColorThemeOption::blends`] and defaults to black if it is not given.
+/// The most recently used color themes are memoized, so images that select the same colors only derive their color theme once, see [`ColorThemeCache`].
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::{ColorThemeOption, RGB};
+/// # use color_scheme_generator::theme_calculation::gamut::color_theme;
+/// # let color_themes = ColorThemeOption {
+/// #   darker: 0,
+/// #   lighter: 0,
+/// #   complementary: false,
+/// #   contrast: false,
+/// #   hue_offset: 0,
+/// #   triadic: false,
+/// #   quadratic: true,
+/// #   tetratic: false,
+/// #   analogous: false,
+/// #   split_complementary: false,
+/// #   monochromatic: 0,
+/// #   shades: 0,
+/// #   tints: 0,
+/// #   tones: 0,
+/// #   blends: 0,
+/// #   interpolation: Default::default(),
+/// #   hue_steps: 0,
+/// #   palette_size: 2,
+/// #   include_seed: false,
+/// #   dominant_pair: false,
+/// # };
+/// let red = RGB { red: 255, green: 0, blue: 0 };
+/// assert_eq!(color_theme(&color_themes, &red, None).len(), 4);
+/// let complementary = ColorThemeOption { quadratic: false, complementary: true, ..color_themes };
+/// assert_eq!(color_theme(&complementary, &red, None), vec![RGB { red: 0, green: 255, blue: 255 }]);
+/// // The seed is absent without --include-seed.
+/// assert!(!color_theme(&complementary, &red, None).contains(&red));
+/// let seeded = ColorThemeOption { include_seed: true, ..complementary.clone() };
+/// assert_eq!(color_theme(&seeded, &red, None), vec![red.clone(), RGB { red: 0, green: 255, blue: 255 }]);
+/// let combined = ColorThemeOption { quadratic: false, complementary: true, contrast: true, triadic: true, ..color_themes };
+/// assert_eq!(
+///     color_theme(&combined, &red, None),
+///     vec![
+///         RGB { red: 0, green: 255, blue: 255 },
+///         RGB { red: 255, green: 255, blue: 255 },
+///         red.clone(),
+///         RGB { red: 0, green: 255, blue: 0 },
+///         RGB { red: 0, green: 0, blue: 255 },
+///     ]
+/// );
+/// let combined = ColorThemeOption { shades: 2, ..color_themes };
+/// assert_eq!(color_theme(&combined, &red, None).len(), 6);
+/// // The monochromatic colors start with a darker color than the seed.
+/// let monochromatic = ColorThemeOption { quadratic: false, monochromatic: 3, ..color_themes };
+/// assert_ne!(color_theme(&monochromatic, &red, None)[0], red);
+/// let seeded = ColorThemeOption { include_seed: true, ..monochromatic.clone() };
+/// assert_eq!(color_theme(&seeded, &red, None)[0], red);
+/// assert_eq!(color_theme(&seeded, &red, None)[1..], color_theme(&monochromatic, &red, None)[..]);
+/// let seeded = ColorThemeOption { include_seed: true, ..color_themes };
+/// assert_eq!(color_theme(&seeded, &red, None), color_theme(&color_themes, &red, None));
+/// ```
+pub fn color_theme(ct: &ColorThemeOption, color1: &RGB, color2: Option<&RGB>) -> Vec<RGB> {
+    let color2 = match color2 {
+        Some(c) => c,
+        None => &RGB {
+            red: 0,
+            green: 0,
+            blue: 0,
+        },
+    };
+    COLOR_THEMES.get_or_insert_with(ct, color1, color2, || {
+        derive_color_theme(ct, color1, color2)
+    })
+}
+
+fn derive_color_theme(ct: &ColorThemeOption, color1: &RGB, color2: &RGB) -> Vec<RGB> {
+    let mut derived = vec![];
+    if ct.darker > 0 {
+        derived.push(darker(color1, ct.darker));
+    }
+    if ct.lighter > 0 {
+        derived.push(lighter(color1, ct.lighter));
+    }
+    if ct.complementary {
+        derived.push(complementary(color1));
+    }
+    if ct.contrast {
+        derived.push(contrast(color1));
+    }
+    if ct.hue_offset > 0 {
+        derived.push(hue_offset(color1, ct.hue_offset));
+    }
+
+    let mut colors = derived;
+    if ct.triadic {
+        colors.extend(triadic(color1));
+    }
+    if ct.quadratic {
+        colors.extend(quadratic(color1));
+    }
+    if ct.tetratic {
+        colors.extend(tetratic(color1, color2));
+    }
+    if ct.analogous {
+        colors.extend(analogous(color1));
+    }
+    if ct.split_complementary {
+        colors.extend(split_complementary(color1));
+    }
+    if ct.monochromatic > 0 {
+        colors.extend(monochromatic(color1, ct.monochromatic));
+    }
+    if ct.shades > 0 {
+        colors.extend(shades(color1, ct.shades));
+    }
+    if ct.tints > 0 {
+        colors.extend(tints(color1, ct.tints));
+    }
+    if ct.tones > 0 {
+        colors.extend(tones(color1, ct.tones));
+    }
+    if ct.blends > 0 {
+        colors.extend(blends(color1, color2, ct.blends, ct.interpolation));
+    }
+    if ct.hue_steps > 0 {
+        colors.extend(hue_steps(color1, ct.hue_steps));
+    }
+    if ct.include_seed && colors.first() != Some(color1) {
+        colors.insert(0, color1.to_owned());
+    }
+    colors
+}
+
+/// Make a color darker by reducing its lightness by the given percentage.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::gamut::darker;
+/// let white = RGB { red: 255, green: 255, blue: 255 };
+/// assert_eq!(darker(&white, 100), RGB { red: 0, green: 0, blue: 0 });
+/// ```
+pub fn darker(color: &RGB, percent: u8) -> RGB {
+    let (h, s, l) = color.to_hsl();
+    RGB::from_hsl(h, s, l - l * percentage(percent))
+}
+
+/// Make a color lighter by increasing its lightness by the given percentage.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::gamut::lighter;
+/// let black = RGB { red: 0, green: 0, blue: 0 };
+/// assert_eq!(lighter(&black, 100), RGB { red: 255, green: 255, blue: 255 });
+/// ```
+pub fn lighter(color: &RGB, percent: u8) -> RGB {
+    let (h, s, l) = color.to_hsl();
+    RGB::from_hsl(h, s, l + (1.0 - l) * percentage(percent))
+}
+
+/// Get the color opposite to the given color on the color wheel.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::gamut::complementary;
+/// let red = RGB { red: 255, green: 0, blue: 0 };
+/// assert_eq!(complementary(&red), RGB { red: 0, green: 255, blue: 255 });
+/// ```
+pub fn complementary(color: &RGB) -> RGB {
+    hue_offset(color, 180)
+}
+
+/// Get the color with the highest contrast to the given color, which is either black or white.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::gamut::contrast;
+/// let yellow = RGB { red: 255, green: 255, blue: 0 };
+/// assert_eq!(contrast(&yellow), RGB { red: 0, green: 0, blue: 0 });
+/// ```
+pub fn contrast(color: &RGB) -> RGB {
+    let yiq =
+        (u32::from(color.red) * 299 + u32::from(color.green) * 587 + u32::from(color.blue) * 114)
+            / 1000;
+    match yiq >= 128 {
+        true => RGB {
+            red: 0,
+            green: 0,
+            blue: 0,
+        },
+        false => RGB {
+            red: 255,
+            green: 255,
+            blue: 255,
+        },
+    }
+}
+
+/// Minimum WCAG contrast ratio [`text_color`] tries to reach between the text and the bar color.
+pub const MIN_TEXT_CONTRAST: f64 = 4.5;
+
+/// Get a readable text color, either white or black, for text drawn over the bar color.
+///
+/// # Notes
+/// White is preferred if its contrast ratio against the bar color is at least [`MIN_TEXT_CONTRAST`], then black.
+/// If neither reaches it the one with the higher contrast ratio is returned.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::gamut::text_color;
+/// let black = RGB { red: 0, green: 0, blue: 0 };
+/// let white = RGB { red: 255, green: 255, blue: 255 };
+/// assert_eq!(text_color(&black), white);
+/// assert_eq!(text_color(&RGB { red: 119, green: 119, blue: 119 }), black);
+/// ```
+pub fn text_color(bar_color: &RGB) -> RGB {
+    let white = RGB {
+        red: 255,
+        green: 255,
+        blue: 255,
+    };
+    let black = RGB {
+        red: 0,
+        green: 0,
+        blue: 0,
+    };
+    let (white_ratio, black_ratio) = (
+        white.contrast_ratio(bar_color),
+        black.contrast_ratio(bar_color),
+    );
+    if white_ratio >= MIN_TEXT_CONTRAST || white_ratio > black_ratio {
+        white
+    } else {
+        black
+    }
+}
+
+/// Rotate the hue of a color by the given amount of degrees.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::gamut::hue_offset;
+/// let red = RGB { red: 255, green: 0, blue: 0 };
+/// assert_eq!(hue_offset(&red, 120), RGB { red: 0, green: 255, blue: 0 });
+/// ```
+pub fn hue_offset(color: &RGB, degrees: u16) -> RGB {
+    let (h, s, l) = color.to_hsl();
+    RGB::from_hsl(h + f64::from(degrees), s, l)
+}
+
+/// Get three colors equally spaced around the color wheel, starting with the given color.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::gamut::triadic;
+/// let red = RGB { red: 255, green: 0, blue: 0 };
+/// assert_eq!(
+///     triadic(&red),
+///     vec![
+///         RGB { red: 255, green: 0, blue: 0 },
+///         RGB { red: 0, green: 255, blue: 0 },
+///         RGB { red: 0, green: 0, blue: 255 },
+///     ]
+/// );
+/// ```
+pub fn triadic(color: &RGB) -> Vec<RGB> {
+    vec![
+        color.to_owned(),
+        hue_offset(color, 120),
+        hue_offset(color, 240),
+    ]
+}
+
+/// Get count colors equally spaced around the color wheel, starting with the given color.
+///
+/// # Notes
+/// A count of 3 is the same as [`triadic`] and a count of 4 is the same as [`quadratic`].
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::gamut::{hue_steps, quadratic};
+/// let seed = RGB::from_hsl(20.0, 0.8, 0.5);
+/// let colors = hue_steps(&seed, 6);
+/// assert_eq!(colors.len(), 6);
+/// for (i, color) in colors.iter().enumerate() {
+///     let (h, _, _) = color.to_hsl();
+///     let expected = 20.0 + 60.0 * i as f64;
+///     assert!((h - expected).abs() < 1.0, "{h} is not {expected}");
+/// }
+/// let red = RGB { red: 255, green: 0, blue: 0 };
+/// assert_eq!(hue_steps(&red, 4), quadratic(&red));
+/// assert_eq!(hue_steps(&red, 1), vec![red]);
+/// ```
+pub fn hue_steps(color: &RGB, count: u8) -> Vec<RGB> {
+    let (h, s, l) = color.to_hsl();
+    (0..count)
+        .map(|i| match i {
+            0 => color.to_owned(),
+            _ => RGB::from_hsl(h + 360.0 * f64::from(i) / f64::from(count), s, l),
+        })
+        .collect()
+}
+
+/// Get four colors equally spaced around the color wheel, starting with the given color.
+pub fn quadratic(color: &RGB) -> Vec<RGB> {
+    vec![
+        color.to_owned(),
+        hue_offset(color, 90),
+        hue_offset(color, 180),
+        hue_offset(color, 270),
+    ]
+}
+
+/// Get the two given colors followed by their complementary colors.
+pub fn tetratic(color1: &RGB, color2: &RGB) -> Vec<RGB> {
+    vec![
+        color1.to_owned(),
+        color2.to_owned(),
+        complementary(color1),
+        complementary(color2),
+    ]
+}
+
+/// Get the given color followed by the two colors that sit 30 degrees next to it on the color wheel.
+pub fn analogous(color: &RGB) -> Vec<RGB> {
+    vec![
+        color.to_owned(),
+        hue_offset(color, 330),
+        hue_offset(color, 30),
+    ]
+}
+
+/// Get the given color followed by the two colors that sit 30 degrees next to its complement on the color wheel.
+pub fn split_complementary(color: &RGB) -> Vec<RGB> {
+    vec![
+        color.to_owned(),
+        hue_offset(color, 150),
+        hue_offset(color, 210),
+    ]
+}
+
+/// Get count colors with the same hue and saturation as the given color, evenly spaced in lightness.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::gamut::monochromatic;
+/// let red = RGB { red: 255, green: 0, blue: 0 };
+/// assert_eq!(monochromatic(&red, 3)[1], red);
+/// ```
+pub fn monochromatic(color: &RGB, count: u8) -> Vec<RGB> {
+    let (h, s, _) = color.to_hsl();
+    (1..=count)
+        .map(|i| RGB::from_hsl(h, s, f64::from(i) / (f64::from(count) + 1.0)))
+        .collect::<Vec<_>>()
+}
+
+/// Get count colors blended from the given color to black.
+pub fn shades(color: &RGB, count: u8) -> Vec<RGB> {
+    blends(
+        color,
+        &RGB {
+            red: 0,
+            green: 0,
+            blue: 0,
+        },
+        count,
+        Interpolation::Srgb,
+    )
+}
+
+/// Get count colors blended from the given color to white.
+pub fn tints(color: &RGB, count: u8) -> Vec<RGB> {
+    blends(
+        color,
+        &RGB {
+            red: 255,
+            green: 255,
+            blue: 255,
+        },
+        count,
+        Interpolation::Srgb,
+    )
+}
+
+/// Get count colors blended from the given color to gray.
+pub fn tones(color: &RGB, count: u8) -> Vec<RGB> {
+    blends(
+        color,
+        &RGB {
+            red: 128,
+            green: 128,
+            blue: 128,
+        },
+        count,
+        Interpolation::Srgb,
+    )
+}
+
+/// Get count colors interpolated from color1 to color2 in a color space, both ends included.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::{Interpolation, RGB};
+/// # use color_scheme_generator::theme_calculation::gamut::blends;
+/// let black = RGB { red: 0, green: 0, blue: 0 };
+/// let white = RGB { red: 255, green: 255, blue: 255 };
+/// let colors = blends(&black, &white, 3, Interpolation::Srgb);
+/// assert_eq!(colors, vec![black, RGB { red: 128, green: 128, blue: 128 }, white]);
+/// ```
+/// The sRGB midpoint of red and green is a dark olive, the CIELAB midpoint is halfway between their lightness.
+/// ```
+/// # use color_scheme_generator::common::{Interpolation, RGB};
+/// # use color_scheme_generator::theme_calculation::gamut::blends;
+/// let red = RGB { red: 255, green: 0, blue: 0 };
+/// let green = RGB { red: 0, green: 255, blue: 0 };
+/// let srgb = blends(&red, &green, 3, Interpolation::Srgb)[1].clone();
+/// let lab = blends(&red, &green, 3, Interpolation::Lab)[1].clone();
+/// assert_eq!(srgb, RGB { red: 128, green: 128, blue: 0 });
+/// assert_ne!(lab, srgb);
+/// let halfway = (red.to_lab().0 + green.to_lab().0) / 2.0;
+/// assert!((lab.to_lab().0 - halfway).abs() < 1.0);
+/// assert!(srgb.to_lab().0 < halfway - 10.0);
+/// assert_eq!(blends(&red, &green, 3, Interpolation::Hsl)[1], RGB { red: 255, green: 255, blue: 0 });
+/// ```
+pub fn blends(color1: &RGB, color2: &RGB, count: u8, interpolation: Interpolation) -> Vec<RGB> {
+    match count {
+        0 => vec![],
+        1 => vec![color1.to_owned()],
+        _ => (0..count)
+            .map(|i| {
+                let t = f64::from(i) / f64::from(count - 1);
+                match interpolation {
+                    Interpolation::Srgb => RGB {
+                        red: interpolate(color1.red, color2.red, t),
+                        green: interpolate(color1.green, color2.green, t),
+                        blue: interpolate(color1.blue, color2.blue, t),
+                    },
+                    Interpolation::Lab => {
+                        let (l1, a1, b1) = color1.to_lab();
+                        let (l2, a2, b2) = color2.to_lab();
+                        RGB::from_lab(l1 + (l2 - l1) * t, a1 + (a2 - a1) * t, b1 + (b2 - b1) * t)
+                    }
+                    Interpolation::Hsl => {
+                        let (h1, s1, l1) = color1.to_hsl();
+                        let (h2, s2, l2) = color2.to_hsl();
+                        let dh = (h2 - h1 + 540.0) % 360.0 - 180.0;
+                        RGB::from_hsl(h1 + dh * t, s1 + (s2 - s1) * t, l1 + (l2 - l1) * t)
+                    }
+                }
+            })
+            .collect::<Vec<_>>(),
+    }
+}
+
+fn interpolate(from: u8, to: u8, t: f64) -> u8 {
+    (f64::from(from) + (f64::from(to) - f64::from(from)) * t).round() as u8
+}
+
+fn percentage(percent: u8) -> f64 {
+    f64::from(percent.min(100)) / 100.0
+}
@@ -0,0 +1,288 @@
+#![deny(unused_extern_crates)]
+#![warn(missing_docs)]
+use crate::common::{linear, Centrality, Cli, ColorCount, ColorSchemeError, Coverage, RGB};
+use crate::theme_calculation::{median, quantize, Deadline, MODE_QUANTIZATION_BITS};
+use log::warn;
+use std::collections::HashMap;
+
+/// Amount of rows of the image that are converted to RGB at a time.
+pub const CHUNK_ROWS: u32 = 64;
+
+/// Amount of pixels sampled to estimate the median.
+pub const RESERVOIR_SIZE: usize = 1 << 16;
+
+/// Fixed sample of the pixels of an image, where every pixel has the same chance of being sampled.
+struct Reservoir {
+    sample: Vec<image::Rgb<u8>>,
+    seen: u64,
+    state: u64,
+}
+
+impl Reservoir {
+    fn new() -> Reservoir {
+        Reservoir {
+            sample: Vec::with_capacity(RESERVOIR_SIZE),
+            seen: 0,
+            state: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    /// Advance the xorshift generator, which is seeded with a constant so the sample of an image is always the same.
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    fn push(&mut self, pixel: image::Rgb<u8>) {
+        self.seen += 1;
+        if self.sample.len() < RESERVOIR_SIZE {
+            self.sample.push(pixel);
+            return;
+        }
+        let index = self.next() % self.seen;
+        if let Some(sampled) = self.sample.get_mut(index as usize) {
+            *sampled = pixel;
+        }
+    }
+}
+
+/// Running state of a centrality that is updated one pixel at a time.
+enum Accumulator {
+    Average {
+        sum: [u64; 3],
+        count: u64,
+    },
+    LinearAverage {
+        sum: [f64; 3],
+        count: u64,
+    },
+    Median(Reservoir),
+    Prevalent {
+        bits: u8,
+        counts: HashMap<image::Rgb<u8>, usize>,
+    },
+}
+
+impl Accumulator {
+    fn new(args: &Cli) -> Option<Accumulator> {
+        match args.centrality {
+            Centrality::Average if args.gamma => Some(Accumulator::LinearAverage {
+                sum: [0.0; 3],
+                count: 0,
+            }),
+            Centrality::Average => Some(Accumulator::Average {
+                sum: [0; 3],
+                count: 0,
+            }),
+            Centrality::Median => Some(Accumulator::Median(Reservoir::new())),
+            Centrality::Prevalent => Some(Accumulator::Prevalent {
+                bits: 8,
+                counts: HashMap::new(),
+            }),
+            Centrality::Mode => Some(Accumulator::Prevalent {
+                bits: MODE_QUANTIZATION_BITS,
+                counts: HashMap::new(),
+            }),
+            _ => None,
+        }
+    }
+
+    fn push(&mut self, pixel: image::Rgb<u8>) {
+        match self {
+            Accumulator::Average { sum, count } => {
+                for (s, p) in sum.iter_mut().zip(pixel.0) {
+                    *s += u64::from(p);
+                }
+                *count += 1;
+            }
+            Accumulator::LinearAverage { sum, count } => {
+                for (s, p) in sum.iter_mut().zip(pixel.0) {
+                    *s += linear(p);
+                }
+                *count += 1;
+            }
+            Accumulator::Median(reservoir) => reservoir.push(pixel),
+            Accumulator::Prevalent { bits, counts } => {
+                *counts.entry(quantize(&pixel, *bits)).or_insert(0) += 1
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Accumulator::Average { count, .. } | Accumulator::LinearAverage { count, .. } => {
+                *count == 0
+            }
+            Accumulator::Median(reservoir) => reservoir.sample.is_empty(),
+            Accumulator::Prevalent { counts, .. } => counts.is_empty(),
+        }
+    }
+
+    /// Get the selected colors, along with their coverage if the centrality counts pixels.
+    fn finish(self, palette_size: u8) -> (Vec<RGB>, Option<Coverage>) {
+        match self {
+            Accumulator::Average { sum, count } => {
+                let [red, green, blue] = sum.map(|s| (s / count.max(1)).min(255) as u8);
+                (vec![RGB { red, green, blue }], None)
+            }
+            Accumulator::LinearAverage { sum, count } => (
+                vec![RGB::from_linear(sum.map(|s| s / count.max(1) as f64))],
+                None,
+            ),
+            Accumulator::Median(reservoir) => {
+                let channel = |i: usize| {
+                    median(&mut reservoir.sample.iter().map(|p| p.0[i]).collect::<Vec<_>>())
+                };
+                let median = RGB {
+                    red: channel(0),
+                    green: channel(1),
+                    blue: channel(2),
+                };
+                (vec![median], None)
+            }
+            Accumulator::Prevalent { counts, .. } => {
+                let pixels = counts.values().sum();
+                let mut most_prevalent = counts.into_iter().collect::<Vec<_>>();
+                most_prevalent.sort_by_key(|(pixel, count)| (std::cmp::Reverse(*count), pixel.0));
+                let colors = most_prevalent
+                    .into_iter()
+                    .take(usize::from(palette_size))
+                    .map(|(p, count)| ColorCount {
+                        color: RGB {
+                            red: p.0[0],
+                            green: p.0[1],
+                            blue: p.0[2],
+                        },
+                        count,
+                    })
+                    .collect::<Vec<_>>();
+                (
+                    colors.iter().map(|c| c.color.clone()).collect(),
+                    Some(Coverage { colors, pixels }),
+                )
+            }
+        }
+    }
+}
+
+/// Select the colors of the centrality of an image by converting it to RGB in chunks of [`CHUNK_ROWS`] rows,
+/// instead of collecting every pixel of the image into a [`Vec`] first.
+///
+/// # Notes
+/// The image is already decoded and downsampled, so this bounds only the memory used on top of the decoded image,
+/// not the memory used to decode it.
+///
+/// Returns `None` if the centrality cannot be streamed, only [`Centrality::Average`], [`Centrality::Median`],
+/// [`Centrality::Prevalent`] and [`Centrality::Mode`] can.
+///
+/// The average, prevalent and mode colors are the same as when every pixel is collected.
+/// The median is the median of a random sample of [`RESERVOIR_SIZE`] pixels, so it is an estimate for larger images.
+/// The sample is always the same for the same image.
+///
+/// Transparent pixels and pixels outside of the lightness bounds are ignored, and only every [`Cli::sample_rate`]th opaque pixel is analyzed,
+/// as when every pixel is collected.
+///
+/// # Errors
+/// Will error if the image has no pixels or none of its pixels are opaque, and with [`ColorSchemeError::Timeout`]
+/// once [`Cli::timeout`] elapses.
+///
+/// # Examples
+/// ```
+/// # use clap::Parser;
+/// # use color_scheme_generator::common::Cli;
+/// # use color_scheme_generator::theme_calculation::{average_pixel, linear_average_pixel, median_pixel, opaque_pixels, prevalent_pixel};
+/// # use color_scheme_generator::theme_calculation::streaming::streaming_centrality;
+/// let image = image::DynamicImage::ImageRgb8(image::RgbImage::from_fn(400, 300, |x, y| {
+///     image::Rgb([(x % 256) as u8, (y % 200) as u8, ((x * y) % 97) as u8])
+/// }));
+/// let pixels = opaque_pixels(&image, 1);
+/// let streaming = |centrality: &str| {
+///     let cli = Cli::parse_from(["color_scheme_generator", "img.png", "-c", centrality, "--palette-size", "3"]);
+///     streaming_centrality(&image, &cli).unwrap().unwrap()
+/// };
+/// assert_eq!(streaming("average"), vec![average_pixel(&pixels)]);
+/// assert_eq!(streaming("prevalent"), prevalent_pixel(&pixels, 3, 8));
+/// let cli = Cli::parse_from(["color_scheme_generator", "img.png", "-c", "average", "--gamma"]);
+/// assert_eq!(streaming_centrality(&image, &cli).unwrap().unwrap(), vec![linear_average_pixel(&pixels)]);
+/// let cli = Cli::parse_from(["color_scheme_generator", "img.png", "-c", "average", "--sample-rate", "3"]);
+/// let sampled = pixels.iter().step_by(3).copied().collect::<Vec<_>>();
+/// assert_eq!(streaming_centrality(&image, &cli).unwrap().unwrap(), vec![average_pixel(&sampled)]);
+///
+/// let estimate = &streaming("median")[0];
+/// let median = median_pixel(&pixels);
+/// assert!(estimate.red.abs_diff(median.red) <= 4);
+/// assert!(estimate.green.abs_diff(median.green) <= 4);
+/// assert!(estimate.blue.abs_diff(median.blue) <= 4);
+///
+/// let cli = Cli::parse_from(["color_scheme_generator", "img.png", "-c", "kmeans"]);
+/// assert!(streaming_centrality(&image, &cli).unwrap().is_none());
+/// ```
+pub fn streaming_centrality(
+    image: &image::DynamicImage,
+    args: &Cli,
+) -> Result<Option<Vec<RGB>>, ColorSchemeError> {
+    let deadline = Deadline::after(args.timeout);
+    Ok(streaming_selection(image, args, &deadline)?.map(|(colors, _)| colors))
+}
+
+/// Colors selected by a centrality along with their coverage.
+type Selection = (Vec<RGB>, Option<Coverage>);
+
+/// Select the colors of the centrality like [`streaming_centrality`], along with their [`Coverage`] if the centrality counts pixels,
+/// giving up once the deadline passes.
+pub(crate) fn streaming_selection(
+    image: &image::DynamicImage,
+    args: &Cli,
+    deadline: &Deadline,
+) -> Result<Option<Selection>, ColorSchemeError> {
+    let (Some(mut all), Some(mut bounded)) = (Accumulator::new(args), Accumulator::new(args))
+    else {
+        return Ok(None);
+    };
+    let (width, height) = (image.width(), image.height());
+    if width == 0 || height == 0 {
+        return Err(ColorSchemeError::EmptyImage);
+    }
+    let lightness = f64::from(args.min_lightness)..=f64::from(args.max_lightness);
+    let mut opaque = 0_usize;
+    for y in (0..height).step_by(CHUNK_ROWS as usize) {
+        deadline.check()?;
+        let chunk = image.crop_imm(0, y, width, CHUNK_ROWS.min(height - y));
+        for p in chunk.to_rgba8().pixels() {
+            if p.0[3] < args.alpha_threshold {
+                continue;
+            }
+            let sampled = opaque.is_multiple_of(args.sample_rate.max(1) as usize);
+            opaque += 1;
+            if !sampled {
+                continue;
+            }
+            let pixel = image::Rgb([p.0[0], p.0[1], p.0[2]]);
+            all.push(pixel);
+            let (_, _, l) = RGB {
+                red: p.0[0],
+                green: p.0[1],
+                blue: p.0[2],
+            }
+            .to_hsl();
+            if lightness.contains(&(l * 100.0)) {
+                bounded.push(pixel);
+            }
+        }
+    }
+    if all.is_empty() {
+        return Err(ColorSchemeError::NoOpaquePixels);
+    }
+    let accumulator = match bounded.is_empty() {
+        true => {
+            warn!("No pixels are within the lightness bounds. Analyzing every pixel instead.");
+            all
+        }
+        false => bounded,
+    };
+    Ok(Some(
+        accumulator.finish(super::selection_size(&args.color_themes)),
+    ))
+}
@@ -0,0 +1,36 @@
+#![deny(unused_extern_crates)]
+#![warn(missing_docs)]
+use crate::common::RGB;
+
+/// Default width in pixels of the swatch written by `--swatch`.
+pub const SWATCH_WIDTH: u32 = 600;
+
+/// Default height in pixels of the swatch written by `--swatch`.
+pub const SWATCH_HEIGHT: u32 = 100;
+
+/// Render a color theme as a horizontal strip of evenly divided color blocks.
+///
+/// # Notes
+/// When the width is not a multiple of the amount of colors the leftover pixels are spread between the blocks.
+/// An empty color theme renders a black image.
+///
+/// # Examples
+/// ```
+/// # use color_scheme_generator::common::RGB;
+/// # use color_scheme_generator::theme_calculation::swatch::swatch;
+/// let colors = [RGB { red: 255, green: 0, blue: 0 }, RGB { red: 0, green: 0, blue: 255 }];
+/// let path = std::env::temp_dir().join("color_scheme_generator_swatch.png");
+/// swatch(&colors, 60, 10).save(&path).unwrap();
+/// let image = image::open(&path).unwrap().to_rgb8();
+/// assert_eq!(image.get_pixel(0, 0), &image::Rgb([255, 0, 0]));
+/// assert_eq!(image.get_pixel(59, 9), &image::Rgb([0, 0, 255]));
+/// assert_eq!(swatch(&[], 2, 2).get_pixel(1, 1), &image::Rgb([0, 0, 0]));
+/// ```
+pub fn swatch(colors: &[RGB], width: u32, height: u32) -> image::RgbImage {
+    image::RgbImage::from_fn(width, height, |x, _| {
+        match colors.get(x as usize * colors.len() / width as usize) {
+            Some(c) => image::Rgb([c.red, c.green, c.blue]),
+            None => image::Rgb([0, 0, 0]),
+        }
+    })
+}